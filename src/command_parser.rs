@@ -3,8 +3,9 @@ use std::path;
 use clap::Parser;
 use log::{error, info, warn};
 
-use crate::{io_uring::{UringMode, UringSqFillingMode, UringTaskWork}, util::{self, statistic::{MultiplexPort, OutputFormat, Parameter, SimulateConnection, UringParameter}, ExchangeFunction, IOModel, UDPerfMode}};
-use crate::net::{self, socket_options::SocketOptions};
+use crate::{io_uring::{UringMode, UringSqFillingMode, UringTaskWork}, util::{self, statistic::{FlowMode, MultiplexPort, OutputFormat, SimulateConnection, TrafficPattern, DatagramSizeDistribution, PayloadMode}, ExchangeFunction, IOModel, UDPerfMode}};
+use crate::util::parameter_builder::{ParameterBuilder, SocketOptionsBuilder, UringParameterBuilder};
+use crate::net;
 
 #[derive(Parser,Default,Debug)]
 #[clap(version, about="A network performance measurement tool")]
@@ -14,7 +15,7 @@ pub struct udperf {
     #[arg(default_value_t, value_enum)]
     mode: UDPerfMode,
 
-    /// IP address to measure against/listen on
+    /// IP address or hostname to measure against/listen on. IPv6 addresses are supported, listening on :: accepts IPv4 and IPv6
     #[arg(short = 'a',long, default_value_t = String::from("0.0.0.0"))]
     ip: String,
 
@@ -26,15 +27,35 @@ pub struct udperf {
     #[arg(short, long, default_value_t = crate::DEFAULT_SENDER_PORT)]
     pub sender_port: u16,
 
+    /// Negotiate the test parameters and exchange the results over a TCP control connection on the same port number. Has to be enabled on the sender and the receiver.
+    #[arg(long, default_value_t = false)]
+    with_control_channel: bool,
+
+    /// Reverse the direction of the measurement: The remote receiver host sends and the sender host receives. Enables the control channel, which has to be enabled on the receiver.
+    #[arg(long, default_value_t = false)]
+    reverse: bool,
+
+    /// Send and receive at the same time. The remote receiver host sends to the sender host on the ports following the receiver ports. Enables the control channel, which has to be enabled on the receiver.
+    #[arg(long, default_value_t = false)]
+    bidir: bool,
+
+    /// Measure the round-trip time instead of the throughput: The receiver echoes every datagram and the sender waits for the echo before sending the next one(s). Has to be enabled on the receiver, if the control channel isn't used.
+    #[arg(long, default_value_t = false)]
+    ping_pong: bool,
+
     /// Start multiple sender/receiver threads in parallel. The port number is incremented automatically for every thread.
     #[arg(long, default_value_t = 1)]
     parallel: u16,
 
+    /// Amount of sockets driven by every sender/receiver thread with io-model epoll. Every socket uses its own port, following the ports of the previous thread
+    #[arg(long, default_value_t = 1)]
+    sockets_per_thread: u16,
+
     /// Do not finish the execution after the first measurement
     #[arg(short, long, default_value_t = false)]
     pub run_infinite: bool,
 
-    /// Interval printouts of the statistic in seconds (0 to disable). WARNING: Interval statistics are printed at the end of the test, not at the interval!
+    /// Interval printouts of the statistic in seconds (0 to disable). The statistics of all threads are merged and printed as soon as the interval elapsed
     #[arg(short, long, default_value_t = crate::DEFAULT_INTERVAL)]
     interval: f64,
 
@@ -42,6 +63,34 @@ pub struct udperf {
     #[arg(short = 'l', long, default_value_t = crate::DEFAULT_UDP_DATAGRAM_SIZE)]
     datagram_size: u32,
 
+    /// Distribution of the datagram sizes: fixed (--datagram-size), uniform (between --datagram-size-min and --datagram-size), imix (64/576/1500 bytes IP packets in the ratio 7:4:1) or a weighted list read from --datagram-size-file. Can't be used with GSO
+    #[arg(long, default_value_t, value_enum)]
+    datagram_size_distribution: DatagramSizeDistribution,
+
+    /// uniform: Minimum size of the datagrams
+    #[arg(long, default_value_t = crate::DEFAULT_UDP_DATAGRAM_SIZE_MIN)]
+    datagram_size_min: u32,
+
+    /// file: File with the weighted datagram sizes. Every line contains the datagram size in bytes and its weight (e.g. "1472 1")
+    #[arg(long)]
+    datagram_size_file: Option<path::PathBuf>,
+
+    /// Content of the payload after the message header: zeros, a repeating pattern of ASCII characters, pseudo random bytes generated from --payload-seed or the content of --payload-file
+    #[arg(long, default_value_t, value_enum)]
+    payload_mode: PayloadMode,
+
+    /// random: Seed of the pseudo random payload. Sender and receiver need the same seed to verify the payload
+    #[arg(long, default_value_t = crate::DEFAULT_PAYLOAD_SEED)]
+    payload_seed: u64,
+
+    /// file: File which content is repeated to fill the payload
+    #[arg(long)]
+    payload_file: Option<path::PathBuf>,
+
+    /// Receiver verifies the payload of every datagram and counts the corrupted datagrams. Without the control channel, the payload parameters of both sides must match
+    #[arg(long, default_value_t = false)]
+    with_payload_verification: bool,
+
     /// Amount of seconds to run the test for
     #[arg(short = 't', long, default_value_t = crate::DEFAULT_DURATION)]
     time: u64,
@@ -62,6 +111,30 @@ pub struct udperf {
     #[arg(long, default_value_t = crate::DEFAULT_BANDWIDTH)]
     bandwidth: u64,
 
+    /// Send bandwidth in total (not per thread) in datagrams per second (0 for disabled). Enforced by the userspace pacer
+    #[arg(long, default_value_t = 0)]
+    bandwidth_pps: u64,
+
+    /// Enforce the bandwidth with a token bucket in udperf, which is shared by all sender threads, instead of kernel pacing. Doesn't require the fq qdisc
+    #[arg(long, default_value_t = false)]
+    with_userspace_pacing: bool,
+
+    /// Arrival process of the datagrams: constant bit rate, on/off bursts, Poisson inter-departure times with the bandwidth as mean rate or a bandwidth ramp read from --ramp-file. Every pattern except cbr uses the userspace pacer
+    #[arg(long, default_value_t, value_enum)]
+    traffic_pattern: TrafficPattern,
+
+    /// on-off: Length of a burst in milliseconds. The burst is sent with the bandwidth or as fast as possible, if no bandwidth is set
+    #[arg(long, default_value_t = crate::DEFAULT_BURST_LENGTH)]
+    burst_length: u64,
+
+    /// on-off: Length of the pause between two bursts in milliseconds
+    #[arg(long, default_value_t = crate::DEFAULT_BURST_PAUSE)]
+    burst_pause: u64,
+
+    /// ramp: File with the bandwidth steps. Every line contains the start of the step in seconds and the bandwidth in Mbit/s (e.g. "5 200")
+    #[arg(long)]
+    ramp_file: Option<path::PathBuf>,
+
     /// Set GSO buffer size which overwrites the MSS by default if GSO/GRO is enabled
     #[arg(long, default_value_t = crate::DEFAULT_GSO_BUFFER_SIZE)]
     with_gso_buffer: u32,
@@ -74,6 +147,30 @@ pub struct udperf {
     #[arg(long, default_value_t = false)]
     with_ip_frag: bool,
 
+    /// Set the hop limit (IPv6) or TTL (IPv4) of outgoing packets
+    #[arg(long)]
+    with_hop_limit: Option<u32>,
+
+    /// Set the IPv6 flow label (20 bit) of outgoing packets
+    #[arg(long)]
+    with_flow_label: Option<u32>,
+
+    /// Enable kernel software receive timestamps (SO_TIMESTAMPING) on the receiver and report the latency from the kernel receiving a datagram until udperf processes it. Not available with exchange function normal
+    #[arg(long, default_value_t = false)]
+    with_rx_timestamps: bool,
+
+    /// Enable kernel software transmit timestamps (SO_TIMESTAMPING) on the sender and report the delay from the send call until the datagram enters the qdisc and from the qdisc until it is passed to the driver
+    #[arg(long, default_value_t = false)]
+    with_tx_timestamps: bool,
+
+    /// Stamp every datagram with the transmit time of the sender and report the one-way delay (per flow) and the jitter on the receiver. The one-way delay requires synchronized clocks on both hosts
+    #[arg(long, default_value_t = false)]
+    with_one_way_delay: bool,
+
+    /// Count the datagrams dropped because the receive queue of a receiver socket was full (SO_RXQ_OVFL) and sample the peak receive memory of the sockets (SK_MEMINFO via sock_diag)
+    #[arg(long, default_value_t = false)]
+    with_socket_drops: bool,
+
     /// Disable non-blocking socket
     #[arg(long, default_value_t = false)]
     without_non_blocking: bool,
@@ -94,7 +191,7 @@ pub struct udperf {
     #[arg(long, default_value_t, value_enum)]
     io_model: IOModel,
 
-    /// Define the type the output 
+    /// Define the type the output. JSON lines and InfluxDB line protocol are streamed to stdout, the Prometheus exposition is written to the output file path with the extension .prom
     #[arg(long, default_value_t, value_enum)]
     output_format: OutputFormat,
 
@@ -102,6 +199,14 @@ pub struct udperf {
     #[arg(long, default_value = crate::DEFAULT_FILE_NAME)]
     output_file_path: path::PathBuf,
 
+    /// Output the statistic of every thread (with its core ID, port and test IDs) besides the total statistic, and summarize the fairness between the threads
+    #[arg(long, default_value_t = false)]
+    with_thread_statistics: bool,
+
+    /// Measure every thread with perf_event_open counters (cycles, instructions, cache misses, context switches, page faults) and report the costs per datagram and per syscall. Falls back to software counters without a hardware PMU
+    #[arg(long, default_value_t = false)]
+    with_perf_counters: bool,
+
     /// Test label which appears in the output file, if multiple tests are run in parallel. Useful for benchmark automation.
     #[arg(long, default_value_t = String::from("udperf-test"))]
     label_test: String,
@@ -122,9 +227,25 @@ pub struct udperf {
     #[arg(long, default_value_t, value_enum)]
     multiplex_port_receiver: MultiplexPort,
 
-    /// CURRENTLY IGNORED. Simulate a single QUIC connection or one QUIC connection per thread.
+    /// Simulate a single QUIC connection (all threads use the same test IDs) or one QUIC connection per thread. Defaults to single, if the receiver threads share a port, otherwise to multiple
+    #[arg(long, value_enum)]
+    simulate_connection: Option<SimulateConnection>,
+
+    /// Amount of flows every sender thread simulates. Every flow has its own test ID and sequence of packet IDs, the receiver reports the statistics per flow
+    #[arg(long, default_value_t = 1)]
+    flows_per_thread: u16,
+
+    /// How the flows are distinguished: connection-id (one socket, the flows are interleaved in the datagrams of every sendmmsg call) or four-tuple (one socket with its own source port per flow)
     #[arg(long, default_value_t, value_enum)]
-    simulate_connection: SimulateConnection,
+    flow_mode: FlowMode,
+
+    /// epoll: Register the sockets edge-triggered (EPOLLET)
+    #[arg(long, default_value_t = false)]
+    epoll_edge_triggered: bool,
+
+    /// epoll: Register the sockets with EPOLLEXCLUSIVE, so only one thread is woken up if the threads share a socket
+    #[arg(long, default_value_t = false)]
+    epoll_exclusive: bool,
 
     /// io_uring: Which mode to use
     #[arg(long, default_value_t, value_enum)]
@@ -160,7 +281,7 @@ pub struct udperf {
 
     /// Show help in markdown format
     #[arg(long, hide = true)]
-    markdown_help: bool,
+    pub markdown_help: bool,
 }
 
 impl udperf {
@@ -182,227 +303,160 @@ impl udperf {
             return None;
         }
     
-        let ipv4 = match net::parse_ipv4(&self.ip) {
+        let ip = match net::parse_ip(&self.ip) {
             Ok(x) => x,
-            Err(_) => { error!("Invalid IPv4 address!"); return None; },
-        };
-    
-        let packet_buffer_size = match self.exchange_function {
-            ExchangeFunction::Mmsg => self.with_mmsg_amount,
-            _ => 1,
-        };
-
-        let mss = if self.with_gsro {
-            info!("GSO/GRO enabled with buffer size {}", self.with_gso_buffer);
-            self.with_gso_buffer
-        } else {
-            self.with_mss
+            Err(x) => { error!("{} {}", x, self.ip); return None; },
         };
 
-        let simulate_connection = match self.multiplex_port_receiver {
-            MultiplexPort::Sharing => SimulateConnection::Single,
-            _ => SimulateConnection::Multiple
-        };
-
-        info!("Simulate connection: {:?}", simulate_connection);
+        info!("Simulate connection: {:?}", self.simulate_connection.unwrap_or(if self.multiplex_port_receiver == MultiplexPort::Sharing { SimulateConnection::Single } else { SimulateConnection::Multiple }));
         info!("Exchange function used: {:?}", self.exchange_function);
-        info!("MSS used: {}", mss);
+        info!("MSS used: {}", if self.with_gsro && !self.ping_pong { self.with_gso_buffer } else { self.with_mss });
         info!("IO model used: {:?}", self.io_model);
         info!("UDP datagram size used: {}", self.datagram_size);
 
-        let socket_options = self.parse_socket_options(self.mode);
-
-        let uring_parameters = UringParameter {
-            uring_mode: self.uring_mode,
-            ring_size: self.uring_ring_size,
-            burst_size: if self.uring_burst_size == crate::DEFAULT_URING_RING_SIZE / crate::URING_BURST_SIZE_DIVIDEND { (self.uring_ring_size as f32 / crate::URING_BURST_SIZE_DIVIDEND as f32).ceil() as u32 } else { self.uring_burst_size } ,
-            buffer_size: self.uring_ring_size * crate::URING_BUFFER_SIZE_MULTIPLICATOR,
-            sqpoll: self.uring_sqpoll,
-            sqpoll_shared: self.uring_sqpoll_shared,
-            sq_filling_mode: self.uring_sq_mode,
-            task_work: self.uring_task_work,
-            record_utilization: self.uring_record_utilization
-        };
+        let mut socket_options = SocketOptionsBuilder::new()
+            .nonblocking(!self.without_non_blocking)
+            .ip_fragmentation(self.with_ip_frag)
+            .rx_timestamps(self.with_rx_timestamps)
+            .tx_timestamps(self.with_tx_timestamps)
+            .rxq_overflow(self.with_socket_drops);
+        if self.with_socket_buffer != 1.0 {
+            socket_options = socket_options.socket_buffer_size((crate::DEFAULT_SOCKET_BUFFER_SIZE as f32 * self.with_socket_buffer).round() as u32);
+        }
+        if let Some(hop_limit) = self.with_hop_limit {
+            socket_options = socket_options.hop_limit(hop_limit);
+        }
+        if let Some(flow_label) = self.with_flow_label {
+            socket_options = socket_options.flow_label(flow_label);
+        }
 
-        let parameter = util::statistic::Parameter::new(
-            self.label_test.clone(),
-            self.label_run.clone(),
-            self.repetition_id,
-            self.mode,
-            ipv4, 
-            self.parallel,
-            self.interval,
-            self.output_format, 
-            self.output_file_path.clone(),
-            self.io_model, 
-            self.time, 
-            mss, 
-            self.datagram_size, 
-            packet_buffer_size, 
-            socket_options, 
-            self.exchange_function,
-            self.multiplex_port,
-            self.multiplex_port_receiver,
-            simulate_connection,
-            self.with_core_affinity,
-            self.with_numa_affinity,
-            uring_parameters
-        );
-
-        self.parameter_check(parameter) 
-    }
+        let mut uring_parameter = UringParameterBuilder::new()
+            .uring_mode(self.uring_mode)
+            .ring_size(self.uring_ring_size)
+            .sqpoll(self.uring_sqpoll)
+            .sqpoll_shared(self.uring_sqpoll_shared)
+            .sq_filling_mode(self.uring_sq_mode)
+            .task_work(self.uring_task_work)
+            .record_utilization(self.uring_record_utilization);
+        if self.uring_burst_size != crate::DEFAULT_URING_RING_SIZE / crate::URING_BURST_SIZE_DIVIDEND {
+            uring_parameter = uring_parameter.burst_size(self.uring_burst_size);
+        }
 
-    fn parameter_check(&self, mut parameter: util::statistic::Parameter)-> Option<Parameter> {
-        if parameter.datagram_size > crate::MAX_UDP_DATAGRAM_SIZE {
-            error!("UDP datagram size is too big! Maximum is {}", crate::MAX_UDP_DATAGRAM_SIZE);
-            return None;
+        let mut builder = ParameterBuilder::new(self.mode)
+            .ip(ip)
+            .port(self.port)
+            .sender_port(self.sender_port)
+            .parallel(self.parallel)
+            .sockets_per_thread(self.sockets_per_thread)
+            .flows(self.flows_per_thread, self.flow_mode)
+            .interval(self.interval)
+            .time(self.time)
+            .output_format(self.output_format)
+            .thread_statistics(self.with_thread_statistics)
+            .perf_counters(self.with_perf_counters)
+            .labels(&self.label_test, &self.label_run, self.repetition_id)
+            .io_model(self.io_model)
+            .epoll(self.epoll_edge_triggered, self.epoll_exclusive)
+            .mss(self.with_mss)
+            .datagram_size(self.datagram_size)
+            .datagram_size_distribution(self.datagram_size_distribution, self.datagram_size_min)
+            .payload(self.payload_mode, self.payload_seed)
+            .payload_verification(self.with_payload_verification)
+            .gsro(self.with_gsro, self.with_gso_buffer)
+            .bandwidth(self.bandwidth)
+            .bandwidth_pps(self.bandwidth_pps)
+            .userspace_pacing(self.with_userspace_pacing)
+            .traffic_pattern(self.traffic_pattern, self.burst_length, self.burst_pause)
+            .exchange_function(self.exchange_function)
+            .mmsg_amount(self.with_mmsg_amount)
+            .multiplex_port(self.multiplex_port, self.multiplex_port_receiver)
+            .core_affinity(self.with_core_affinity)
+            .numa_affinity(self.with_numa_affinity)
+            .control_channel(self.with_control_channel)
+            .reverse(self.reverse)
+            .bidirectional(self.bidir)
+            .ping_pong(self.ping_pong)
+            .one_way_delay(self.with_one_way_delay)
+            .socket_options(socket_options)
+            .uring_parameter(uring_parameter);
+        if self.output_file_path != path::PathBuf::from(crate::DEFAULT_FILE_NAME) {
+            builder = builder.output_file_path(self.output_file_path.clone());
+        }
+        if let Some(datagram_size_file) = &self.datagram_size_file {
+            builder = builder.datagram_size_file_path(datagram_size_file.clone());
+        }
+        if let Some(payload_file) = &self.payload_file {
+            builder = builder.payload_file_path(payload_file.clone());
         }
+        if let Some(ramp_file) = &self.ramp_file {
+            builder = builder.ramp_file_path(ramp_file.clone());
+        }
+        if let Some(simulate_connection) = self.simulate_connection {
+            builder = builder.simulate_connection(simulate_connection);
+        }
+
+        self.parameter_check();
 
-        if parameter.mode == util::UDPerfMode::Sender && self.multiplex_port_receiver == MultiplexPort::Sharding && (self.multiplex_port == MultiplexPort::Sharing || self.multiplex_port == MultiplexPort::Sharding ) {
+        match builder.build() {
+            Ok(parameter) => Some(parameter),
+            Err(x) => {
+                error!("{}", x);
+                None
+            }
+        }
+    }
+
+    // Warnings about parameters, which are ignored or need additional setup. Invalid parameters are rejected by the ParameterBuilder.
+    fn parameter_check(&self) {
+        if self.mode == UDPerfMode::Sender && self.multiplex_port_receiver == MultiplexPort::Sharding && (self.multiplex_port == MultiplexPort::Sharing || self.multiplex_port == MultiplexPort::Sharding ) {
             warn!("Sharding on receiver side doesn't work, if sender side is set to sharing or sharding (uses one port), since all traffic would be balanced to one thread (see man for SO_REUSEPORT)!");
         }
 
-        if parameter.mode == util::UDPerfMode::Receiver && self.multiplex_port != MultiplexPort::Individual {
+        if self.mode == UDPerfMode::Receiver && self.multiplex_port != MultiplexPort::Individual {
             warn!("Can't set sender multiplexing on receiver side!");
         }
 
         let cores_amount = core_affinity::get_core_ids().unwrap_or_default().len();
-        if parameter.amount_threads > cores_amount as u16 {
+        if self.parallel > cores_amount as u16 {
             warn!("Amount of threads is bigger than available cores! Multiple threads are going to run on the same core! Available cores: {}", cores_amount);
-        } else if parameter.amount_threads * 2 > cores_amount as u16 {
+        } else if self.parallel * 2 > cores_amount as u16 {
             warn!("If receiver/sender is running on the same machine, with the same amount of threads, multiple threads are going to run on the same core! Available cores: {}", cores_amount);
         }
 
-        if parameter.mode == util::UDPerfMode::Receiver && self.time != crate::DEFAULT_DURATION {
+        if self.mode == UDPerfMode::Receiver && self.time != crate::DEFAULT_DURATION {
             warn!("Time is ignored in receiver mode!");
         }
 
-        if parameter.io_model != IOModel::IoUring && (self.uring_mode != UringMode::Normal || self.uring_ring_size != crate::DEFAULT_URING_RING_SIZE) {
+        if self.io_model != IOModel::IoUring && (self.uring_mode != UringMode::Normal || self.uring_ring_size != crate::DEFAULT_URING_RING_SIZE) {
             warn!("Uring specific parameters are only used with io-model io_uring enabled!");
         }
 
-        if !self.uring_ring_size.is_power_of_two() {
-            error!("Uring ring size must be a power of 2!");
-            return None;
+        if self.io_model != IOModel::Epoll && (self.epoll_edge_triggered || self.epoll_exclusive) {
+            warn!("Epoll specific parameters are only used with io-model epoll enabled!");
         }
 
-        if parameter.uring_parameter.burst_size > self.uring_ring_size {
-            error!("Uring burst size {} must be smaller than the ring size {}!", parameter.uring_parameter.burst_size, self.uring_ring_size);
-            return None;
+        if self.epoll_exclusive && self.multiplex_port_receiver != MultiplexPort::Sharing && self.multiplex_port != MultiplexPort::Sharing {
+            warn!("EPOLLEXCLUSIVE only has an effect, if the threads share a socket!");
         }
 
-        if self.uring_ring_size > crate::URING_MAX_RING_SIZE {
-            error!("Uring ring size is too big! Maximum is {}", crate::URING_MAX_RING_SIZE);
-            return None;
+        if self.with_rx_timestamps && self.exchange_function == ExchangeFunction::Normal && self.io_model != IOModel::IoUring {
+            warn!("Receive timestamps are delivered as control messages, which are not available with exchange function normal!");
         }
 
-        if self.io_model == IOModel::IoUring && self.uring_mode == UringMode::Zerocopy && parameter.mode != util::UDPerfMode::Sender {
-            warn!("Zero copy is only available with io_uring on the sender!");
-            return None;
-        }
-
-        if self.interval > 0.0 && (self.interval * (self.time as f64 / self.interval).round() - self.time as f64).abs() > 1e-9  {
-            error!("Interval doesn't fit perfect in the time!");
-            return None;
+        if self.with_socket_drops && self.multiplex_port_receiver == MultiplexPort::Sharing {
+            warn!("The drops and memory of a socket shared by all threads are reported by every thread!");
         }
 
-        if self.interval > 0.0 && self.time == 0 && self.mode == UDPerfMode::Receiver {
-            error!("Interval is set but time is 0! Time must be set when interval output is enabled!");
-            return None;
+        if self.with_tx_timestamps && self.multiplex_port == MultiplexPort::Sharing {
+            warn!("Transmit timestamps can't be assigned to the send calls, if all threads share one socket!");
         }
 
-        if Self::has_more_than_one_decimal(self.interval) {
-            error!("Interval has more than one decimal place! Only tenth of a second is allowed!");
-            return None;
-        }
-
-        if self.bandwidth > 0 {
-            // Check if bandwidth would overflow
+        if self.bandwidth > 0 || self.bandwidth_pps > 0 {
             if self.mode == UDPerfMode::Receiver {
                 warn!("Bandwidth limitation is only available on the sender side! Parameter is ignored");
-                parameter.socket_options.socket_pacing_rate = 0;
-            } else if self.bandwidth as u128 / 8 / 1000 / 1000 >= u64::MAX.into() {
-                error!("Socket pacing rate is too big! Maximum is {} Mbit/s", u64::MAX / 1000 / 1000 * 8);
-                return None;
-            } else {
-                warn!("For bandwidth limitation to work, you need to enable fair queue packet scheduler on the network interface with: tc qdisc add dev $INTERFACE root fq")
+            } else if !self.with_userspace_pacing && self.bandwidth_pps == 0 && self.traffic_pattern == TrafficPattern::Cbr {
+                warn!("For bandwidth limitation to work, you need to enable fair queue packet scheduler on the network interface with: tc qdisc add dev $INTERFACE root fq. Alternatively use --with-userspace-pacing")
             }
         }
-
-        if self.io_model == IOModel::IoUring && self.uring_mode == UringMode::Normal || self.uring_mode == UringMode::Zerocopy {
-            warn!("Setting packet_buffer_size to {}!", parameter.uring_parameter.buffer_size);
-            parameter.packet_buffer_size = parameter.uring_parameter.buffer_size as usize;
-        }
-
-        if self.uring_sqpoll_shared && !self.uring_sqpoll {
-            warn!("Uring sqpoll_shared can't be used without sqpoll!");
-            warn!("Setting sqpoll to true!");
-            parameter.uring_parameter.sqpoll = true;
-        }
-
-        if parameter.uring_parameter.sqpoll && parameter.uring_parameter.task_work != UringTaskWork::Default {
-            warn!("Neither DEFER nor COOP can be used with SQ_POLL! Setting task_work to Default!");
-            parameter.uring_parameter.task_work = UringTaskWork::Default;
-        }
-
-        if parameter.output_file_path != path::PathBuf::from(crate::DEFAULT_FILE_NAME) {
-            parameter.output_format = OutputFormat::File;
-        }
-
-        Some(parameter)
-    }
-
-    fn has_more_than_one_decimal(n: f64) -> bool {
-        let s = format!("{}", n);
-        let parts: Vec<&str> = s.split('.').collect();
-        if parts.get(1).is_none() {
-            return false;
-        }
-        parts[1].len() > 1
-    }
-
-
-    fn parse_socket_options(&self, mode: UDPerfMode) -> SocketOptions {
-        let gso = if self.with_gsro && mode == util::UDPerfMode::Sender {
-            Some(self.datagram_size)
-        } else {
-            None
-        };
-
-        let (recv_buffer_size, send_buffer_size) = if self.with_socket_buffer == 1.0 { 
-            (None,None) 
-        } else {
-            (Some((crate::DEFAULT_SOCKET_BUFFER_SIZE as f32 * self.with_socket_buffer).round() as u32 ), Some((crate::DEFAULT_SOCKET_BUFFER_SIZE as f32 * self.with_socket_buffer).round() as u32))
-        };
-
-        if recv_buffer_size.is_some() && send_buffer_size.is_some() {
-            info!("Setting udp buffer sizes with recv {} and send {}", recv_buffer_size.unwrap(), send_buffer_size.unwrap());
-        }
-
-        let gro = mode == util::UDPerfMode::Receiver && self.with_gsro;
-
-        let reuseport = match mode {
-            UDPerfMode::Sender => self.multiplex_port == MultiplexPort::Sharding,
-            UDPerfMode::Receiver => self.multiplex_port_receiver == MultiplexPort::Sharding,
-        };
-
-        // Convert Mbit/s total to byte/s per thread
-        let bandwidth_per_thread = if self.multiplex_port != MultiplexPort::Sharing {
-            self.bandwidth / self.parallel as u64
-        } else {
-            self.bandwidth
-        } / 8 * 1000 * 1000;
-        info!("Bandwidth per thread: {} Bytes/s", bandwidth_per_thread);
-        
-        SocketOptions::new(
-            !self.without_non_blocking, 
-            self.with_ip_frag, 
-            reuseport,
-            gso, 
-            gro, 
-            bandwidth_per_thread,
-            recv_buffer_size, 
-            send_buffer_size
-        )
     }
 }
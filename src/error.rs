@@ -0,0 +1,66 @@
+use std::{fmt, io};
+
+// Errors of udperf. Errors of system calls carry the errno, so the reason of a failed measurement can be evaluated (e.g. ENOBUFS vs ECONNREFUSED).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UdperfError {
+    // The operation would block on a non-blocking socket (EAGAIN/EWOULDBLOCK)
+    WouldBlock,
+    // Waiting for an event returned due to the timeout
+    Timeout,
+    // The INIT message of a measurement was received by the receiver
+    InitMessageReceived,
+    // The LAST message of a measurement was received by the receiver
+    LastMessageReceived,
+    // A system call failed with the errno
+    Os { context: &'static str, errno: i32 },
+    // The parameters of the measurement are invalid
+    InvalidParameter(&'static str),
+    Other(&'static str),
+}
+
+impl UdperfError {
+    // Creates the error from the errno of the last failed system call
+    pub fn last_os_error(context: &'static str) -> UdperfError {
+        Self::from_io_error(context, &io::Error::last_os_error())
+    }
+
+    pub fn from_errno(context: &'static str, errno: i32) -> UdperfError {
+        if errno == libc::EAGAIN {
+            UdperfError::WouldBlock
+        } else {
+            UdperfError::Os { context, errno }
+        }
+    }
+
+    pub fn from_io_error(context: &'static str, error: &io::Error) -> UdperfError {
+        match error.raw_os_error() {
+            Some(errno) => Self::from_errno(context, errno),
+            None if error.kind() == io::ErrorKind::TimedOut || error.kind() == io::ErrorKind::WouldBlock => UdperfError::Timeout,
+            None => UdperfError::Other(context)
+        }
+    }
+
+    pub fn errno(&self) -> Option<i32> {
+        match self {
+            UdperfError::WouldBlock => Some(libc::EAGAIN),
+            UdperfError::Os { errno, .. } => Some(*errno),
+            _ => None
+        }
+    }
+}
+
+impl fmt::Display for UdperfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UdperfError::WouldBlock => write!(f, "Operation would block (EAGAIN)"),
+            UdperfError::Timeout => write!(f, "Timeout"),
+            UdperfError::InitMessageReceived => write!(f, "INIT message received"),
+            UdperfError::LastMessageReceived => write!(f, "LAST message received"),
+            UdperfError::Os { context, errno } => write!(f, "{}: {}", context, io::Error::from_raw_os_error(*errno)),
+            UdperfError::InvalidParameter(context) => write!(f, "Invalid parameter: {}", context),
+            UdperfError::Other(context) => write!(f, "{}", context),
+        }
+    }
+}
+
+impl std::error::Error for UdperfError {}
@@ -3,170 +3,442 @@ use log::{debug, error, info, warn};
 use crate::command_parser::udperf;
 use crate::io_uring::normal::IoUringNormal;
 use crate::io_uring::IoUringOperatingModes;
+use crate::net::{self, control::{ControlChannel, ControlMessage}};
 use crate::net::socket::Socket;
 use crate::node::{sender::Sender, receiver::Receiver, Node};
 use crate::util::core_affinity_manager::CoreAffinityManager;
-use crate::util::{statistic::{MultiplexPort, Parameter, SimulateConnection}, UDPerfMode};
+use crate::util::cpu_util::{CpuUtil, SystemCpuUtil};
+use crate::util::udp_counters::UdpCounterSnapshot;
+use crate::util::perf_counters::PerfCounters;
+use crate::util::interval_reporter::IntervalReporter;
+use crate::util::pacer::Pacer;
+use crate::util::{exporter, statistic::{self, MultiplexPort, OutputFormat, Parameter, SimulateConnection}, UDPerfMode};
 use crate::Statistic;
 
 use std::os::fd::RawFd;
-use std::sync::{Arc, Mutex};
-use std::{net::SocketAddrV4, thread};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::{net::SocketAddr, thread};
+use crate::UdperfError;
 extern crate core_affinity;
 
+// Statistics of a single measurement
+#[derive(Debug, Clone)]
+pub struct RunReport {
+    // Total statistic of every direction. Only the bidirectional mode has two directions, the first one is the local mode.
+    pub statistics: Vec<Statistic>,
+    // Interval statistics merged over all threads, in the order they were reported
+    pub intervals: Vec<Statistic>,
+}
+
+impl RunReport {
+    pub fn total(&self) -> Option<&Statistic> {
+        self.statistics.first()
+    }
+}
+
 impl udperf {
     pub fn exec(self, parameter: Parameter) -> Option<Statistic> {
         info!("Starting udperf...");
-        debug!("Running with Parameter: {:?}", parameter);
 
-        let core_affinity_manager = Arc::new(Mutex::new(CoreAffinityManager::new(parameter.mode, None, parameter.numa_affinity)));
+        loop {
+            let report = match run(parameter.clone()) {
+                Ok(report) => report,
+                Err(x) => {
+                    error!("Error running measurement: {}", x);
+                    return None;
+                }
+            };
 
-        if parameter.core_affinity {
-            core_affinity_manager.lock().unwrap().bind_to_core(0).expect("Error setting affinity");
+            if !(self.run_infinite && parameter.mode == UDPerfMode::Receiver) {
+                return report.statistics.into_iter().next();
+            }
         }
+    }
+}
 
-        loop {
-            #[allow(clippy::type_complexity)]
-            let mut fetch_handle: Vec<thread::JoinHandle<Result<(Statistic, Vec<Statistic>), &str>>> = Vec::new();
-    
-            // If socket sharing enabled, creating the socket and bind to port/connect must happen before the threads are spawned
-            let socket = self.create_socket(&parameter);
+// Runs a single measurement and returns the total and interval statistics. The results are printed in the output format of the parameters.
+pub fn run(parameter: Parameter) -> Result<RunReport, UdperfError> {
+    debug!("Running with Parameter: {:?}", parameter);
+
+    if parameter.output_format == OutputFormat::Prometheus {
+        exporter::reset_prometheus_series(&parameter.output_file_path);
+    }
+
+    let core_affinity_manager = Arc::new(Mutex::new(CoreAffinityManager::new(parameter.mode, None, parameter.numa_affinity)));
+
+    if parameter.core_affinity {
+        core_affinity_manager.lock().unwrap().bind_to_core(0).expect("Error setting affinity");
+    }
+
+    #[allow(clippy::type_complexity)]
+    let mut fetch_handle: Vec<Vec<thread::JoinHandle<Result<(Statistic, Vec<Statistic>), UdperfError>>>> = Vec::new();
+
+    // The parameters can be changed by the control channel for a single measurement
+    let mut parameter = parameter;
+    // Mode of the control channel, which stays the same in reverse mode (sender connects, receiver accepts)
+    let control_mode = parameter.mode;
+
+    // The control channel has to be set up before the threads are spawned, since the receiver takes over the parameters of the sender
+    let mut control_channel = if parameter.control_channel {
+        match setup_control_channel(&mut parameter) {
+            Ok(control_channel) => Some(control_channel),
+            Err(x) => {
+                error!("Error setting up control channel: {}", x);
+                return Err(x);
+            }
+        }
+    } else {
+        None
+    };
+    let stop_measurement = Arc::new(AtomicBool::new(false));
+    let (ready_tx, ready_rx) = mpsc::channel();
 
-            // If SQ_POLL and io_uring enabled, create io_uring fd here
-            let io_uring: Option<IoUringNormal> = if parameter.uring_parameter.sqpoll_shared {
-                IoUringNormal::new(parameter.clone(), None).ok()
+    // Every direction of the measurement runs its own threads. Only the bidirectional mode has two directions.
+    let directions = directions(&parameter);
+
+    // If socket sharing enabled, creating the socket and bind to port/connect must happen before the threads are spawned
+    let socket = create_socket(&parameter)?;
+
+    // If SQ_POLL and io_uring enabled, create io_uring fd here
+    let io_uring: Option<IoUringNormal> = if parameter.uring_parameter.sqpoll_shared {
+        IoUringNormal::new(parameter.clone(), None).ok()
+    } else {
+        None
+    };
+    let io_uring_fd = io_uring.as_ref().map(|io_uring| io_uring.get_raw_fd()); 
+
+    // Interval statistics are sent by the threads to the reporter, which prints them while the measurement is running
+    let (interval_reporter, interval_tx) = if parameter.output_interval != 0.0 {
+        let pinned_cores = if parameter.core_affinity { Some(Arc::clone(&core_affinity_manager)) } else { None };
+        let (handle, interval_tx) = IntervalReporter::spawn(parameter.output_format, parameter.amount_threads, parameter.output_interval, pinned_cores, parameter.ip.is_ipv6());
+        (Some(handle), Some(interval_tx))
+    } else {
+        (None, None)
+    };
+
+    // The CPU utilization of all cores is sampled before the threads are spawned, so the start of the threads is included
+    let mut system_util = SystemCpuUtil::new();
+
+    for (direction_id, direction) in directions.iter().enumerate() {
+        let mut direction_handles = Vec::new();
+        // The opposite direction uses the ports following the receiver ports of the first direction
+        let base_port = parameter.port + direction_id as u16 * parameter.amount_threads * parameter.sockets_per_thread;
+        // The rate of the userspace pacer is shared by all sender threads of the direction
+        let pacer = if direction.mode == UDPerfMode::Sender { Pacer::new(direction).map(Arc::new) } else { None };
+
+        for i in 0..direction.amount_threads {
+            // Every socket of a thread uses its own port, starting with the receiver port of the thread
+            let receiver_port = if direction.multiplex_port_receiver != MultiplexPort::Individual {
+                info!("Receiver port is shared/sharded. Incrementing port number is disabled.");
+                base_port
             } else {
-                None
+                base_port + i * direction.sockets_per_thread
             };
-            let io_uring_fd = io_uring.as_ref().map(|io_uring| io_uring.get_raw_fd()); 
 
+            // Get instance of core affinity manager
+            let core_affinity = Arc::clone(&core_affinity_manager);
+            // Use same test id for all threads if one connection is simulated. Every flow (and socket) of a thread uses its own test id following the test id of the thread.
+            let test_id = if direction.simulate_connection == SimulateConnection::Single { 0 } else { i as u64 * direction.amount_flows() as u64 };
+            let local_port_sender: Option<u16> = if direction.multiplex_port == MultiplexPort::Sharding { Some(parameter.sender_port) } else { None };
+            let parameter_clone = direction.clone();
+            let stop_measurement = Arc::clone(&stop_measurement);
+            let ready_tx = ready_tx.clone();
+            let interval_tx = interval_tx.clone();
+            let pacer = pacer.clone();
 
-            for i in 0..parameter.amount_threads {
-                let receiver_port = if parameter.multiplex_port_receiver != MultiplexPort::Individual {
-                    info!("Receiver port is shared/sharded. Incrementing port number is disabled.");
-                    self.port
-                } else {
-                    self.port + i
-                };
+            direction_handles.push(thread::spawn(move || exec_thread(parameter_clone, i, socket, io_uring_fd, receiver_port, local_port_sender, test_id, core_affinity, stop_measurement, ready_tx, interval_tx, pacer)));
+        }
+        fetch_handle.push(direction_handles);
+    }
+    drop(ready_tx);
+    drop(interval_tx);
 
-                // Get instance of core affinity manager
-                let core_affinity = Arc::clone(&core_affinity_manager);
-                // Use same test id for all threads if one connection is simulated
-                let test_id = if parameter.simulate_connection == SimulateConnection::Single { 0 } else { i as u64 };
-                let local_port_sender: Option<u16> = if parameter.multiplex_port == MultiplexPort::Sharding { Some(self.sender_port) } else { None };
-                let parameter_clone = parameter.clone();
+    // The CPU utilization is measured from the start of the threads, also while the receiver waits for the end of the measurement on the control channel
+    let mut util = CpuUtil::new();
+    let mut udp_counters = UdpCounterSnapshot::new(parameter.ip.is_ipv6());
 
-                fetch_handle.push(thread::spawn(move || Self::exec_thread(parameter_clone, socket, io_uring_fd, receiver_port, local_port_sender, test_id, core_affinity)));
+    if let Some(control_channel) = control_channel.as_mut() {
+        if control_mode == UDPerfMode::Receiver {
+            // Wait until all threads have bound their sockets, before the remote host is allowed to start
+            for _ in 0..parameter.amount_threads as usize * directions.len() {
+                if ready_rx.recv().is_err() { break }
             }
-    
-            info!("Waiting for all threads to finish...");
-            let mut util = crate::util::cpu_util::CpuUtil::new();
-            util.get_relative_cpu_util();
+            if let Err(x) = control_channel.send(&ControlMessage::Start) {
+                error!("Error starting measurement over control channel: {}", x);
+            }
+        }
 
-            let amount_interval_outputs = if parameter.output_interval == 0.0 { 0 } else { (parameter.test_runtime_length as f64 / parameter.output_interval).floor() as usize };
-            debug!("Amount of interval outputs: {}", amount_interval_outputs);
+        // The host running the receiver threads waits for the host running the sender threads. In reverse mode, the remote host sends the stop message.
+        if (control_mode == UDPerfMode::Receiver) != parameter.reverse {
+            wait_for_stop(control_channel, &stop_measurement);
+        }
+    }
 
+    info!("Waiting for all threads to finish...");
 
-            // Iter over join handle and sum up statistics
-            let mut final_statistics = Statistic::new(parameter.clone());
-            let mut final_interval_statistics: Vec<Statistic> = vec![Statistic::new(parameter.clone()); amount_interval_outputs];
-            for (interval_id, statistic) in final_interval_statistics.iter_mut().enumerate() {
-                statistic.interval_id = interval_id as u64 + 1;
-            }
+    let mut final_statistics: Vec<Statistic> = Vec::new();
+    // Statistics of the single threads of every direction
+    let mut thread_statistics: Vec<Vec<Statistic>> = Vec::new();
+    // The first error of a thread is returned after the results of the other threads are printed and exchanged
+    let mut thread_error: Option<UdperfError> = None;
 
-            for handle in fetch_handle {
-                match handle.join() {
-                    Ok(result) => {
-                        // TODO: Merge interval_statistics
-                        if let Ok((statistic, interval_statistics)) = result { 
-                            final_statistics = final_statistics + statistic;
-                            if amount_interval_outputs != 0 {
-                                for statistic in interval_statistics {
-                                    let interval_id = statistic.interval_id as usize - 1;
-                                    final_interval_statistics[interval_id] = final_interval_statistics[interval_id].clone() + statistic;
-                                }
-                            } 
-                        }
-                    },
-                    Err(x) => warn!("Error joining thread: {:?}", x),
-                }
-            }
+    for (direction, direction_handles) in directions.iter().zip(fetch_handle) {
+        // Iter over join handle and sum up statistics
+        let mut direction_statistic = Statistic::new(direction.clone());
+        let mut direction_thread_statistics = Vec::new();
 
-            for statistic in final_interval_statistics.iter_mut() {
-                // Fix interval CPU util: (statistics.cpu_user_time, statistics.cpu_system_time, statistics.cpu_total_time) = util.get_relative_cpu_util();
-                if statistic.amount_datagrams != 0 {
-                    statistic.print(parameter.output_format, true);
-                }
+        for handle in direction_handles {
+            match handle.join() {
+                Ok(Ok((statistic, _))) => {
+                    direction_thread_statistics.push(statistic.clone());
+                    direction_statistic = direction_statistic + statistic;
+                },
+                Ok(Err(x)) => {
+                    thread_error.get_or_insert(x);
+                },
+                Err(x) => {
+                    error!("Error joining thread: {:?}", x);
+                    thread_error.get_or_insert(UdperfError::Other("Thread panicked"));
+                },
             }
+        }
+        direction_statistic.set_thread_fairness(&mut direction_thread_statistics);
+        final_statistics.push(direction_statistic);
+        thread_statistics.push(direction_thread_statistics);
+    }
 
-            // Update CPU spent time
-            (final_statistics.cpu_user_time, final_statistics.cpu_system_time, final_statistics.cpu_total_time) = util.get_absolut_cpu_util();
+    // The reporter finishes after all threads dropped their interval channel. The remaining intervals are printed before the summary.
+    let intervals = match interval_reporter.map(|interval_reporter| interval_reporter.join()) {
+        Some(Ok(intervals)) => intervals,
+        Some(Err(_)) => {
+            warn!("Error joining interval reporter thread");
+            Vec::new()
+        },
+        None => Vec::new()
+    };
 
-            if final_statistics.amount_datagrams != 0 {
-                final_statistics.print(parameter.output_format, false);
-            }
+    // Update CPU spent time. In bidirectional mode both directions share the CPU utilization of the process.
+    let cpu_util = util.get_absolut_cpu_util();
+    // With core affinity only the cores of the threads are measured
+    let core_ids = if parameter.core_affinity { Some(core_affinity_manager.lock().unwrap().bound_core_ids()) } else { None };
+    let system_cpu_util = system_util.get_absolut_cpu_util(core_ids.as_deref());
+    let cores = SystemCpuUtil::cores(core_ids.as_deref());
+    let udp_counters = udp_counters.get_absolut_counters();
+    for (statistic, direction_thread_statistics) in final_statistics.iter_mut().zip(thread_statistics.iter_mut()) {
+        statistic.set_cpu_usage(cpu_util);
+        (statistic.cores_user_time, statistic.cores_system_time, statistic.cores_irq_time, statistic.cores_softirq_time) = system_cpu_util;
+        statistic.cores = cores.clone();
+        statistic.set_udp_counters(udp_counters);
 
-            info!("All threads finished!");
-            if let Some(socket) = socket {
-                socket.close().expect("Error closing socket");
-            }
+        if parameter.thread_statistics {
+            Statistic::print_threads(direction_thread_statistics, parameter.output_format);
+        }
+
+        if statistic.amount_datagrams != 0 {
+            statistic.print(parameter.output_format, false);
+        }
+    }
+
+    if parameter.bidirectional && parameter.output_format == OutputFormat::Text {
+        Statistic::print_bidirectional(&final_statistics);
+    }
+
+    if let Some(mut control_channel) = control_channel {
+        exchange_results(&mut control_channel, control_mode, &parameter, &final_statistics);
+    }
+
+    info!("All threads finished!");
+    if let Some(socket) = socket {
+        socket.close().expect("Error closing socket");
+    }
+
+    match thread_error {
+        Some(x) => Err(x),
+        None => Ok(RunReport { statistics: final_statistics, intervals })
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn exec_thread(parameter: Parameter, thread_id: u16, socket: Option<Socket>, io_uring: Option<RawFd>, receiver_port: u16, sender_port: Option<u16>, test_id: u64, core_affinity_manager: Arc<Mutex<CoreAffinityManager>>, stop_measurement: Arc<AtomicBool>, ready: mpsc::Sender<()>, interval_reporter: Option<mpsc::Sender<Statistic>>, pacer: Option<Arc<Pacer>>) -> Result<(Statistic, Vec<Statistic>), UdperfError> {
+    let sock_address_receiver = SocketAddr::new(parameter.ip, receiver_port);
+
+    let core_id = if parameter.core_affinity {
+        Some(core_affinity_manager.lock().unwrap().set_affinity().unwrap())
+    } else {
+        None
+    };
     
-            if !(self.run_infinite && parameter.mode == UDPerfMode::Receiver) {
-                return Some(final_statistics);
+    let mut node: Box<dyn Node> = if parameter.mode == UDPerfMode::Sender {
+        Box::new(Sender::new(test_id, sender_port, sock_address_receiver, socket, io_uring, interval_reporter, pacer, parameter.clone())?)
+    } else {
+        Box::new(Receiver::new(sock_address_receiver, socket, io_uring, stop_measurement, interval_reporter, parameter.clone())?)
+    };
+    // Signal that the socket is bound and the node is ready to run
+    let _ = ready.send(());
+    drop(ready);
+
+    // CPU utilization of this thread only
+    let mut cpu_util = CpuUtil::thread();
+    // The measurement isn't aborted, if the performance counters are not available (e.g. blocked by seccomp)
+    let perf_counters = if parameter.perf_counters {
+        PerfCounters::thread().map_err(|error| warn!("{:?}: Performance counters not available: {}", thread::current().id(), error)).ok()
+    } else {
+        None
+    };
+
+    match node.run(parameter.io_model) {
+        Ok((mut statistic, intervals)) => { 
+            info!("{:?}: Finished measurement!", thread::current().id());
+            statistic.set_cpu_usage(cpu_util.get_absolut_cpu_util());
+            if let Some(perf_counters) = perf_counters {
+                statistic.set_perf_counters(perf_counters.read());
+            }
+            statistic.thread_id = Some(thread_id);
+            // Without core affinity, the core the thread ran on at last
+            statistic.core_id = core_id.or_else(|| usize::try_from(unsafe { libc::sched_getcpu() }).ok());
+            statistic.port = Some(receiver_port);
+            // The receiver records the test IDs of the received flows
+            if parameter.mode == UDPerfMode::Sender {
+                statistic.test_ids = statistic::format_id_ranges(test_id..test_id + parameter.amount_flows() as u64);
             }
+            Ok((statistic, intervals))
+        },
+        Err(x) => {
+            error!("{:?}: Error running app: {}", thread::current().id(), x);
+            Err(x)
         }
     }
+}
 
-    #[allow(clippy::too_many_arguments)]
-    fn exec_thread(parameter: Parameter, socket: Option<Socket>, io_uring: Option<RawFd>, receiver_port: u16, sender_port: Option<u16>, test_id: u64, core_affinity_manager: Arc<Mutex<CoreAffinityManager>>) -> Result<(Statistic, Vec<Statistic>), &'static str> {
-        let sock_address_receiver = SocketAddrV4::new(parameter.ip, receiver_port);
 
-        if parameter.core_affinity {
-            core_affinity_manager.lock().unwrap().set_affinity().unwrap();
-        }
-        
-        let mut node: Box<dyn Node> = if parameter.mode == UDPerfMode::Sender {
-            Box::new(Sender::new(test_id, sender_port, sock_address_receiver, socket, io_uring, parameter.clone()))
-        } else {
-            Box::new(Receiver::new(sock_address_receiver, socket, io_uring, parameter.clone()))
-        };
-
-        match node.run(parameter.io_model) {
-            Ok(statistic) => { 
-                info!("{:?}: Finished measurement!", thread::current().id());
-                Ok(statistic)
-            },
-            Err(x) => {
-                error!("{:?}: Error running app: {}", thread::current().id(), x);
-                Err("Error running app")
+fn setup_control_channel(parameter: &mut Parameter) -> Result<ControlChannel, UdperfError> {
+    let sock_address = SocketAddr::new(parameter.ip, parameter.port);
+
+    match parameter.mode {
+        UDPerfMode::Sender => {
+            let mut control_channel = ControlChannel::connect(sock_address)?;
+            control_channel.send(&ControlMessage::Parameter(Box::new(parameter.clone()), parameter.output_interval))?;
+
+            if parameter.reverse {
+                info!("Reverse mode: Switching to mode {:?}", UDPerfMode::Receiver);
+                parameter.mode = UDPerfMode::Receiver;
+                parameter.socket_options.convert_to_receiver();
+            }
+
+            // Wait for the remote host to be ready
+            match control_channel.receive()? {
+                ControlMessage::Start => {
+                    info!("Remote host is ready to start the measurement");
+                    Ok(control_channel)
+                },
+                _ => Err(UdperfError::Other("Expected start message from receiver"))
+            }
+        },
+        UDPerfMode::Receiver => {
+            let mut control_channel = ControlChannel::accept(sock_address)?;
+
+            match control_channel.receive()? {
+                ControlMessage::Parameter(mut remote, output_interval) => {
+                    remote.output_interval = output_interval;
+                    parameter.update_from_remote(&remote);
+                    Ok(control_channel)
+                },
+                _ => Err(UdperfError::Other("Expected parameter message from sender"))
             }
         }
     }
+}
+
+// Every direction of the measurement is running its own threads. Returns the parameters of every direction.
+fn directions(parameter: &Parameter) -> Vec<Parameter> {
+    if !parameter.bidirectional {
+        return vec![parameter.clone()];
+    }
+
+    let mut forward = parameter.clone();
+    if forward.mode == UDPerfMode::Receiver {
+        // Pacing is only applied to the sender threads of the reverse direction
+        forward.socket_options.socket_pacing_rate = 0;
+    }
+    let reverse = parameter.reversed();
+    vec![forward, reverse]
+}
 
+// Blocks until the remote host finished the measurement or closed the connection. The receiver threads are stopped, even if the LAST message got lost.
+fn wait_for_stop(control_channel: &mut ControlChannel, stop_measurement: &AtomicBool) {
+    match control_channel.receive() {
+        Ok(ControlMessage::Stop) => info!("Remote host finished the measurement"),
+        Ok(x) => warn!("Unexpected control message received: {:?}", x),
+        Err(x) => warn!("Control channel closed before the measurement finished: {}", x),
+    }
+    stop_measurement.store(true, Ordering::Relaxed);
+}
 
-    fn create_socket(&self, parameter: &Parameter) -> Option<Socket> {
-        if parameter.mode == UDPerfMode::Sender && parameter.multiplex_port == MultiplexPort::Sharing {
-            info!("Creating master socket for all sender threads to use, since socket sharing is enabled");
-            let mut socket = Socket::new(parameter.socket_options).expect("Error creating socket");
-            let sock_address_in = SocketAddrV4::new(crate::DEFAULT_SENDER_IP, self.sender_port);
+fn exchange_results(control_channel: &mut ControlChannel, control_mode: UDPerfMode, parameter: &Parameter, statistics: &[Statistic]) {
+    // The host running the sender threads stops the measurement. In reverse mode, this is the remote host.
+    if (control_mode == UDPerfMode::Sender) != parameter.reverse {
+        if let Err(x) = control_channel.send(&ControlMessage::Stop) {
+            warn!("Error stopping measurement over control channel: {}", x);
+            return;
+        }
+    }
 
-            socket.bind(sock_address_in).expect("Error binding to local port");
+    match control_mode {
+        UDPerfMode::Sender => {
 
-            // connect (includes bind) to specific 4-tuple, since every thread sends to same port on the receiver side
-            if parameter.multiplex_port_receiver == MultiplexPort::Sharding || parameter.multiplex_port_receiver == MultiplexPort::Sharing {
-                let sock_address_out = SocketAddrV4::new(parameter.ip, self.port);
-                socket.connect(sock_address_out).expect("Error connecting to remote host");
+            // The receiver sends one result for every direction
+            for _ in 0..statistics.len() {
+                match control_channel.receive() {
+                    Ok(ControlMessage::Result(mut remote_statistic)) => {
+                        // Utilization arrays and output settings are not transferred over the control channel
+                        remote_statistic.parameter.uring_parameter.record_utilization = false;
+                        remote_statistic.parameter.output_file_path = parameter.output_file_path.clone();
+                        if remote_statistic.amount_datagrams != 0 {
+                            remote_statistic.print(parameter.output_format, false);
+                        }
+                    },
+                    Ok(x) => warn!("Unexpected control message received: {:?}", x),
+                    Err(x) => {
+                        warn!("Error receiving results from remote host: {}", x);
+                        return;
+                    },
+                }
+            }
+        },
+        UDPerfMode::Receiver => {
+            for statistic in statistics {
+                if let Err(x) = control_channel.send(&ControlMessage::Result(Box::new(statistic.clone()))) {
+                    warn!("Error sending results to remote host: {}", x);
+                    return;
+                }
             }
+        }
+    }
+}
 
-            Some(socket)
-        } else if parameter.mode == UDPerfMode::Receiver && parameter.multiplex_port_receiver == MultiplexPort::Sharing {
-            info!("Creating master socket for all receiver threads to use, since socket sharing is enabled");
-            let sock_address_in = SocketAddrV4::new(parameter.ip, self.port);
-            let mut socket = Socket::new(parameter.socket_options).expect("Error creating socket");
-            socket.bind(sock_address_in).expect("Error binding to local port");
-            Some(socket)
-        } else {
-            None
+fn create_socket(parameter: &Parameter) -> Result<Option<Socket>, UdperfError> {
+    if parameter.mode == UDPerfMode::Sender && parameter.multiplex_port == MultiplexPort::Sharing {
+        info!("Creating master socket for all sender threads to use, since socket sharing is enabled");
+        let mut socket = Socket::new(parameter.socket_options)?;
+        let sock_address_in = SocketAddr::new(net::unspecified_address(parameter.ip), parameter.sender_port);
+
+        socket.bind(sock_address_in)?;
+
+        // connect (includes bind) to specific 4-tuple, since every thread sends to same port on the receiver side
+        if parameter.multiplex_port_receiver == MultiplexPort::Sharding || parameter.multiplex_port_receiver == MultiplexPort::Sharing {
+            let sock_address_out = SocketAddr::new(parameter.ip, parameter.port);
+            socket.connect(sock_address_out)?;
         }
+
+        Ok(Some(socket))
+    } else if parameter.mode == UDPerfMode::Receiver && parameter.multiplex_port_receiver == MultiplexPort::Sharing {
+        info!("Creating master socket for all receiver threads to use, since socket sharing is enabled");
+        let sock_address_in = SocketAddr::new(parameter.ip, parameter.port);
+        let mut socket = Socket::new(parameter.socket_options)?;
+        socket.bind(sock_address_in)?;
+        Ok(Some(socket))
+    } else {
+        Ok(None)
     }
 }
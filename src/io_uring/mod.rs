@@ -2,17 +2,19 @@ pub mod normal;
 pub mod provided_buffer;
 pub mod multishot;
 pub mod send;
+pub mod ping_pong;
 
 use std::os::fd::RawFd;
 use io_uring::{buf_ring::BufRing, cqueue, opcode, types::{SubmitArgs, Timespec}, IoUring, Probe, Submitter};
 use log::{debug, error, info, warn};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use crate::{util::statistic::{Parameter, UringParameter}, Statistic};
+use crate::UdperfError;
 
 const URING_SQ_POLL_TIMEOUT: u32 = 2_000;
 pub const IORING_CQE_F_NOTIF: u32 = 8;
 
-#[derive(clap::ValueEnum, Debug, PartialEq, Serialize, Clone, Copy, Default)]
+#[derive(clap::ValueEnum, Debug, PartialEq, Serialize, Deserialize, Clone, Copy, Default)]
 pub enum UringSqFillingMode {
     #[default]
     Topup,
@@ -20,7 +22,7 @@ pub enum UringSqFillingMode {
     Syscall 
 }
 
-#[derive(clap::ValueEnum, Debug, PartialEq, Serialize, Clone, Copy, Default)]
+#[derive(clap::ValueEnum, Debug, PartialEq, Serialize, Deserialize, Clone, Copy, Default)]
 pub enum UringTaskWork {
     #[default]
     Default,
@@ -29,7 +31,7 @@ pub enum UringTaskWork {
     CoopDefer
 }
 
-#[derive(clap::ValueEnum, Debug, PartialEq, Serialize, Clone, Copy, Default)]
+#[derive(clap::ValueEnum, Debug, PartialEq, Serialize, Deserialize, Clone, Copy, Default)]
 pub enum UringMode {
     #[default]
     Normal,
@@ -41,14 +43,14 @@ pub enum UringMode {
 pub trait IoUringOperatingModes {
     type Mode;
 
-    fn new(parameter: Parameter, io_uring_fd: Option<RawFd>) -> Result<Self::Mode, &'static str>;
+    fn new(parameter: Parameter, io_uring_fd: Option<RawFd>) -> Result<Self::Mode, UdperfError>;
 
     fn get_statistic(&self) -> Statistic;
 
     fn reset_statistic(&mut self, parameter: Parameter);
 
     // Return 1 if CQ is overflown (EBUSY error returned)
-    fn io_uring_enter(submitter: &mut Submitter, timeout: u32, min_complete: usize) -> Result<u64, &'static str> {
+    fn io_uring_enter(submitter: &mut Submitter, timeout: u32, min_complete: usize) -> Result<u64, UdperfError> {
         // Simulates https://man7.org/linux/man-pages/man3/io_uring_submit_and_wait_timeout.3.html
         // Submit to kernel and wait for completion event or timeout. In case the thread doesn't receive any messages.
         let mut args = SubmitArgs::new();
@@ -73,13 +75,13 @@ pub trait IoUringOperatingModes {
             },
             Err(err) => {
                 error!("Error submitting io_uring sqe: {}", err);
-                Err("IO_URING_ERROR")
+                Err(UdperfError::from_io_error("Failed to submit io_uring sqe", &err))
             }
         }
     }
 }
 
-fn create_ring(parameters: UringParameter, io_uring_fd: Option<RawFd>) -> Result<IoUring, &'static str> {
+fn create_ring(parameters: UringParameter, io_uring_fd: Option<RawFd>) -> Result<IoUring, UdperfError> {
         info!("Setting up io_uring with burst size: {}, and sq ring size: {}", parameters.burst_size, parameters.ring_size);
 
         let mut ring_builder = IoUring::<io_uring::squeue::Entry>::builder();
@@ -118,7 +120,7 @@ fn create_ring(parameters: UringParameter, io_uring_fd: Option<RawFd>) -> Result
         };
 
         let mut ring = ring_builder.build(parameters.ring_size)
-            .map_err(|_| "Failed to create io_uring")?;
+            .map_err(|err| UdperfError::from_io_error("Failed to create io_uring", &err))?;
 
         let sq_cap = ring.submission().capacity();
         debug!("Created io_uring instance successfully with CQ size: {} and SQ size: {}", ring.completion().capacity(), sq_cap);
@@ -209,7 +211,7 @@ fn calc_sq_fill_mode(amount_inflight: u32, parameter: UringParameter, ring: &mut
 // It shall only be used for io_uring, since the error codes are different (negated).
 // Errors are negated, since a positive amount of bytes received is a success.
 // io_uring doesn't use the errno variable, but returns the error code directly.
-pub fn parse_received_bytes(amount_received_bytes: i32) -> Result<u32, &'static str> {
+pub fn parse_received_bytes(amount_received_bytes: i32) -> Result<u32, UdperfError> {
     match amount_received_bytes {
         -105 => { 
             // result is -105, libc::ENOBUFS, no buffer space available (https://github.com/tokio-rs/io-uring/blob/b29e81583ed9a2c35feb1ba6f550ac1abf398f48/src/squeue.rs#L87) -> Only needed for provided buffers
@@ -222,7 +224,7 @@ pub fn parse_received_bytes(amount_received_bytes: i32) -> Result<u32, &'static
             // From: https://linux.die.net/man/2/recvmsg
             // libc::EAGAIN == 11
             debug!("EAGAIN: No messages available at the socket!"); // This should not happen in io_uring with FAST_POLL
-            Err("EAGAIN")
+            Err(UdperfError::WouldBlock)
         },
         -90 => { // libc::EMSGSIZE -> Message too long
             warn!("EMSGSIZE: The message is too long to fit into the supplied buffer and was truncated.");
@@ -230,13 +232,13 @@ pub fn parse_received_bytes(amount_received_bytes: i32) -> Result<u32, &'static
         },
         _ if amount_received_bytes < 0 => {
             error!("Error receiving message! Negated error code: {}", amount_received_bytes);
-            Err("Failed to receive data!")
+            Err(UdperfError::from_errno("Failed to receive data with io_uring", -amount_received_bytes))
         },
         _ => Ok(1) // Positive amount of bytes received
     }
 }
 
-fn check_io_uring_features_available(ring: &IoUring, parameter: UringParameter) -> Result<(), &'static str> {
+fn check_io_uring_features_available(ring: &IoUring, parameter: UringParameter) -> Result<(), UdperfError> {
     let mut probe = Probe::new();
     if ring.submitter().register_probe(&mut probe).is_err() {
         warn!("Unable to check for availability of io-uring features, since probe is not supported!");
@@ -245,15 +247,15 @@ fn check_io_uring_features_available(ring: &IoUring, parameter: UringParameter)
 
     if parameter.uring_mode == UringMode::Multishot {
         if !probe.is_supported(opcode::RecvMsgMulti::CODE) {
-            return Err("IORING_OP_RECVMSG_MULTI is not supported in the kernel!");
+            return Err(UdperfError::Other("IORING_OP_RECVMSG_MULTI is not supported in the kernel!"));
         }
         if !probe.is_supported(opcode::ProvideBuffers::CODE) {
-            return Err("IORING_OP_PROVIDE_BUFFERS is not supported in the kernel!");
+            return Err(UdperfError::Other("IORING_OP_PROVIDE_BUFFERS is not supported in the kernel!"));
         }
     } else if parameter.uring_mode == UringMode::ProvidedBuffer && !probe.is_supported(opcode::ProvideBuffers::CODE) {
-        return Err("IORING_OP_PROVIDE_BUFFERS is not supported in the kernel!");
+        return Err(UdperfError::Other("IORING_OP_PROVIDE_BUFFERS is not supported in the kernel!"));
     } else if parameter.uring_mode == UringMode::Zerocopy && !probe.is_supported(opcode::SendMsgZc::CODE) {
-        return Err("IORING_OP_SENDMSG_ZC is not supported in the kernel!");
+        return Err(UdperfError::Other("IORING_OP_SENDMSG_ZC is not supported in the kernel!"));
     }
 
     if !ring.params().is_feature_fast_poll() {
@@ -6,6 +6,7 @@ use log::{debug, error};
 use crate::{util::statistic::{Parameter, UringParameter}, Statistic};
 
 use super::IoUringOperatingModes;
+use crate::UdperfError;
 
 pub struct IoUringMultishot {
     ring: IoUring,
@@ -16,7 +17,7 @@ pub struct IoUringMultishot {
 }
 
 impl IoUringMultishot {
-    fn submit(&mut self, socket_fd: i32) -> Result<u32, &'static str> {
+    fn submit(&mut self, socket_fd: i32) -> Result<u32, UdperfError> {
         // Use the socket file descripter to receive messages
         debug!("Arming multishot request");
         let sqe = opcode::RecvMsgMulti::new(types::Fd(socket_fd), &self.msghdr, crate::URING_BUFFER_GROUP).build();
@@ -27,12 +28,12 @@ impl IoUringMultishot {
             },
             Err(err) => {
                 error!("Error pushing io_uring sqe: {}", err);
-                Err("IO_URING ERROR")
+                Err(UdperfError::Other("IO_URING ERROR"))
             }
         }
     }
 
-    pub fn fill_sq_and_submit(&mut self, armed: bool, socket_fd: i32) -> Result<u32, &'static str> {
+    pub fn fill_sq_and_submit(&mut self, armed: bool, socket_fd: i32) -> Result<u32, UdperfError> {
         let mut amount_new_requests = 0;
         if !armed {
             amount_new_requests = self.submit(socket_fd)?;
@@ -68,7 +69,7 @@ impl IoUringMultishot {
 impl IoUringOperatingModes for IoUringMultishot {
     type Mode = IoUringMultishot;
 
-    fn new(parameter: Parameter, io_uring_fd: Option<RawFd>) -> Result<Self, &'static str> {
+    fn new(parameter: Parameter, io_uring_fd: Option<RawFd>) -> Result<Self, UdperfError> {
         let ring = super::create_ring(parameter.uring_parameter, io_uring_fd)?;
         let buf_ring = super::create_buf_ring(&mut ring.submitter(), parameter.uring_parameter.buffer_size as u16, parameter.mss);
 
@@ -5,6 +5,7 @@ use std::os::{fd::RawFd, unix::io::AsRawFd};
 use crate::{util::{packet_buffer::PacketBuffer, statistic::{Parameter, UringParameter}}, Statistic};
 
 use super::IoUringOperatingModes;
+use crate::UdperfError;
 pub struct IoUringNormal {
     ring: IoUring,
     parameter: UringParameter,
@@ -12,7 +13,7 @@ pub struct IoUringNormal {
 }
 
 impl IoUringNormal {
-    fn submit(&mut self, amount_requests: usize, packet_buffer: &mut PacketBuffer, socket_fd: i32) -> Result<u32, &'static str> {
+    fn submit(&mut self, amount_requests: usize, packet_buffer: &mut PacketBuffer, socket_fd: i32) -> Result<u32, UdperfError> {
         let mut submission_count = 0;
         let mut sq = self.ring.submission();
         debug!("BEGIN io_uring_submit: Current sq len: {}. Dropped messages: {}", sq.len(), sq.dropped());
@@ -40,7 +41,7 @@ impl IoUringNormal {
         Ok(submission_count)
     }
 
-    pub fn fill_sq_and_submit(&mut self, amount_inflight: u32, packet_buffer: &mut PacketBuffer, socket_fd: i32) -> Result<u32, &'static str> {
+    pub fn fill_sq_and_submit(&mut self, amount_inflight: u32, packet_buffer: &mut PacketBuffer, socket_fd: i32) -> Result<u32, UdperfError> {
         let (min_complete, amount_new_requests) = match super::calc_sq_fill_mode(amount_inflight, self.parameter, &mut self.ring) {
             (0,0) => return Ok(0),
             (to_submit, min_complete) => {
@@ -77,7 +78,7 @@ impl IoUringNormal {
 impl IoUringOperatingModes for IoUringNormal {
     type Mode = IoUringNormal;
 
-    fn new(parameter: Parameter, io_uring_fd: Option<RawFd>) -> Result<IoUringNormal, &'static str> {
+    fn new(parameter: Parameter, io_uring_fd: Option<RawFd>) -> Result<IoUringNormal, UdperfError> {
         let ring = super::create_ring(parameter.uring_parameter, io_uring_fd)?;
 
         Ok(IoUringNormal {
@@ -0,0 +1,89 @@
+use io_uring::{cqueue::Entry, opcode, squeue, types, CompletionQueue, IoUring};
+use log::{debug, warn};
+use std::os::fd::RawFd;
+
+use crate::{util::statistic::Parameter, Statistic};
+
+use super::IoUringOperatingModes;
+use crate::UdperfError;
+
+pub const USER_DATA_SEND: u64 = 0;
+pub const USER_DATA_RECV: u64 = 1;
+
+// In ping-pong mode only one request is inflight at a time. The receive request for the echo is submitted separately, since it stays armed if an echo got lost.
+pub struct IoUringPingPong {
+    ring: IoUring,
+    pub zerocopy: bool,
+    statistic: Statistic
+}
+
+impl IoUringPingPong {
+    pub fn submit_send(&mut self, msghdr: &libc::msghdr, socket_fd: i32) -> Result<(), UdperfError> {
+        let sqe = if self.zerocopy {
+            opcode::SendMsgZc::new(types::Fd(socket_fd), msghdr)
+                .ioprio(super::send::IORING_SEND_ZC_REPORT_USAGE)
+                .build()
+                .user_data(USER_DATA_SEND)
+        } else {
+            opcode::SendMsg::new(types::Fd(socket_fd), msghdr)
+                .build()
+                .user_data(USER_DATA_SEND)
+        };
+
+        self.push(&sqe)
+    }
+
+    pub fn submit_recv(&mut self, echo_msghdr: &mut libc::msghdr, socket_fd: i32) -> Result<(), UdperfError> {
+        let sqe = opcode::RecvMsg::new(types::Fd(socket_fd), echo_msghdr)
+            .build()
+            .user_data(USER_DATA_RECV);
+
+        self.push(&sqe)
+    }
+
+    fn push(&mut self, sqe: &squeue::Entry) -> Result<(), UdperfError> {
+        let mut sq = self.ring.submission();
+        match unsafe { sq.push(sqe) } {
+            Ok(_) => {
+                debug!("Pushed ping-pong request with user_data {}. Current sq len: {}", sqe.get_user_data(), sq.len());
+                Ok(())
+            },
+            Err(err) => {
+                warn!("Error pushing io_uring sqe: {}", err);
+                Err(UdperfError::Other("Error pushing io_uring sqe"))
+            }
+        }
+    }
+
+    // Submits the pending entries and waits for at least one completion event or the timeout
+    pub fn submit_and_wait(&mut self) -> Result<(), UdperfError> {
+        self.statistic.uring_cq_overflows += Self::io_uring_enter(&mut self.ring.submitter(), crate::URING_ENTER_TIMEOUT, 1)?;
+        Ok(())
+    }
+
+    pub fn get_cq(&mut self) -> CompletionQueue<'_, Entry> {
+        self.ring.completion()
+    }
+}
+
+impl IoUringOperatingModes for IoUringPingPong {
+    type Mode = IoUringPingPong;
+
+    fn new(parameter: Parameter, io_uring_fd: Option<RawFd>) -> Result<IoUringPingPong, UdperfError> {
+        let ring = super::create_ring(parameter.uring_parameter, io_uring_fd)?;
+
+        Ok(IoUringPingPong {
+            ring,
+            zerocopy: parameter.uring_parameter.uring_mode == super::UringMode::Zerocopy,
+            statistic: Statistic::new(parameter)
+        })
+    }
+
+    fn get_statistic(&self) -> Statistic {
+        self.statistic.clone()
+    }
+
+    fn reset_statistic(&mut self, parameter: Parameter) {
+        self.statistic = Statistic::new(parameter);
+    }
+}
@@ -7,26 +7,37 @@ use log::{debug, warn};
 use crate::{util::statistic::{Parameter, UringParameter}, Statistic};
 
 use super::IoUringOperatingModes;
+use crate::UdperfError;
 
 pub struct IoUringProvidedBuffer {
     ring: IoUring,
     buf_ring: BufRing,
     parameter: UringParameter,
-    msghdr: msghdr,
+    // With receive timestamps enabled, every inflight request needs its own msghdr, since the kernel writes the control messages into msg_control on completion. The index of the msghdr is passed as user_data.
+    msghdrs: Vec<msghdr>,
+    next_msghdr: usize,
+    _control_buffers: Vec<[u8; crate::LENGTH_MSGHDR_CONTROL_MESSAGE_BUFFER]>,
     statistic: Statistic
 }
 
 impl IoUringProvidedBuffer {
-    fn submit(&mut self, amount_requests: usize, socket_fd: i32) -> Result<u32, &'static str> {
+    fn submit(&mut self, amount_requests: usize, socket_fd: i32) -> Result<u32, UdperfError> {
         let mut submission_count = 0;
         let mut sq = self.ring.submission();
         debug!("BEGIN io_uring_submit: Current sq len: {}. Dropped messages: {}", sq.len(), sq.dropped());
 
         for _ in 0..amount_requests {
-            let sqe = opcode::RecvMsg::new(types::Fd(socket_fd), &mut self.msghdr)
+            let index = self.next_msghdr;
+            self.next_msghdr = (self.next_msghdr + 1) % self.msghdrs.len();
+            let msghdr = &mut self.msghdrs[index];
+            msghdr.msg_flags = 0;
+            msghdr.msg_controllen = crate::LENGTH_MSGHDR_CONTROL_MESSAGE_BUFFER;
+
+            let sqe = opcode::RecvMsg::new(types::Fd(socket_fd), msghdr)
                 .buf_group(crate::URING_BUFFER_GROUP) 
                 .build()
-                .flags(squeue::Flags::BUFFER_SELECT);
+                .flags(squeue::Flags::BUFFER_SELECT)
+                .user_data(index as u64);
 
             match unsafe { sq.push(&sqe) } {
                 Ok(_) => submission_count += 1,
@@ -43,7 +54,7 @@ impl IoUringProvidedBuffer {
     }
 
     // TODO: Could be generic function: Only self type differs 
-    pub fn fill_sq_and_submit(&mut self, amount_inflight: u32, socket_fd: i32) -> Result<u32, &'static str> {
+    pub fn fill_sq_and_submit(&mut self, amount_inflight: u32, socket_fd: i32) -> Result<u32, UdperfError> {
         let (min_complete, amount_new_requests) = match super::calc_sq_fill_mode(amount_inflight, self.parameter, &mut self.ring) {
             (0,0) => return Ok(0),
             (to_submit, min_complete) => {
@@ -68,32 +79,42 @@ impl IoUringProvidedBuffer {
         Ok(amount_new_requests)
     }
 
-    pub fn get_bufs_and_cq(&mut self) -> (&mut BufRing, CompletionQueue<'_, Entry>) {
-        (&mut self.buf_ring, self.ring.completion())
+    pub fn get_bufs_and_cq(&mut self) -> (&mut BufRing, CompletionQueue<'_, Entry>, &[msghdr]) {
+        (&mut self.buf_ring, self.ring.completion(), &self.msghdrs)
     }
 }
 
 impl IoUringOperatingModes for IoUringProvidedBuffer {
     type Mode = IoUringProvidedBuffer;
 
-    fn new(parameter: Parameter, io_uring_fd: Option<RawFd>) -> Result<Self, &'static str> {
+    fn new(parameter: Parameter, io_uring_fd: Option<RawFd>) -> Result<Self, UdperfError> {
         let ring = super::create_ring(parameter.uring_parameter, io_uring_fd)?;
         let buf_ring = super::create_buf_ring(&mut ring.submitter(), parameter.uring_parameter.buffer_size as u16, parameter.mss);
 
         // Generic msghdr: msg_controllen and msg_namelen relevant, when using provided buffers
         // https://github.com/SUPERCILEX/clipboard-history/blob/418b2612f8e62693e42057029df78f6fbf49de3e/receiver/src/reactor.rs#L206
         // https://github.com/axboe/liburing/blob/cc61897b928e90c4391e0d6390933dbc9088d98f/examples/io_uring-udp.c#L113
-        let msghdr = {
+        // Receive timestamps and drop counters are delivered as control messages, which need an own buffer for every request in flight
+        let control_messages = parameter.socket_options.rx_timestamps || parameter.socket_options.rxq_overflow;
+        let amount_msghdrs = if control_messages { parameter.uring_parameter.buffer_size as usize } else { 1 };
+        let mut control_buffers = vec![[0_u8; crate::LENGTH_MSGHDR_CONTROL_MESSAGE_BUFFER]; if control_messages { amount_msghdrs } else { 0 }];
+        let mut msghdrs = Vec::with_capacity(amount_msghdrs);
+        for index in 0..amount_msghdrs {
             let mut hdr = unsafe { std::mem::zeroed::<libc::msghdr>() };
             hdr.msg_controllen = crate::LENGTH_MSGHDR_CONTROL_MESSAGE_BUFFER;
-            hdr
-        };
+            if let Some(control_buffer) = control_buffers.get_mut(index) {
+                hdr.msg_control = control_buffer.as_mut_ptr() as *mut libc::c_void;
+            }
+            msghdrs.push(hdr);
+        }
 
         Ok(IoUringProvidedBuffer {
             ring,
             buf_ring,
             parameter: parameter.uring_parameter,
-            msghdr,
+            msghdrs,
+            next_msghdr: 0,
+            _control_buffers: control_buffers,
             statistic: Statistic::new(parameter)
         })
     }
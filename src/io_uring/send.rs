@@ -3,11 +3,12 @@ use std::os::fd::RawFd;
 use io_uring::{cqueue::Entry, opcode, types, CompletionQueue, IoUring};
 use log::{debug, trace, warn};
 
-use crate::{util::{packet_buffer::PacketBuffer, statistic::{Parameter, UringParameter}}, Statistic};
+use crate::{util::{packet_buffer::PacketBuffer, pacer::Pacer, statistic::{Parameter, UringParameter}}, Statistic};
 
 pub const IORING_SEND_ZC_REPORT_USAGE: u16 = 8;
 
 use super::IoUringOperatingModes;
+use crate::UdperfError;
 pub struct IoUringSend {
     ring: IoUring,
     parameter: UringParameter,
@@ -16,15 +17,21 @@ pub struct IoUringSend {
 }
 
 impl IoUringSend {
-    fn submit(&mut self, amount_requests: usize, packet_buffer: &mut PacketBuffer, next_packet_id: u64, socket_fd: i32) -> Result<usize, &'static str> {
+    fn submit(&mut self, amount_requests: usize, packet_buffer: &mut PacketBuffer, next_packet_id: u64, socket_fd: i32, pacer: Option<&Pacer>) -> Result<usize, UdperfError> {
         let mut submission_count = 0;
         let mut sq = self.ring.submission();
         let packets_per_buffer = packet_buffer.packets_amount_per_msghdr();
         debug!("BEGIN io_uring_submit: Current sq len: {}. Dropped messages: {}", sq.len(), sq.dropped());
 
         // Add all packet_ids in one go -> Probably more efficient, but not benched
+        packet_buffer.sample_datagram_sizes(0, amount_requests);
         packet_buffer.add_packet_ids(next_packet_id, Some(amount_requests))?;
 
+        // The requests are paced before they are pushed, since with submission queue polling the kernel can execute them right away
+        if let Some(pacer) = pacer {
+            pacer.acquire((amount_requests * packets_per_buffer) as u64, packet_buffer.amount_bytes(0, amount_requests));
+        }
+
         for i in 0..amount_requests {
             let packet_id = next_packet_id + ( i * packets_per_buffer ) as u64;
             trace!("Message number {}/{}: Used buffer index {}", i, amount_requests, i);
@@ -43,22 +50,39 @@ impl IoUringSend {
             };
         }
 
+        // The tokens of the requests, which weren't pushed, are returned to the pacer
+        if let Some(pacer) = pacer.filter(|_| submission_count < amount_requests) {
+            pacer.release(((amount_requests - submission_count) * packets_per_buffer) as u64, packet_buffer.amount_bytes(submission_count, amount_requests - submission_count));
+        }
+
         debug!("END io_uring_submit: Submitted {} io_uring sqe. Current sq len: {}. Dropped messages: {}", submission_count, sq.len(), sq.dropped());
         Ok(submission_count)
     }
 
-    fn submit_zc(&mut self, amount_requests: usize, packet_buffer: &mut PacketBuffer, next_packet_id: u64, socket_fd: i32) -> Result<usize, &'static str> {
+    fn submit_zc(&mut self, amount_requests: usize, packet_buffer: &mut PacketBuffer, next_packet_id: u64, socket_fd: i32, pacer: Option<&Pacer>) -> Result<usize, UdperfError> {
         let mut submission_count = 0;
         let mut amount_datagrams = 0;
         let mut sq = self.ring.submission();
+        let packets_per_buffer = packet_buffer.packets_amount_per_msghdr();
         debug!("BEGIN io_uring_submit: Current sq len: {}. Dropped messages: {}", sq.len(), sq.dropped());
 
-        for i in 0..amount_requests {
+        let mut packet_buffer_indexes = Vec::with_capacity(amount_requests);
+        for _ in 0..amount_requests {
             let packet_buffer_index = packet_buffer.get_buffer_index()?;
-            trace!("Message number {}/{}: Used buffer index {}", i, amount_requests, i);
 
             // Add packet_ids to specific msghdr
+            packet_buffer.sample_datagram_sizes(packet_buffer_index, 1);
             amount_datagrams += packet_buffer.add_packet_ids_to_msghdr(next_packet_id + amount_datagrams, packet_buffer_index)?;
+            packet_buffer_indexes.push(packet_buffer_index);
+        }
+
+        // The buffers are taken from the pool in any order, so the bytes are counted from the buffers actually used
+        if let Some(pacer) = pacer {
+            pacer.acquire((amount_requests * packets_per_buffer) as u64, packet_buffer_indexes.iter().map(|index| packet_buffer.amount_bytes(*index, 1)).sum());
+        }
+
+        for (i, &packet_buffer_index) in packet_buffer_indexes.iter().enumerate() {
+            trace!("Message number {}/{}: Used buffer index {}", i, amount_requests, packet_buffer_index);
 
             // Set IORING_SEND_ZC_REPORT_USAGE in ioprio flags to check if a copy is done nevertheless -> IORING_NOTIF_USAGE_ZC_COPIED in cqe.flags
             // https://github.com/axboe/liburing/blob/b68cf47a120d6b117a81ed9f7617aad13314258c/src/include/liburing/io_uring.h#L343
@@ -78,20 +102,27 @@ impl IoUringSend {
             };
         }
 
+        // The buffers and tokens of the requests, which weren't pushed, are returned
+        let unsubmitted_indexes = packet_buffer_indexes.split_off(submission_count);
+        if let Some(pacer) = pacer.filter(|_| !unsubmitted_indexes.is_empty()) {
+            pacer.release((unsubmitted_indexes.len() * packets_per_buffer) as u64, unsubmitted_indexes.iter().map(|index| packet_buffer.amount_bytes(*index, 1)).sum());
+        }
+        packet_buffer.return_buffer_index(unsubmitted_indexes);
+
         debug!("END io_uring_submit: Submitted {} io_uring sqe. Current sq len: {}. Dropped messages: {}", submission_count, sq.len(), sq.dropped());
         Ok(submission_count)
     }
 
-    pub fn fill_sq_and_submit(&mut self, amount_inflight: usize, packet_buffer: &mut PacketBuffer, next_packet_id: u64, socket_fd: i32) -> Result<usize, &'static str> {
+    pub fn fill_sq_and_submit(&mut self, amount_inflight: usize, packet_buffer: &mut PacketBuffer, next_packet_id: u64, socket_fd: i32, pacer: Option<&Pacer>) -> Result<usize, UdperfError> {
         let mut amount_new_requests = 0;
 
         let min_complete = match super::calc_sq_fill_mode(amount_inflight as u32, self.parameter, &mut self.ring) {
             (0,0) => return Ok(0),
             (to_submit, min_complete) => {
                 amount_new_requests += if self.zerocopy {
-                    self.submit_zc(to_submit, packet_buffer, next_packet_id, socket_fd)?
+                    self.submit_zc(to_submit, packet_buffer, next_packet_id, socket_fd, pacer)?
                 } else {
-                    self.submit(to_submit, packet_buffer, next_packet_id, socket_fd)?
+                    self.submit(to_submit, packet_buffer, next_packet_id, socket_fd, pacer)?
                 };
                 
                 min_complete
@@ -121,7 +152,7 @@ impl IoUringSend {
 impl IoUringOperatingModes for IoUringSend {
     type Mode = IoUringSend;
 
-    fn new(parameter: Parameter, io_uring_fd: Option<RawFd>) -> Result<IoUringSend, &'static str> {
+    fn new(parameter: Parameter, io_uring_fd: Option<RawFd>) -> Result<IoUringSend, UdperfError> {
         let ring = super::create_ring(parameter.uring_parameter, io_uring_fd)?;
 
         Ok(IoUringSend {
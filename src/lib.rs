@@ -4,31 +4,41 @@ mod util;
 mod command_parser;
 mod executor;
 mod io_uring;
+mod error;
 
-use std::net::Ipv4Addr;
-
-pub use util::statistic::Statistic;
+pub use util::statistic::{Statistic, Parameter, UringParameter, OutputFormat, MultiplexPort, SimulateConnection, FlowMode, TrafficPattern, DatagramSizeDistribution, PayloadMode};
+pub use util::parameter_builder::{ParameterBuilder, SocketOptionsBuilder, UringParameterBuilder};
+pub use util::{UDPerfMode, ExchangeFunction, IOModel};
+pub use net::socket_options::SocketOptions;
+pub use io_uring::{UringMode, UringSqFillingMode, UringTaskWork};
+pub use executor::{run, RunReport};
+pub use error::UdperfError;
 
 // const UDP_RATE: usize = (1024 * 1024) // /* 1 Mbps */
 const DEFAULT_MSS: u32= 1472;
 const DEFAULT_UDP_DATAGRAM_SIZE: u32 = 1472;
+const DEFAULT_UDP_DATAGRAM_SIZE_MIN: u32 = 64; // Minimum of the uniform size distribution
+const DEFAULT_PAYLOAD_SEED: u64 = 0;
 const DEFAULT_GSO_BUFFER_SIZE: u32 = 64768 ; // 65507 is the possible maximum , but then the last packet is only have full -> 1472 * 44 = 64768
 //const MAX_SOCKET_BUFFER_SIZE: u32 = 2129920; // 2MB; // The buffer size will be doubled by the kernel to account for overhead. See man 7 socket
 const DEFAULT_SOCKET_BUFFER_SIZE: u32 = 212992; 
 const DEFAULT_DURATION: u64 = 10; // /* seconds */
 const DEFAULT_RECEIVER_PORT: u16 = 45001;
-const DEFAULT_SENDER_IP: Ipv4Addr = Ipv4Addr::new(0,0,0,0);
 const DEFAULT_SENDER_PORT: u16 = 46001;
 const DEFAULT_INTERVAL: f64 = 0.0; // /* seconds */
 const DEFAULT_BANDWIDTH: u64 = 0; // /* Megabits per second (Mbit/s) */
+const DEFAULT_BURST_LENGTH: u64 = 100; // /* milliseconds */
+const DEFAULT_BURST_PAUSE: u64 = 100; // /* milliseconds */
 const WAIT_CONTROL_MESSAGE: u64 = 400; // /* milliseconds */
+const CONTROL_CHANNEL_TIMEOUT: u64 = 5000; // /* milliseconds */
+const PING_PONG_TIMEOUT: u64 = 1000; // /* milliseconds */
 const DEFAULT_FILE_NAME: &str = "udperf-output.csv";
-const MAX_TEST_ID: usize = 1024;
+const MAX_TEST_ID: usize = 65536;
 
 // Maximum datagram size UDP is (64K - 1) - IP and UDP header sizes 
 const MAX_UDP_DATAGRAM_SIZE: u32 = 65535 - 8 - 20;
 const DEFAULT_AMOUNT_MSG_WHEN_SENDMMSG: usize = 1;
-const LENGTH_MSGHDR_CONTROL_MESSAGE_BUFFER: usize = 24;
+const LENGTH_MSGHDR_CONTROL_MESSAGE_BUFFER: usize = 24 + 64 + 24; // CMSG_SPACE of UDP_GRO (u32) + CMSG_SPACE of SCM_TIMESTAMPING (3x timespec) + CMSG_SPACE of SO_RXQ_OVFL (u32)
 
 // uring defaults
 const DEFAULT_URING_RING_SIZE: u32 = 16;
@@ -36,7 +46,7 @@ const URING_BUFFER_SIZE_MULTIPLICATOR: u32 = 4; // The buffer size is a multipli
 const URING_BURST_SIZE_DIVIDEND: u32 = 4; // The burst size is a division of the ring size
 const URING_MAX_RING_SIZE: u32 = 2048; // Maximum ring size
 const URING_BUFFER_GROUP: u16 = 0;
-const URING_ADDITIONAL_BUFFER_LENGTH: i32 = 40;
+const URING_ADDITIONAL_BUFFER_LENGTH: i32 = 16 + LENGTH_MSGHDR_CONTROL_MESSAGE_BUFFER as i32; // struct io_uring_recvmsg_out + control messages
 const URING_ENTER_TIMEOUT: u32 = 10_000_000;
 const URING_SQPOLL_CPU: u32 = 0;
 
@@ -5,8 +5,13 @@ fn main() {
 
     let parameter = match udperf.parse_parameter() {
         Some(x) => x,
-        None => { return },
+        // Printing the markdown help isn't a failure, invalid parameters are
+        None if udperf.markdown_help => { return },
+        None => std::process::exit(1),
     };
 
-    udperf.exec(parameter);
-}
\ No newline at end of file
+    // A failed measurement results in a non-zero exit code
+    if udperf.exec(parameter).is_none() {
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,81 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::time::Duration;
+
+use log::{debug, error, info};
+use serde::{Deserialize, Serialize};
+
+use crate::util::statistic::{Parameter, Statistic};
+use crate::UdperfError;
+
+// Messages exchanged over the TCP control connection. Every message is serialized as a single JSON line.
+// The output interval isn't part of the serialized parameters (and therefore of the result files), so it's sent besides them.
+#[derive(Debug, Serialize, Deserialize)]
+pub enum ControlMessage {
+    Parameter(Box<Parameter>, f64),
+    Start,
+    Stop,
+    Result(Box<Statistic>),
+}
+
+pub struct ControlChannel {
+    reader: BufReader<TcpStream>,
+    stream: TcpStream,
+}
+
+impl ControlChannel {
+    // Sender side: Connect to the control port of the receiver
+    pub fn connect(sock_address: SocketAddr) -> Result<ControlChannel, UdperfError> {
+        info!("Connecting control channel to {}", sock_address);
+        let stream = match TcpStream::connect_timeout(&sock_address, Duration::from_millis(crate::CONTROL_CHANNEL_TIMEOUT)) {
+            Ok(stream) => stream,
+            Err(x) => {
+                error!("Failed to connect control channel to {}: {}", sock_address, x);
+                return Err(UdperfError::from_io_error("Failed to connect control channel. Start the receiver first!", &x));
+            }
+        };
+        Self::from_stream(stream)
+    }
+
+    // Receiver side: Listen on the control port and wait for a single sender to connect
+    pub fn accept(sock_address: SocketAddr) -> Result<ControlChannel, UdperfError> {
+        let listener = TcpListener::bind(sock_address).map_err(|x| {
+            error!("Failed to bind control channel to {}: {}", sock_address, x);
+            UdperfError::from_io_error("Failed to bind control channel", &x)
+        })?;
+
+        info!("Waiting for sender to connect to control channel on {}", sock_address);
+        let (stream, remote) = listener.accept().map_err(|x| UdperfError::from_io_error("Failed to accept control channel connection", &x))?;
+        info!("Control channel connected to {}", remote);
+        Self::from_stream(stream)
+    }
+
+    fn from_stream(stream: TcpStream) -> Result<ControlChannel, UdperfError> {
+        stream.set_nodelay(true).map_err(|x| UdperfError::from_io_error("Failed to set TCP_NODELAY on control channel", &x))?;
+        let reader = BufReader::new(stream.try_clone().map_err(|x| UdperfError::from_io_error("Failed to clone control channel stream", &x))?);
+        Ok(ControlChannel { reader, stream })
+    }
+
+    pub fn send(&mut self, message: &ControlMessage) -> Result<(), UdperfError> {
+        debug!("Sending control message: {:?}", message);
+        let mut line = serde_json::to_string(message).map_err(|_| UdperfError::Other("Failed to serialize control message"))?;
+        line.push('\n');
+        self.stream.write_all(line.as_bytes()).map_err(|x| UdperfError::from_io_error("Failed to send control message", &x))
+    }
+
+    pub fn receive(&mut self) -> Result<ControlMessage, UdperfError> {
+        let mut line = String::new();
+        match self.reader.read_line(&mut line) {
+            Ok(0) => Err(UdperfError::Other("Control channel closed by remote host")),
+            Ok(_) => {
+                let message = serde_json::from_str(&line).map_err(|x| {
+                    error!("Failed to parse control message: {}", x);
+                    UdperfError::Other("Failed to parse control message")
+                })?;
+                debug!("Received control message: {:?}", message);
+                Ok(message)
+            },
+            Err(x) => Err(UdperfError::from_io_error("Failed to receive control message", &x))
+        }
+    }
+}
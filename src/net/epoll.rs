@@ -0,0 +1,108 @@
+use std::collections::VecDeque;
+use log::{debug, error, trace, warn};
+
+use super::socket::Socket;
+use crate::UdperfError;
+
+// Maximum amount of events returned by a single epoll_wait call
+const MAX_EPOLL_EVENTS: usize = 64;
+
+// The data of a registered socket is its index in the socket list of the sender/receiver.
+// The indexes of the ready sockets are queued, since with edge-triggered notifications every ready socket has to be served until it returns EAGAIN.
+pub struct Epoll {
+    epoll_fd: i32,
+    events: Vec<libc::epoll_event>,
+    ready_sockets: VecDeque<usize>,
+    flags: u32,
+}
+
+impl Epoll {
+    pub fn new(edge_triggered: bool, exclusive: bool) -> Result<Epoll, UdperfError> {
+        let epoll_fd = unsafe { libc::epoll_create1(libc::EPOLL_CLOEXEC) };
+        if epoll_fd == -1 {
+            let error = UdperfError::last_os_error("Failed to create epoll instance");
+            error!("{}", error);
+            return Err(error);
+        }
+
+        let mut flags = 0;
+        if edge_triggered {
+            flags |= libc::EPOLLET as u32;
+        }
+        if exclusive {
+            // Only one of the threads waiting on a shared socket is woken up
+            flags |= libc::EPOLLEXCLUSIVE as u32;
+        }
+        debug!("Created epoll instance {} with flags {:#x}", epoll_fd, flags);
+
+        Ok(Epoll {
+            epoll_fd,
+            events: vec![libc::epoll_event { events: 0, u64: 0 }; MAX_EPOLL_EVENTS],
+            ready_sockets: VecDeque::with_capacity(MAX_EPOLL_EVENTS),
+            flags,
+        })
+    }
+
+    pub fn add(&self, socket: &Socket, events: libc::c_int, index: usize) -> Result<(), UdperfError> {
+        self.control(libc::EPOLL_CTL_ADD, socket, events as u32 | self.flags, index)
+    }
+
+    // EPOLL_CTL_MOD fails with EINVAL on sockets registered with EPOLLEXCLUSIVE, so these are removed and added again
+    pub fn modify(&self, socket: &Socket, events: libc::c_int, index: usize) -> Result<(), UdperfError> {
+        if self.flags & libc::EPOLLEXCLUSIVE as u32 != 0 {
+            self.control(libc::EPOLL_CTL_DEL, socket, 0, index)?;
+            return self.add(socket, events, index);
+        }
+        self.control(libc::EPOLL_CTL_MOD, socket, events as u32 | self.flags, index)
+    }
+
+    fn control(&self, operation: libc::c_int, socket: &Socket, events: u32, index: usize) -> Result<(), UdperfError> {
+        let mut event = libc::epoll_event { events, u64: index as u64 };
+        let result = unsafe {
+            libc::epoll_ctl(self.epoll_fd, operation, socket.get_socket_id(), &mut event)
+        };
+
+        if result == -1 {
+            let error = UdperfError::last_os_error("Failed to register socket at epoll instance");
+            error!("{}", error);
+            return Err(error);
+        }
+        Ok(())
+    }
+
+    // timeout = -1: Block until a socket is ready
+    pub fn wait(&mut self, timeout: i32) -> Result<(), UdperfError> {
+        let amount_events = unsafe {
+            libc::epoll_wait(self.epoll_fd, self.events.as_mut_ptr(), self.events.len() as i32, timeout)
+        };
+
+        if amount_events == -1 {
+            let error = UdperfError::last_os_error("Error occured executing epoll_wait()");
+            error!("{}", error);
+            Err(error)
+        } else if amount_events == 0 {
+            warn!("Epoll returned due to timeout");
+            Err(UdperfError::Timeout)
+        } else {
+            trace!("Epoll returned with {} events", amount_events);
+            for event in &self.events[..amount_events as usize] {
+                let index = event.u64 as usize;
+                if !self.ready_sockets.contains(&index) {
+                    self.ready_sockets.push_back(index);
+                }
+            }
+            Ok(())
+        }
+    }
+
+    // Returns the index of the next socket, which was reported as ready by a previous wait()
+    pub fn next_ready(&mut self) -> Option<usize> {
+        self.ready_sockets.pop_front()
+    }
+}
+
+impl Drop for Epoll {
+    fn drop(&mut self) {
+        unsafe { libc::close(self.epoll_fd) };
+    }
+}
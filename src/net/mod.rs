@@ -1,12 +1,16 @@
-use std::{net::Ipv4Addr, str::FromStr};
+use std::{net::{IpAddr, Ipv4Addr, Ipv6Addr, ToSocketAddrs}, str::FromStr};
 
-use log::warn;
+use log::{info, warn};
+use crate::UdperfError;
 
+pub mod control;
+pub mod epoll;
+pub mod sock_diag;
 pub mod socket;
 pub mod socket_options;
 
 #[repr(u64)]
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 #[allow(clippy::upper_case_acronyms)]
 pub enum MessageType {
     INIT,
@@ -14,21 +18,23 @@ pub enum MessageType {
     LAST
 }
 
-const LEN_HEADER: usize = std::mem::size_of::<MessageHeader>();
+pub const LEN_HEADER: usize = std::mem::size_of::<MessageHeader>();
 
 #[derive(Debug)]
 #[repr(transparent)]
 pub struct MessageHeader {
-    header: [u64; 3]
+    header: [u64; 5]
 }
 // First 8 bytes: MessageType
 // Second 8 bytes: Test ID
 // Third 8 bytes: Packet ID
+// Fourth 8 bytes: Transmit timestamp of the sender in nanoseconds since the UNIX epoch
+// Fifth 8 bytes: Send time on the monotonic clock of the sender in nanoseconds (only set in ping-pong mode)
 
 impl MessageHeader {
     pub fn new(mtype: MessageType, test_id: u64, packet_id: u64) -> MessageHeader {
         MessageHeader {
-            header: [mtype as u64, test_id, packet_id]
+            header: [mtype as u64, test_id, packet_id, 0, 0]
         }
     }
 
@@ -56,6 +62,34 @@ impl MessageHeader {
         }
     }
 
+    pub fn get_timestamp(buffer: &[u8]) -> u64 {
+        unsafe {
+            let header = std::mem::transmute::<&[u8], &[u64]>(buffer);
+            header[3]
+        }
+    }
+
+    pub fn set_timestamp_raw(buffer: &mut [u8], timestamp: u64) {
+        unsafe {
+            let header = std::mem::transmute::<&mut [u8], &mut [u64]>(buffer);
+            header[3] = timestamp;
+        }
+    }
+
+    pub fn get_send_time(buffer: &[u8]) -> u64 {
+        unsafe {
+            let header = std::mem::transmute::<&[u8], &[u64]>(buffer);
+            header[4]
+        }
+    }
+
+    pub fn set_send_time_raw(buffer: &mut [u8], send_time: u64) {
+        unsafe {
+            let header = std::mem::transmute::<&mut [u8], &mut [u64]>(buffer);
+            header[4] = send_time;
+        }
+    }
+
     pub fn get_test_id(buffer: &[u8]) -> u64 {
         unsafe {
             let header = std::mem::transmute::<&[u8], &[u64]>(buffer);
@@ -63,6 +97,13 @@ impl MessageHeader {
         }
     }
 
+    pub fn set_test_id_raw(buffer: &mut [u8], test_id: u64) {
+        unsafe {
+            let header = std::mem::transmute::<&mut [u8], &mut [u64]>(buffer);
+            header[1] = test_id;
+        }
+    }
+
     pub fn get_message_type(buffer: &[u8]) -> MessageType {
         unsafe {
             let header = std::mem::transmute::<&[u8], &[u64]>(buffer);
@@ -76,10 +117,30 @@ impl MessageHeader {
 }
 
 
-pub fn parse_ipv4(adress: &str) -> Result<Ipv4Addr, &'static str> {
-    match Ipv4Addr::from_str(adress) {
-        Ok(x) => Ok(x),
-        Err(_) => Err("Invalid IPv4 address!"),
+// Accepts IPv4 and IPv6 literals (optionally in brackets) as well as hostnames, which are resolved to the first returned address
+pub fn parse_ip(address: &str) -> Result<IpAddr, UdperfError> {
+    let literal = address.trim_start_matches('[').trim_end_matches(']');
+    if let Ok(x) = IpAddr::from_str(literal) {
+        return Ok(x);
+    }
+
+    match (address, 0).to_socket_addrs() {
+        Ok(mut addresses) => match addresses.next() {
+            Some(x) => {
+                info!("Resolved hostname {} to {}", address, x.ip());
+                Ok(x.ip())
+            },
+            None => Err(UdperfError::Other("Hostname could not be resolved to an IP address!")),
+        },
+        Err(_) => Err(UdperfError::Other("Invalid IP address or hostname!")),
+    }
+}
+
+// Returns the unspecified address of the same address family as the remote address, which is used to bind the local socket
+pub fn unspecified_address(remote: IpAddr) -> IpAddr {
+    match remote {
+        IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
     }
 }
 
@@ -0,0 +1,231 @@
+use log::{debug, error, info};
+
+use super::socket::Socket;
+use crate::UdperfError;
+
+// Constants and structs from linux/sock_diag.h and linux/inet_diag.h, which are not exported by libc
+const SOCK_DIAG_BY_FAMILY: u16 = 20;
+const INET_DIAG_SKMEMINFO: u16 = 7;
+const SK_MEMINFO_VARS: usize = libc::SK_MEMINFO_DROPS as usize + 1;
+const RESPONSE_BUFFER_SIZE: usize = 32768;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+struct InetDiagSockId {
+    idiag_sport: u16,
+    idiag_dport: u16,
+    idiag_src: [u32; 4],
+    idiag_dst: [u32; 4],
+    idiag_if: u32,
+    idiag_cookie: [u32; 2],
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+struct InetDiagReqV2 {
+    sdiag_family: u8,
+    sdiag_protocol: u8,
+    idiag_ext: u8,
+    pad: u8,
+    idiag_states: u32,
+    id: InetDiagSockId,
+}
+
+// Of struct inet_diag_msg only the inode is needed
+const INET_DIAG_MSG_LENGTH: usize = 72;
+const INET_DIAG_MSG_INODE_OFFSET: usize = 68;
+
+#[repr(C)]
+struct DiagRequest {
+    header: libc::nlmsghdr,
+    request: InetDiagReqV2,
+}
+
+// Memory of a socket, as reported by SK_MEMINFO
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SocketMemory {
+    pub rmem_alloc: u32,
+    pub drops: u32,
+}
+
+// Queries the memory of the sockets of a thread over a NETLINK_SOCK_DIAG socket.
+// UDP sockets are dumped per local port and identified by their inode, since with SO_REUSEPORT several sockets share a port.
+pub struct SockDiag {
+    netlink_fd: i32,
+    family: u8,
+    sockets: Vec<(u16, u32)>,
+    response: Vec<u8>,
+    sequence: u32,
+}
+
+impl SockDiag {
+    pub fn new(sockets: &[Socket], ipv6: bool) -> Result<SockDiag, UdperfError> {
+        let netlink_fd = unsafe { libc::socket(libc::AF_NETLINK, libc::SOCK_DGRAM | libc::SOCK_CLOEXEC, libc::NETLINK_SOCK_DIAG) };
+        if netlink_fd == -1 {
+            let error = UdperfError::last_os_error("Failed to create sock_diag netlink socket");
+            error!("{}", error);
+            return Err(error);
+        }
+
+        let sockets = sockets.iter().map(|socket| Ok((get_local_port(socket.get_socket_id())?, get_inode(socket.get_socket_id())?))).collect::<Result<Vec<_>, UdperfError>>()?;
+        info!("Created sock_diag netlink socket {} for the sockets (port, inode) {:?}", netlink_fd, sockets);
+
+        Ok(SockDiag {
+            netlink_fd,
+            family: if ipv6 { libc::AF_INET6 as u8 } else { libc::AF_INET as u8 },
+            sockets,
+            response: vec![0; RESPONSE_BUFFER_SIZE],
+            sequence: 0,
+        })
+    }
+
+    // Returns the memory of every socket in the order of the sockets. A socket, which wasn't found anymore (e.g. already closed), is reported as None.
+    pub fn query(&mut self) -> Result<Vec<Option<SocketMemory>>, UdperfError> {
+        let mut socket_memory = vec![None; self.sockets.len()];
+        for index in 0..self.sockets.len() {
+            // Sockets sharing a port are already returned by the dump of the previous socket
+            let (port, inode) = self.sockets[index];
+            if socket_memory[index].is_some() {
+                continue;
+            }
+            self.send_request(port)?;
+            for (response_inode, memory) in self.receive_response()? {
+                if let Some(position) = self.sockets.iter().position(|&(socket_port, socket_inode)| socket_port == port && socket_inode == response_inode) {
+                    socket_memory[position] = Some(memory);
+                }
+            }
+            debug!("Queried memory of socket with inode {}: {:?}", inode, socket_memory[index]);
+        }
+        Ok(socket_memory)
+    }
+
+    fn send_request(&mut self, port: u16) -> Result<(), UdperfError> {
+        self.sequence = self.sequence.wrapping_add(1);
+        let request = DiagRequest {
+            header: libc::nlmsghdr {
+                nlmsg_len: std::mem::size_of::<DiagRequest>() as u32,
+                nlmsg_type: SOCK_DIAG_BY_FAMILY,
+                nlmsg_flags: (libc::NLM_F_REQUEST | libc::NLM_F_DUMP) as u16,
+                nlmsg_seq: self.sequence,
+                nlmsg_pid: 0,
+            },
+            request: InetDiagReqV2 {
+                sdiag_family: self.family,
+                sdiag_protocol: libc::IPPROTO_UDP as u8,
+                idiag_ext: 1 << (INET_DIAG_SKMEMINFO - 1),
+                idiag_states: u32::MAX,
+                // The UDP dump only returns sockets with the given local port
+                id: InetDiagSockId { idiag_sport: port.to_be(), ..Default::default() },
+                ..Default::default()
+            },
+        };
+
+        let send_result = unsafe {
+            libc::send(self.netlink_fd, &request as *const _ as _, std::mem::size_of::<DiagRequest>(), 0)
+        };
+        if send_result == -1 {
+            let error = UdperfError::last_os_error("Failed to send sock_diag request");
+            error!("{}", error);
+            return Err(error);
+        }
+        Ok(())
+    }
+
+    // Parses the inet_diag messages of the dump, until the dump is done
+    fn receive_response(&mut self) -> Result<Vec<(u32, SocketMemory)>, UdperfError> {
+        let mut sockets = Vec::new();
+        loop {
+            let amount_received_bytes = unsafe {
+                libc::recv(self.netlink_fd, self.response.as_mut_ptr() as _, self.response.len(), 0)
+            };
+            if amount_received_bytes == -1 {
+                let error = UdperfError::last_os_error("Failed to receive sock_diag response");
+                error!("{}", error);
+                return Err(error);
+            }
+
+            let mut offset = 0;
+            while offset + std::mem::size_of::<libc::nlmsghdr>() <= amount_received_bytes as usize {
+                let header = unsafe { std::ptr::read_unaligned(self.response[offset..].as_ptr() as *const libc::nlmsghdr) };
+                let message_length = header.nlmsg_len as usize;
+                if message_length < std::mem::size_of::<libc::nlmsghdr>() || offset + message_length > amount_received_bytes as usize {
+                    return Err(UdperfError::Other("Malformed sock_diag response"));
+                }
+
+                match header.nlmsg_type as libc::c_int {
+                    libc::NLMSG_DONE => return Ok(sockets),
+                    libc::NLMSG_ERROR => {
+                        let errno = unsafe { std::ptr::read_unaligned(self.response[offset + std::mem::size_of::<libc::nlmsghdr>()..].as_ptr() as *const i32) };
+                        error!("sock_diag request failed with error {}", std::io::Error::from_raw_os_error(-errno));
+                        return Err(UdperfError::Other("sock_diag request failed"));
+                    },
+                    _ => {
+                        let message = &self.response[offset + std::mem::size_of::<libc::nlmsghdr>()..offset + message_length];
+                        if let Some(socket) = parse_diag_message(message) {
+                            sockets.push(socket);
+                        }
+                    }
+                }
+                offset += align(message_length);
+            }
+        }
+    }
+}
+
+impl Drop for SockDiag {
+    fn drop(&mut self) {
+        unsafe { libc::close(self.netlink_fd) };
+    }
+}
+
+// The attributes (struct rtattr) follow the inet_diag_msg, each aligned to 4 bytes
+fn parse_diag_message(message: &[u8]) -> Option<(u32, SocketMemory)> {
+    if message.len() < INET_DIAG_MSG_LENGTH {
+        return None;
+    }
+    let inode = u32::from_ne_bytes(message[INET_DIAG_MSG_INODE_OFFSET..INET_DIAG_MSG_INODE_OFFSET + 4].try_into().ok()?);
+
+    let mut offset = align(INET_DIAG_MSG_LENGTH);
+    while offset + 4 <= message.len() {
+        let attribute_length = u16::from_ne_bytes([message[offset], message[offset + 1]]) as usize;
+        let attribute_type = u16::from_ne_bytes([message[offset + 2], message[offset + 3]]);
+        if attribute_length < 4 || offset + attribute_length > message.len() {
+            break;
+        }
+
+        if attribute_type == INET_DIAG_SKMEMINFO && attribute_length >= 4 + SK_MEMINFO_VARS * 4 {
+            let meminfo = |index: libc::c_int| {
+                let start = offset + 4 + index as usize * 4;
+                u32::from_ne_bytes([message[start], message[start + 1], message[start + 2], message[start + 3]])
+            };
+            return Some((inode, SocketMemory { rmem_alloc: meminfo(libc::SK_MEMINFO_RMEM_ALLOC), drops: meminfo(libc::SK_MEMINFO_DROPS) }));
+        }
+        offset += align(attribute_length);
+    }
+    None
+}
+
+fn align(length: usize) -> usize {
+    (length + 3) & !3
+}
+
+fn get_local_port(socket: i32) -> Result<u16, UdperfError> {
+    let mut sockaddr: libc::sockaddr_storage = unsafe { std::mem::zeroed() };
+    let mut sockaddr_len = std::mem::size_of::<libc::sockaddr_storage>() as libc::socklen_t;
+    let result = unsafe { libc::getsockname(socket, &mut sockaddr as *mut _ as _, &mut sockaddr_len) };
+    if result == -1 {
+        return Err(UdperfError::last_os_error("Failed to get local address of socket"));
+    }
+
+    // The port is at the same offset in sockaddr_in and sockaddr_in6
+    let sockaddr_in = unsafe { *(&sockaddr as *const _ as *const libc::sockaddr_in) };
+    Ok(u16::from_be(sockaddr_in.sin_port))
+}
+
+fn get_inode(socket: i32) -> Result<u32, UdperfError> {
+    let mut stat: libc::stat = unsafe { std::mem::zeroed() };
+    if unsafe { libc::fstat(socket, &mut stat) } == -1 {
+        return Err(UdperfError::last_os_error("Failed to get inode of socket"));
+    }
+    Ok(stat.st_ino as u32)
+}
@@ -1,99 +1,106 @@
 
 use log::{debug, error, info, trace, warn};
-use std::{self, io::Error, mem::MaybeUninit, net::SocketAddrV4};
+use std::{self, mem::MaybeUninit, net::{Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6}};
 
 use super::socket_options::{self, SocketOptions};
+use crate::UdperfError;
 
 #[derive(Debug, Copy, Clone)]
 pub struct Socket {
-    sock_addr_in: Option<SocketAddrV4>,
-    sock_addr_out: Option<SocketAddrV4>,
+    sock_addr_in: Option<SocketAddr>,
+    sock_addr_out: Option<SocketAddr>,
     socket: i32,
+    ipv6: bool,
+    flow_label: Option<u32>,
     //sendmmsg_econnrefused_counter: u16
 } 
 
 impl Socket {
-    pub fn new(mut socket_options: SocketOptions) -> Option<Socket> {
-        let socket = Self::create_socket()?; 
+    pub fn new(mut socket_options: SocketOptions) -> Result<Socket, UdperfError> {
+        let socket = Self::create_socket(socket_options.ipv6)?; 
 
         socket_options.set_socket_options(socket).expect("Error updating socket options! Check your system configuration!");
 
-        Some(Socket {
+        Ok(Socket {
             sock_addr_in: None,
             sock_addr_out: None,
             socket,
+            ipv6: socket_options.ipv6,
+            flow_label: socket_options.flow_label,
             //sendmmsg_econnrefused_counter: 0
         })
     }
 
-    fn create_socket() -> Option<i32> {
-        let socket = unsafe { libc::socket(libc::AF_INET, libc::SOCK_DGRAM, 0) };
+    fn create_socket(ipv6: bool) -> Result<i32, UdperfError> {
+        let domain = if ipv6 { libc::AF_INET6 } else { libc::AF_INET };
+        let socket = unsafe { libc::socket(domain, libc::SOCK_DGRAM, 0) };
         if socket == -1 {
-            error!("Failed to create socket");
-            return None;
+            let error = UdperfError::last_os_error("Failed to create socket");
+            error!("{}", error);
+            return Err(error);
         }
         
         info!("Created socket: {:?}", socket);
-        Some(socket)
+        Ok(socket)
     }
 
 
-    pub fn connect(&mut self, sock_address: SocketAddrV4) -> Result<(), &'static str> {
-        self.sock_addr_out = Some(sock_address);
-        let sockaddr = Self::create_sockaddr(&self.sock_addr_out.expect("Outgoing socket address not set!"));
+    pub fn connect(&mut self, sock_address: SocketAddr) -> Result<(), UdperfError> {
+        self.sock_addr_out = Some(self.map_address(sock_address)?);
+        let (sockaddr, sockaddr_len) = Self::create_sockaddr(&self.sock_addr_out.expect("Outgoing socket address not set!"));
  
         let connect_result = unsafe {
             libc::connect(
                 self.socket,
                 &sockaddr as *const _ as _,
-                std::mem::size_of_val(&sockaddr) as libc::socklen_t
+                sockaddr_len
             )
         };
         debug!("'Connected' to remote host with result: {:?}", connect_result);
     
         if connect_result == -1 {
-            return Err("Failed to connect to remote host");
+            return Err(UdperfError::last_os_error("Failed to connect to remote host"));
         }
     
         Ok(())
     }
 
-    pub fn bind(&mut self, sock_address: SocketAddrV4) -> Result<(), &'static str> {
-        self.sock_addr_in = Some(sock_address);
-        let sockaddr = Self::create_sockaddr(&self.sock_addr_in.expect("Outgoing socket address not set!"));
-        debug!("Binding socket to {}:{}", sock_address, sockaddr.sin_port);
+    pub fn bind(&mut self, sock_address: SocketAddr) -> Result<(), UdperfError> {
+        self.sock_addr_in = Some(self.map_address(sock_address)?);
+        let (sockaddr, sockaddr_len) = Self::create_sockaddr(&self.sock_addr_in.expect("Outgoing socket address not set!"));
+        debug!("Binding socket to {}", sock_address);
     
         let bind_result = unsafe {
             libc::bind(
                 self.socket,
                 &sockaddr as *const _ as _,
-                std::mem::size_of_val(&sockaddr) as libc::socklen_t
+                sockaddr_len
             )
         };
     
         if bind_result == -1 {
-            return Err("Failed to bind socket to port");
+            return Err(UdperfError::last_os_error("Failed to bind socket to port"));
         }
     
         Ok(())
     }
 
-    pub fn close(&self) -> Result<(), &'static str> {
+    pub fn close(&self) -> Result<(), UdperfError> {
         let close_result = unsafe {
             libc::close(self.socket)
         };
     
         if close_result == -1 {
-            return Err("Failed to close socket");
+            return Err(UdperfError::last_os_error("Failed to close socket"));
         }
     
         Ok(())
     }
 
-    pub fn send(&self, buffer: &[u8], buffer_len: usize) -> Result<usize, &'static str> {
+    pub fn send(&self, buffer: &[u8], buffer_len: usize) -> Result<usize, UdperfError> {
         if buffer_len == 0 {
             error!("Buffer is empty");
-            return Err("Buffer is empty");
+            return Err(UdperfError::Other("Buffer is empty"));
         }
         debug!("Sending on socket {} with buffer size: {}", self.socket, buffer_len);
         trace!("Buffer: {:?}", buffer);
@@ -109,32 +116,21 @@ impl Socket {
     
         if send_result <= -1 {
             // CHeck for connection refused
-            let errno = Error::last_os_error();
-            match errno.raw_os_error() {
-                Some(libc::ECONNREFUSED) => {
-                    error!("Connection refused while trying to send data!");
-                    return Err("ECONNREFUSED");
-                },
-                Some(libc::EMSGSIZE) => {
-                    error!("EMSGSIZE while trying to send data! The message is too large for the transport protocol.");
-                    return Err("EMSGSIZE");
-                },
-                Some(libc::EAGAIN) => {
-                    debug!("Error EAGAIN/EWOULDBLOCK: Probably socket buffer is full!");
-                    return Err("EAGAIN");
-                },
-                _ => {
-                    error!("Errno when trying to send data: {}", errno);
-                    return Err("Failed to send data");
-                }
+            let error = UdperfError::last_os_error("Failed to send data with send()");
+            match error.errno() {
+                Some(libc::ECONNREFUSED) => error!("Connection refused while trying to send data!"),
+                Some(libc::EMSGSIZE) => error!("EMSGSIZE while trying to send data! The message is too large for the transport protocol."),
+                Some(libc::EAGAIN) => debug!("Error EAGAIN/EWOULDBLOCK: Probably socket buffer is full!"),
+                _ => error!("{}", error)
             }
+            return Err(error);
         }
     
         debug!("Sent datagram with {} bytes", send_result);
         Ok(send_result as usize)
     }
 
-    pub fn sendmsg(&self, msghdr: &libc::msghdr) -> Result<usize, &'static str> {
+    pub fn sendmsg(&self, msghdr: &libc::msghdr) -> Result<usize, UdperfError> {
         debug!("Trying to send message with msghdr length: {}, iov_len: {}", msghdr.msg_iovlen, unsafe {*msghdr.msg_iov}.iov_len);
         trace!("Trying to send message with iov_buffer: {:?}", unsafe { std::slice::from_raw_parts((*msghdr.msg_iov).iov_base as *const u8, (*msghdr.msg_iov).iov_len)});
 
@@ -147,28 +143,20 @@ impl Socket {
         };
     
         if send_result <= -1 {
-            let errno = Error::last_os_error();
-            match errno.raw_os_error() {
-                Some(libc::ECONNREFUSED) => {
-                    error!("Connection refused while trying to send data!");
-                    return Err("ECONNREFUSED");
-                },
-                Some(libc::EAGAIN) => {
-                    debug!("Error EAGAIN/EWOULDBLOCK: Probably socket buffer is full!");
-                    return Err("EAGAIN");
-                },
-                _ => {
-                    error!("Errno when trying to send data with sendmsg(): {}", errno);
-                    return Err("Failed to send data");
-                }
+            let error = UdperfError::last_os_error("Failed to send data with sendmsg()");
+            match error.errno() {
+                Some(libc::ECONNREFUSED) => error!("Connection refused while trying to send data!"),
+                Some(libc::EAGAIN) => debug!("Error EAGAIN/EWOULDBLOCK: Probably socket buffer is full!"),
+                _ => error!("{}", error)
             }
+            return Err(error);
         }
     
         debug!("Sent datagram(s) with {} bytes", send_result);
         Ok(send_result as usize)
     }
 
-    pub fn sendmmsg(&mut self, mmsgvec: &mut [libc::mmsghdr]) -> Result<usize, &'static str> {
+    pub fn sendmmsg(&mut self, mmsgvec: &mut [libc::mmsghdr]) -> Result<usize, UdperfError> {
         let send_result: i32 = unsafe {
             libc::sendmmsg(
                 self.socket,
@@ -179,21 +167,13 @@ impl Socket {
         };
     
         if send_result <= -1 {
-            let errno = Error::last_os_error();
-            match errno.raw_os_error() {
-                Some(libc::ECONNREFUSED) => {
-                    error!("Connection refused while trying to send data!");
-                    return Err("ECONNREFUSED");
-                },
-                Some(libc::EAGAIN) => {
-                    warn!("Error EGAIN/EWOULDBLOCK: Probably socket buffer is full!");
-                    return Err("EAGAIN");
-                },
-                _ => {
-                    error!("Errno when trying to send data with sendmmsg(): {}", errno);
-                    return Err("Failed to send data");
-                }
+            let error = UdperfError::last_os_error("Failed to send data with sendmmsg()");
+            match error.errno() {
+                Some(libc::ECONNREFUSED) => error!("Connection refused while trying to send data!"),
+                Some(libc::EAGAIN) => warn!("Error EGAIN/EWOULDBLOCK: Probably socket buffer is full!"),
+                _ => error!("{}", error)
             }
+            return Err(error);
         // sendmmsg() always returns 1, even when it should return ECONNREFUSED (when the receiver isn't up yet, similar to send()/sendmsg()). This is a workaround to detect ECONNREFUSED.
         // WARNING: sendmmsg_econnrefused_counter doesn't work under real load, because socket buffer is often full
         //} else if send_result == 1 && mmsgvec.len() > 1 {
@@ -210,7 +190,7 @@ impl Socket {
         Ok(send_result as usize)
     }
 
-    pub fn recvmmsg(&self, msgvec: &mut [libc::mmsghdr]) -> Result<usize, &'static str> {
+    pub fn recvmmsg(&self, msgvec: &mut [libc::mmsghdr]) -> Result<usize, UdperfError> {
         let timeout = std::ptr::null::<libc::timespec>() as *mut libc::timespec;
 
         let recv_result: i32 = unsafe {
@@ -224,23 +204,18 @@ impl Socket {
         };
 
         if recv_result <= -1 {
-            let errno = Error::last_os_error();
-            match errno.raw_os_error() {
-                Some(libc::EAGAIN) => {
-                    return Err("EAGAIN");
-                },
-                _ => {
-                    error!("Errno when trying to receive data with recvmmsg(): {}", errno);
-                    return Err("Failed to receive data!");
-                }
+            let error = UdperfError::last_os_error("Failed to receive data with recvmmsg()");
+            if error != UdperfError::WouldBlock {
+                error!("{}", error);
             }
+            return Err(error);
         }
 
         debug!("Received {} mmsghdr(s)", recv_result);
         Ok(recv_result as usize)
     }
 
-    pub fn recvmsg(&self, msghdr: &mut libc::msghdr) -> Result<usize, &'static str> {
+    pub fn recvmsg(&self, msghdr: &mut libc::msghdr) -> Result<usize, UdperfError> {
         debug!("Trying to receive message with msghdr length: {}, iov_len: {}, controllen: {}", msghdr.msg_iovlen, unsafe {*msghdr.msg_iov}.iov_len, msghdr.msg_controllen);
         trace!("Trying to receive message with iov_buffer: {:?}", unsafe { std::slice::from_raw_parts((*msghdr.msg_iov).iov_base as *const u8, (*msghdr.msg_iov).iov_len)});
 
@@ -253,25 +228,42 @@ impl Socket {
         };
     
         if recv_result <= -1 {
-            let errno = Error::last_os_error();
-            match errno.raw_os_error() {
-                // If no messages are available at the socket, the receive calls wait for a message to arrive, unless the socket is nonblocking (see fcntl(2)), in which case the value -1 is returned and the external variable errno is set to EAGAIN or EWOULDBLOCK.
-                // From: https://linux.die.net/man/2/recvmsg
-                Some(libc::EAGAIN) => {
-                    return Err("EAGAIN");
-                },
-                _ => {
-                    error!("Errno when trying to receive data with recvmsg(): {}", errno);
-                    return Err("Failed to receive data!");
-                }
+            let error = UdperfError::last_os_error("Failed to receive data with recvmsg()");
+            // If no messages are available at the socket, the receive calls wait for a message to arrive, unless the socket is nonblocking (see fcntl(2)), in which case the value -1 is returned and the external variable errno is set to EAGAIN or EWOULDBLOCK.
+            // From: https://linux.die.net/man/2/recvmsg
+            if error != UdperfError::WouldBlock {
+                error!("{}", error);
             }
+            return Err(error);
         } 
     
         debug!("Received {} bytes", recv_result);
         Ok(recv_result as usize)
     }
 
-    pub fn recv(&self, buffer: &mut [u8]) -> Result<usize, &'static str> {
+    // Reads one message of the error queue, which contains the transmit timestamps of the kernel. Never blocks, since the error queue is only drained after sending.
+    pub fn recv_errqueue(&self, msghdr: &mut libc::msghdr) -> Result<usize, UdperfError> {
+        let recv_result: isize = unsafe {
+            libc::recvmsg(
+                self.socket,
+                msghdr as *mut _ as _,
+                libc::MSG_ERRQUEUE | libc::MSG_DONTWAIT
+            )
+        };
+
+        if recv_result <= -1 {
+            let error = UdperfError::last_os_error("Failed to receive from error queue with recvmsg()");
+            if error != UdperfError::WouldBlock {
+                error!("{}", error);
+            }
+            return Err(error);
+        }
+
+        debug!("Received {} bytes from error queue", recv_result);
+        Ok(recv_result as usize)
+    }
+
+    pub fn recv(&self, buffer: &mut [u8]) -> Result<usize, UdperfError> {
         let recv_result: isize = unsafe {
             libc::recv(
                 self.socket,
@@ -282,61 +274,165 @@ impl Socket {
         };
 
         if recv_result <= -1 {
-            let errno = Error::last_os_error();
-            match errno.raw_os_error() {
-                Some(libc::EAGAIN) => {
-                    return Err("EAGAIN");
-                },
-                _ => {
-                    error!("Errno when trying to receive data with recv(): {}", errno);
-                    return Err("Failed to receive data!");
-                }
+            let error = UdperfError::last_os_error("Failed to receive data with recv()");
+            if error != UdperfError::WouldBlock {
+                error!("{}", error);
             }
+            return Err(error);
         } 
     
         debug!("Received {} bytes", recv_result);
         Ok(recv_result as usize)
     }
 
-    pub fn get_mss(&self) -> Result<u32, &'static str> {
-        socket_options::get_mss(self.socket)
+    pub fn recv_from(&self, buffer: &mut [u8]) -> Result<(usize, SocketAddr), UdperfError> {
+        let mut sockaddr: libc::sockaddr_storage = unsafe { MaybeUninit::zeroed().assume_init() };
+        let mut sockaddr_len = std::mem::size_of::<libc::sockaddr_storage>() as libc::socklen_t;
+
+        let recv_result: isize = unsafe {
+            libc::recvfrom(
+                self.socket,
+                buffer.as_mut_ptr() as *mut _,
+                buffer.len(),
+                0,
+                &mut sockaddr as *mut _ as _,
+                &mut sockaddr_len
+            )
+        };
+
+        if recv_result <= -1 {
+            let error = UdperfError::last_os_error("Failed to receive data with recvfrom()");
+            if error != UdperfError::WouldBlock {
+                error!("{}", error);
+            }
+            return Err(error);
+        }
+
+        match Self::parse_sockaddr(&sockaddr) {
+            Some(address) => {
+                debug!("Received {} bytes from {}", recv_result, address);
+                Ok((recv_result as usize, address))
+            },
+            None => Err(UdperfError::Other("Received data from unknown address family"))
+        }
+    }
+
+    // Returns the source address of the next datagram without removing it from the socket
+    pub fn peek_source(&self) -> Result<SocketAddr, UdperfError> {
+        let mut sockaddr: libc::sockaddr_storage = unsafe { MaybeUninit::zeroed().assume_init() };
+        let mut sockaddr_len = std::mem::size_of::<libc::sockaddr_storage>() as libc::socklen_t;
+        let mut buffer = [0_u8; 1];
+
+        let recv_result: isize = unsafe {
+            libc::recvfrom(
+                self.socket,
+                buffer.as_mut_ptr() as *mut _,
+                buffer.len(),
+                libc::MSG_PEEK,
+                &mut sockaddr as *mut _ as _,
+                &mut sockaddr_len
+            )
+        };
+
+        if recv_result <= -1 {
+            let error = UdperfError::last_os_error("Failed to peek data with recvfrom()");
+            error!("{}", error);
+            return Err(error);
+        }
+
+        Self::parse_sockaddr(&sockaddr).ok_or(UdperfError::Other("Received data from unknown address family"))
+    }
+
+    pub fn get_mss(&self) -> Result<u32, UdperfError> {
+        socket_options::get_mss(self.socket, self.ipv6)
     }
 
     pub fn get_socket_id(&self) -> i32 {
         self.socket
     }
 
-    pub fn set_sock_addr_out(&mut self, sock_address: SocketAddrV4) {
+    pub fn set_sock_addr_out(&mut self, sock_address: SocketAddr) -> Result<(), UdperfError> {
         if self.sock_addr_out.is_some() {
             warn!("Overwriting existing socket address {} with {} on socket {}!", self.sock_addr_out.unwrap(), sock_address, self.socket);
         }
 
-        self.sock_addr_out = Some(sock_address);
+        self.sock_addr_out = Some(self.map_address(sock_address)?);
+        Ok(())
     }
 
-    fn create_sockaddr(sock_address: &SocketAddrV4) -> libc::sockaddr_in {
-        // Convert Ipv4Addr to libc::in_addr
-        let addr = sock_address.ip(); 
-        let addr_u32 = u32::from_le_bytes(addr.octets());
- 
-        #[cfg(target_os = "linux")]
-        libc::sockaddr_in {
-            sin_family: libc::AF_INET as u16,
-            sin_port: sock_address.port().to_be(), // Convert to big endian
-            sin_addr: libc::in_addr { s_addr: addr_u32 },
-            sin_zero: [0; 8]
+    // IPv6 sockets need IPv4 addresses as IPv4-mapped IPv6 addresses. Additionally the flow label is attached to outgoing IPv6 addresses.
+    fn map_address(&self, sock_address: SocketAddr) -> Result<SocketAddr, UdperfError> {
+        if !self.ipv6 {
+            return match sock_address {
+                SocketAddr::V4(_) => Ok(sock_address),
+                SocketAddr::V6(_) => Err(UdperfError::Other("IPv6 address used on IPv4 socket")),
+            };
         }
+
+        let ip = match sock_address {
+            SocketAddr::V4(x) => x.ip().to_ipv6_mapped(),
+            SocketAddr::V6(x) => *x.ip(),
+        };
+
+        let flowinfo = match self.flow_label {
+            Some(flow_label) if !ip.is_unspecified() => {
+                socket_options::set_flow_label(self.socket, flow_label, ip)?;
+                flow_label
+            },
+            _ => 0
+        };
+
+        Ok(SocketAddr::V6(SocketAddrV6::new(ip, sock_address.port(), flowinfo, 0)))
     }
 
-    #[allow(clippy::manual_map)]
-    pub fn get_sockaddr_out(&self) -> Option<libc::sockaddr_in> {
-        if let Some(sock_addr) = &self.sock_addr_out {
-            Some(Self::create_sockaddr(sock_addr))
-        } else {
-            None
+    fn create_sockaddr(sock_address: &SocketAddr) -> (libc::sockaddr_storage, libc::socklen_t) {
+        let mut storage: libc::sockaddr_storage = unsafe { MaybeUninit::zeroed().assume_init() };
+
+        let len = match sock_address {
+            SocketAddr::V4(addr) => {
+                let sockaddr = libc::sockaddr_in {
+                    sin_family: libc::AF_INET as u16,
+                    sin_port: addr.port().to_be(), // Convert to big endian
+                    sin_addr: libc::in_addr { s_addr: u32::from_ne_bytes(addr.ip().octets()) },
+                    sin_zero: [0; 8]
+                };
+                unsafe { std::ptr::write(&mut storage as *mut _ as *mut libc::sockaddr_in, sockaddr) };
+                std::mem::size_of::<libc::sockaddr_in>()
+            },
+            SocketAddr::V6(addr) => {
+                let sockaddr = libc::sockaddr_in6 {
+                    sin6_family: libc::AF_INET6 as u16,
+                    sin6_port: addr.port().to_be(),
+                    sin6_flowinfo: addr.flowinfo().to_be(),
+                    sin6_addr: libc::in6_addr { s6_addr: addr.ip().octets() },
+                    sin6_scope_id: addr.scope_id(),
+                };
+                unsafe { std::ptr::write(&mut storage as *mut _ as *mut libc::sockaddr_in6, sockaddr) };
+                std::mem::size_of::<libc::sockaddr_in6>()
+            }
+        };
+
+        (storage, len as libc::socklen_t)
+    }
+
+    fn parse_sockaddr(sockaddr: &libc::sockaddr_storage) -> Option<SocketAddr> {
+        match sockaddr.ss_family as i32 {
+            libc::AF_INET => {
+                let addr = unsafe { *(sockaddr as *const _ as *const libc::sockaddr_in) };
+                Some(SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::from(u32::from_be(addr.sin_addr.s_addr)), u16::from_be(addr.sin_port))))
+            },
+            libc::AF_INET6 => {
+                let addr = unsafe { *(sockaddr as *const _ as *const libc::sockaddr_in6) };
+                Some(SocketAddr::V6(SocketAddrV6::new(Ipv6Addr::from(addr.sin6_addr.s6_addr), u16::from_be(addr.sin6_port), 0, addr.sin6_scope_id)))
+            },
+            _ => None
         }
     }
 
+    pub fn get_sockaddr_out(&self) -> Option<(libc::sockaddr_storage, libc::socklen_t)> {
+        self.sock_addr_out.as_ref().map(Self::create_sockaddr)
+    }
+
     pub unsafe fn create_fdset(&self) -> libc::fd_set {
         let mut fd_set: libc::fd_set = MaybeUninit::zeroed().assume_init();
         libc::FD_ZERO(&mut fd_set); 
@@ -353,7 +449,7 @@ impl Socket {
         pollfd_vec
     }
 
-    pub fn poll(&self, pollfd: &mut [libc::pollfd], timeout: i32) -> Result<(), &'static str> {
+    pub fn poll(&self, pollfd: &mut [libc::pollfd], timeout: i32) -> Result<(), UdperfError> {
         let poll_result = unsafe {
             libc::poll(
                 pollfd.as_mut_ptr(),
@@ -363,12 +459,13 @@ impl Socket {
         };
 
         if poll_result == -1 {
-            error!("Error occured executing poll(): {}", Error::last_os_error());
-            Err("Error occured executing poll()")
+            let error = UdperfError::last_os_error("Error occured executing poll()");
+            error!("{}", error);
+            Err(error)
         } else if poll_result == 0 {
             // Poll returned due to timeout
             warn!("Poll returned due to timeout");
-            Err("TIMEOUT")
+            Err(UdperfError::Timeout)
         } else {
             trace!("Poll returned with result: {}", poll_result);
             Ok(())
@@ -376,7 +473,7 @@ impl Socket {
     }
 
     // timeout = -1: Block until data is available
-    pub fn select(&self, read_fds: Option<*mut libc::fd_set>, write_fds: Option<*mut libc::fd_set>, timeout: i32) -> Result<(), &'static str> {
+    pub fn select(&self, read_fds: Option<*mut libc::fd_set>, write_fds: Option<*mut libc::fd_set>, timeout: i32) -> Result<(), UdperfError> {
         let nfds = self.socket + 1;
         let timeval =
             libc::timeval {
@@ -394,11 +491,12 @@ impl Socket {
             ) 
         };
         if result == -1 {
-            error!("Error calling select: {}", Error::last_os_error());
-            Err("Error calling select")
+            let error = UdperfError::last_os_error("Error calling select()");
+            error!("{}", error);
+            Err(error)
         } else if result == 0 {
             debug!("Select returned due to timeout");
-            Err("TIMEOUT")
+            Err(UdperfError::Timeout)
         } else {
             debug!("Select returned with result: {}", result);
             Ok(())
@@ -1,14 +1,15 @@
 use log::{error, info, debug};
-use serde::Serialize;
-use std::{fmt::Display, io::Error};
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
 use crate::util::statistic::serialize_option_as_bool;
+use crate::UdperfError;
 
 
-#[derive(PartialEq, Debug, Clone, Copy, Serialize)]
+#[derive(PartialEq, Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct SocketOptions {
     nonblocking: bool,
     ip_fragmentation: bool,
-    reuseport: bool,
+    pub reuseport: bool,
     #[serde(with = "serialize_option_as_bool")]
     gso: Option<u32>,
     pub gro: bool,
@@ -17,12 +18,19 @@ pub struct SocketOptions {
     recv_buffer_size: Option<u32>,
     #[serde(with = "serialize_option_as_bool")]
     send_buffer_size: Option<u32>,
-
+    pub ipv6: bool,
+    #[serde(with = "serialize_option_as_bool")]
+    hop_limit: Option<u32>,
+    #[serde(with = "serialize_option_as_bool")]
+    pub flow_label: Option<u32>,
+    pub rx_timestamps: bool,
+    pub tx_timestamps: bool,
+    pub rxq_overflow: bool,
 }
 
 impl SocketOptions {
     #[allow(clippy::too_many_arguments)]
-    pub fn new(nonblocking: bool, ip_fragmentation: bool, reuseport: bool, gso: Option<u32>, gro: bool, socket_pacing_rate: u64, recv_buffer_size: Option<u32>, send_buffer_size: Option<u32>) -> Self {
+    pub fn new(nonblocking: bool, ip_fragmentation: bool, reuseport: bool, gso: Option<u32>, gro: bool, socket_pacing_rate: u64, recv_buffer_size: Option<u32>, send_buffer_size: Option<u32>, ipv6: bool, hop_limit: Option<u32>, flow_label: Option<u32>, rx_timestamps: bool, tx_timestamps: bool, rxq_overflow: bool) -> Self {
         SocketOptions {
             nonblocking,
             ip_fragmentation,
@@ -32,19 +40,55 @@ impl SocketOptions {
             socket_pacing_rate,
             recv_buffer_size,
             send_buffer_size,
+            ipv6,
+            hop_limit,
+            flow_label,
+            rx_timestamps,
+            tx_timestamps,
+            rxq_overflow,
         }
     }
 
-    pub fn set_socket_options(&mut self, socket: i32) -> Result<(), &'static str> {
+    // Converts the socket options of a sender into the matching ones of a receiver, which is used in reverse mode
+    pub fn convert_to_receiver(&mut self) {
+        self.gro = self.gso.is_some();
+        self.gso = None;
+        self.socket_pacing_rate = 0;
+        self.tx_timestamps = false;
+    }
+
+    // Converts the socket options of a receiver into the matching ones of a sender, which is used in bidirectional mode
+    pub fn convert_to_sender(&mut self, datagram_size: u32) {
+        self.gso = if self.gro { Some(datagram_size) } else { None };
+        self.gro = false;
+        self.rx_timestamps = false;
+        self.rxq_overflow = false;
+    }
+
+    // Received msghdrs contain control messages, so msg_controllen has to be reset after every receive
+    pub fn receives_cmsg(&self) -> bool {
+        self.gro || self.rx_timestamps || self.rxq_overflow
+    }
+
+    pub fn set_socket_options(&mut self, socket: i32) -> Result<(), UdperfError> {
         debug!("Updating socket options with {:?}", self);
         set_reuseport(socket, self.reuseport)?;
 
         if self.nonblocking {
             set_nonblocking(socket)?;
         } 
+        if self.ipv6 {
+            set_dual_stack(socket)?;
+        }
         if !self.ip_fragmentation {
-            set_ip_fragmentation_off(socket)?;
+            set_ip_fragmentation_off(socket, self.ipv6)?;
         } 
+        if let Some(hop_limit) = self.hop_limit {
+            set_hop_limit(socket, hop_limit, self.ipv6)?;
+        }
+        if self.ipv6 && self.flow_label.is_some() {
+            set_flowinfo_send(socket)?;
+        }
         if let Some(size) = self.gso {
             set_gso(socket, size)?;
         }
@@ -55,6 +99,22 @@ impl SocketOptions {
 
         set_gro(socket, self.gro)?;
 
+        let mut timestamping_flags = 0;
+        if self.rx_timestamps {
+            timestamping_flags |= libc::SOF_TIMESTAMPING_RX_SOFTWARE | libc::SOF_TIMESTAMPING_SOFTWARE;
+        }
+        if self.tx_timestamps {
+            // OPT_ID adds a key to every timestamp, which identifies the send call. OPT_TSONLY omits the payload of the datagram in the error queue.
+            timestamping_flags |= libc::SOF_TIMESTAMPING_TX_SCHED | libc::SOF_TIMESTAMPING_TX_SOFTWARE | libc::SOF_TIMESTAMPING_SOFTWARE | libc::SOF_TIMESTAMPING_OPT_ID | libc::SOF_TIMESTAMPING_OPT_TSONLY;
+        }
+        if timestamping_flags != 0 {
+            set_timestamping(socket, timestamping_flags)?;
+        }
+
+        if self.rxq_overflow {
+            set_rxq_overflow(socket)?;
+        }
+
         if let Some(size) = self.send_buffer_size { 
             set_buffer_size(socket, size, libc::SO_SNDBUF)?;
         } else {
@@ -71,7 +131,7 @@ impl SocketOptions {
 }
 
 
-fn set_socket_option<T: Display>(socket: i32, level: libc::c_int, name: libc::c_int, value: T) -> Result<(), &'static str> {
+fn set_socket_option<T: Display>(socket: i32, level: libc::c_int, name: libc::c_int, value: T) -> Result<(), UdperfError> {
     let value_len = std::mem::size_of::<T>() as libc::socklen_t;
 
     let setsockopt_result = unsafe {
@@ -85,15 +145,16 @@ fn set_socket_option<T: Display>(socket: i32, level: libc::c_int, name: libc::c_
     };
 
     if setsockopt_result == -1 {
-        error!("errno when enabling socket option on socket: {}", Error::last_os_error());
-        return Err("Failed to enable socket option");
+        let error = UdperfError::last_os_error("Failed to enable socket option");
+        error!("{}", error);
+        return Err(error);
     }
 
     debug!("Enabled socket option on socket with value {}", value);
     Ok(())
 }
 
-fn get_socket_option(socket: i32, level: libc::c_int, name: libc::c_int) -> Result<u32, &'static str> {
+fn get_socket_option(socket: i32, level: libc::c_int, name: libc::c_int) -> Result<u32, UdperfError> {
     let ret = 0;
     let mut ret_len = std::mem::size_of_val(&ret) as libc::socklen_t;
 
@@ -108,43 +169,45 @@ fn get_socket_option(socket: i32, level: libc::c_int, name: libc::c_int) -> Resu
     };
 
     if getsockopt_result == -1 {
-        error!("errno when getting socket option: {}", Error::last_os_error());
-        Err("Failed to get socket option")
+        let error = UdperfError::last_os_error("Failed to get socket option");
+        error!("{}", error);
+        Err(error)
     } else {
         debug!("Got socket option on socket: {}", ret);
         Ok(ret)
     }
 }
 
-pub fn set_nonblocking(socket: i32) -> Result<(), &'static str> {    
+pub fn set_nonblocking(socket: i32) -> Result<(), UdperfError> {    
     let mut flags = unsafe { libc::fcntl(socket, libc::F_GETFL, 0) };
     if flags == -1 {
-        return Err("Failed to get socket flags");
+        return Err(UdperfError::last_os_error("Failed to get socket flags"));
     }
 
     flags |= libc::O_NONBLOCK;
 
     let fcntl_result = unsafe { libc::fcntl(socket, libc::F_SETFL, flags) };
     if fcntl_result == -1 {
-        return Err("Failed to set socket flags");
+        return Err(UdperfError::last_os_error("Failed to set socket flags"));
     }
     info!("Set socket to nonblocking mode");
     Ok(())
 }
 
-pub fn set_buffer_size(socket: i32, size: u32, buffer_type: libc::c_int) -> Result<(), &'static str> {
+pub fn set_buffer_size(socket: i32, size: u32, buffer_type: libc::c_int) -> Result<(), UdperfError> {
     let mut current_size = get_socket_option(socket, libc::SOL_SOCKET, buffer_type)?; 
     match buffer_type {
         libc::SO_SNDBUF => info!("Set send buffer size from {} to {}", current_size, size),
         libc::SO_RCVBUF => info!("Set receive buffer size from {} to {}", current_size, size),
-        _ => return Err("Invalid buffer type")
+        _ => return Err(UdperfError::Other("Invalid buffer type"))
     }
 
     match set_socket_option(socket, libc::SOL_SOCKET, buffer_type, size) {
         Ok(_) => {
             current_size = get_socket_option(socket, libc::SOL_SOCKET, buffer_type)?; 
             if current_size < size * 2 {
-                Err(format!("Planned buffer size {} (Buffer size is always allocated times 2 by linux) is smaller than current buffer size {} after trying to set it. Setting buffer size failed...", size * 2, current_size).leak())
+                error!("Planned buffer size {} (Buffer size is always allocated times 2 by linux) is smaller than current buffer size {} after trying to set it. Check net.core.rmem_max/wmem_max", size * 2, current_size);
+                Err(UdperfError::Other("Setting buffer size failed"))
             } else {
                 Ok(())
             }
@@ -153,42 +216,136 @@ pub fn set_buffer_size(socket: i32, size: u32, buffer_type: libc::c_int) -> Resu
     }
 }
 
-fn set_gso(socket: i32, gso_size: u32) -> Result<(), &'static str> {
+fn set_gso(socket: i32, gso_size: u32) -> Result<(), UdperfError> {
     // gso_size should be equal to MSS = ETH_MSS - header(ipv4) - header(udp)
     info!("Set socket option GSO to {}", gso_size);
     set_socket_option(socket, libc::SOL_UDP, libc::UDP_SEGMENT, gso_size)
 }
 
-fn set_gro(socket: i32, status: bool) -> Result<(), &'static str> {
+fn set_gro(socket: i32, status: bool) -> Result<(), UdperfError> {
     let value: u32 = if status { 1 } else { 0 };
     info!("Set socket option GRO to {}", status);
     set_socket_option(socket, libc::SOL_UDP, libc::UDP_GRO, value)
 }
 
-fn set_ip_fragmentation_off(socket: i32) -> Result<(), &'static str> {
+// Receive timestamps are taken by the kernel when the datagram is received by the network stack and reported in a SCM_TIMESTAMPING control message with recvmsg/recvmmsg.
+// Transmit timestamps are taken when the datagram enters the qdisc (TX_SCHED) and when it is passed to the driver (TX_SOFTWARE). They are reported in the error queue of the socket.
+fn set_timestamping(socket: i32, flags: libc::c_uint) -> Result<(), UdperfError> {
+    info!("Set socket option SO_TIMESTAMPING to {:#x}", flags);
+    set_socket_option(socket, libc::SOL_SOCKET, libc::SO_TIMESTAMPING, flags)
+}
+
+// The kernel adds the amount of datagrams, which were dropped because the receive queue of the socket was full, as control message to every received datagram after the first drop
+fn set_rxq_overflow(socket: i32) -> Result<(), UdperfError> {
+    info!("Set socket option SO_RXQ_OVFL to true");
+    set_socket_option(socket, libc::SOL_SOCKET, libc::SO_RXQ_OVFL, 1)
+}
+
+fn set_ip_fragmentation_off(socket: i32, ipv6: bool) -> Result<(), UdperfError> {
     info!("Set socket to no IP fragmentation");
-    set_socket_option(socket, libc::IPPROTO_IP, libc::IP_MTU_DISCOVER, libc::IP_PMTUDISC_DO)
+    if ipv6 {
+        // IPv6 is never fragmented by routers, but the local stack would still fragment datagrams larger than the path MTU
+        set_socket_option(socket, libc::IPPROTO_IPV6, libc::IPV6_MTU_DISCOVER, libc::IPV6_PMTUDISC_DO)?;
+        set_socket_option(socket, libc::IPPROTO_IPV6, libc::IPV6_DONTFRAG, 1)
+    } else {
+        set_socket_option(socket, libc::IPPROTO_IP, libc::IP_MTU_DISCOVER, libc::IP_PMTUDISC_DO)
+    }
+}
+
+// Allow IPv4 traffic on IPv6 sockets with IPv4-mapped addresses, independent of the net.ipv6.bindv6only sysctl
+fn set_dual_stack(socket: i32) -> Result<(), UdperfError> {
+    info!("Set socket option IPV6_V6ONLY to false");
+    set_socket_option(socket, libc::IPPROTO_IPV6, libc::IPV6_V6ONLY, 0)
+}
+
+fn set_hop_limit(socket: i32, hop_limit: u32, ipv6: bool) -> Result<(), UdperfError> {
+    info!("Set socket option hop limit to {}", hop_limit);
+    if ipv6 {
+        set_socket_option(socket, libc::IPPROTO_IPV6, libc::IPV6_UNICAST_HOPS, hop_limit)
+    } else {
+        set_socket_option(socket, libc::IPPROTO_IP, libc::IP_TTL, hop_limit)
+    }
+}
+
+fn set_flowinfo_send(socket: i32) -> Result<(), UdperfError> {
+    info!("Set socket option IPV6_FLOWINFO_SEND");
+    set_socket_option(socket, libc::IPPROTO_IPV6, libc::IPV6_FLOWINFO_SEND, 1)
+}
+
+// struct in6_flowlabel_req from linux/in6.h, which is not exported by libc
+#[repr(C)]
+struct In6FlowlabelReq {
+    flr_dst: libc::in6_addr,
+    flr_label: u32,
+    flr_action: u8,
+    flr_share: u8,
+    flr_flags: u16,
+    flr_expires: u16,
+    flr_linger: u16,
+    flr_pad: u32,
 }
 
-pub fn get_mss(socket: i32) -> Result<u32, &'static str> {
+const IPV6_FL_A_GET: u8 = 0;
+const IPV6_FL_S_ANY: u8 = 255;
+const IPV6_FL_F_CREATE: u16 = 1;
+
+// The kernel only accepts flow labels in the destination address, after the socket acquired a lease for the label and destination
+pub fn set_flow_label(socket: i32, flow_label: u32, destination: std::net::Ipv6Addr) -> Result<(), UdperfError> {
+    let request = In6FlowlabelReq {
+        flr_dst: libc::in6_addr { s6_addr: destination.octets() },
+        flr_label: flow_label.to_be(),
+        flr_action: IPV6_FL_A_GET,
+        flr_share: IPV6_FL_S_ANY,
+        flr_flags: IPV6_FL_F_CREATE,
+        flr_expires: 0,
+        flr_linger: 0,
+        flr_pad: 0,
+    };
+
+    let setsockopt_result = unsafe {
+        libc::setsockopt(
+            socket,
+            libc::IPPROTO_IPV6,
+            libc::IPV6_FLOWLABEL_MGR,
+            &request as *const _ as _,
+            std::mem::size_of::<In6FlowlabelReq>() as libc::socklen_t
+        )
+    };
+
+    if setsockopt_result == -1 {
+        let error = UdperfError::last_os_error("Failed to request flow label");
+        error!("{} {:#x} for {}", error, flow_label, destination);
+        return Err(error);
+    }
+
+    info!("Acquired flow label {:#x} for destination {}", flow_label, destination);
+    Ok(())
+}
+
+pub fn get_mss(socket: i32, ipv6: bool) -> Result<u32, UdperfError> {
     // https://man7.org/linux/man-pages/man7/ip.7.html
     // MSS from TCP returned an error
-    match get_socket_option(socket, libc::IPPROTO_IP, libc::IP_MTU) {
-        Ok(mtu) => Ok(mtu - 20 - 8), // Return MSS instead of MTU
-        Err(_) => Err("Failed to get MSS")
-    }
+    let result = if ipv6 {
+        get_socket_option(socket, libc::IPPROTO_IPV6, libc::IPV6_MTU).map(|mtu| mtu - 40 - 8)
+    } else {
+        get_socket_option(socket, libc::IPPROTO_IP, libc::IP_MTU).map(|mtu| mtu - 20 - 8)
+    };
+
+    // Return MSS instead of MTU
+    result
+
 }
 
-pub fn set_socket_pacing(socket: i32, pacing_rate: u64) -> Result<(), &'static str> {
+pub fn set_socket_pacing(socket: i32, pacing_rate: u64) -> Result<(), UdperfError> {
     info!("Set socket option pacing to for current socket to {}B/s", pacing_rate);
     set_socket_option(socket, libc::SOL_SOCKET, libc::SO_MAX_PACING_RATE, pacing_rate)
 }
 
-pub fn _get_gso_size(socket: i32) -> Result<u32, &'static str> {
+pub fn _get_gso_size(socket: i32) -> Result<u32, UdperfError> {
     get_socket_option(socket, libc::SOL_UDP, libc::UDP_SEGMENT)
 }
 
-pub fn set_reuseport(socket: i32, status: bool) -> Result<(), &'static str> {
+pub fn set_reuseport(socket: i32, status: bool) -> Result<(), UdperfError> {
     let value: u32 = if status { 1 } else { 0 };
     info!("Set socket option REUSEPORT to {}", status);
     set_socket_option(socket, libc::SOL_SOCKET, libc::SO_REUSEPORT, value)
@@ -1,9 +1,10 @@
 use crate::util::{statistic::Statistic, IOModel};
+use crate::UdperfError;
 
 pub mod sender;
 pub mod receiver;
 
 pub trait Node {
-    fn run(&mut self, io_model: IOModel) -> Result<(Statistic, Vec<Statistic>), &'static str>;
-    fn io_wait(&mut self, io_model: IOModel) -> Result<(), &'static str>;
+    fn run(&mut self, io_model: IOModel) -> Result<(Statistic, Vec<Statistic>), UdperfError>;
+    fn io_wait(&mut self, io_model: IOModel) -> Result<(), UdperfError>;
 }
@@ -1,5 +1,8 @@
-use std::net::SocketAddrV4;
+use std::collections::HashMap;
+use std::net::SocketAddr;
 use std::os::fd::RawFd;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
 use std::thread::{self, sleep};
 use std::time::Instant;
 use log::{debug, error, info, trace, warn};
@@ -11,57 +14,106 @@ use crate::io_uring::provided_buffer::IoUringProvidedBuffer;
 use crate::io_uring::{parse_received_bytes, IoUringOperatingModes, UringMode};
 use crate::util::msghdr_vec::MsghdrVec;
 use crate::util::packet_buffer::PacketBuffer;
-use crate::net::{socket::Socket, MessageHeader, MessageType};
+use crate::util::payload;
+use crate::net::{self, epoll::Epoll, sock_diag::SockDiag, socket::Socket, MessageHeader, MessageType};
 use crate::util::{self, statistic::*, ExchangeFunction, IOModel};
 use super::Node;
+use crate::UdperfError;
 
 const INITIAL_POLL_TIMEOUT: i32 = 10000; // in milliseconds
+const ANNOUNCE_INTERVAL: i32 = 1000; // in milliseconds
 const IN_MEASUREMENT_POLL_TIMEOUT: i32 = 1000; // in milliseconds
+const SOCKET_MEMORY_SAMPLE_INTERVAL: u64 = 100; // in milliseconds
 
 pub struct Receiver {
     packet_buffer: PacketBuffer,
-    socket: Socket,
+    // With the epoll IO model a thread can receive on multiple sockets
+    sockets: Vec<Socket>,
+    current_socket: usize,
+    epoll: Option<Epoll>,
     io_uring_sqpoll_fd: Option<RawFd>,
-    next_packet_id: u64,
     parameter: Parameter,
-    measurements: Vec<Measurement>,
+    measurements: HashMap<usize, Measurement>,
+    expected_payload: Option<Vec<u8>>,
+    // Drop counters of the sockets (SO_RXQ_OVFL and SK_MEMINFO) and the peak receive memory since the last interval
+    socket_drops: Vec<u32>,
+    reported_socket_drops: u64,
+    socket_rmem_peak: u32,
+    sock_diag: Option<SockDiag>,
+    last_sock_diag_instant: Instant,
     statistic_interval: StatisticInterval,
-    exchange_function: ExchangeFunction
+    exchange_function: ExchangeFunction,
+    stop_measurement: Arc<AtomicBool>
 }
 
 impl Receiver {
-    pub fn new(sock_address_in: SocketAddrV4, socket: Option<Socket>, io_uring: Option<RawFd>, parameter: Parameter) -> Receiver {
+    pub fn new(sock_address_in: SocketAddr, socket: Option<Socket>, io_uring: Option<RawFd>, stop_measurement: Arc<AtomicBool>, interval_reporter: Option<mpsc::Sender<Statistic>>, parameter: Parameter) -> Result<Receiver, UdperfError> {
         let socket = if let Some(socket) = socket {
             socket
+        } else if parameter.reverse {
+            // In reverse mode sock_address_in is the address of the remote sender, which is waiting for a datagram to learn the address of this receiver
+            let mut socket: Socket = Socket::new(parameter.socket_options)?;
+            socket.bind(SocketAddr::new(net::unspecified_address(sock_address_in.ip()), 0))?;
+            socket.connect(sock_address_in)?;
+            Self::announce(&socket)?;
+            socket
         } else {
-            let mut socket: Socket = Socket::new(parameter.socket_options).expect("Error creating socket");
-            socket.bind(sock_address_in).expect("Error binding to local port");
+            let mut socket: Socket = Socket::new(parameter.socket_options)?;
+            socket.bind(sock_address_in)?;
             socket
         };
 
-        info!("Current mode 'receiver' listening on {}:{} with socketID {}", sock_address_in.ip(), sock_address_in.port(), socket.get_socket_id());
+        if parameter.reverse {
+            info!("Current mode 'receiver' (reverse) receiving from remote host {} with socketID {}", sock_address_in, socket.get_socket_id());
+        } else {
+            info!("Current mode 'receiver' listening on {} with socketID {}", sock_address_in, socket.get_socket_id());
+        }
+
+        // The additional sockets listen on the ports following the port of the first socket
+        let mut sockets = vec![socket];
+        for port_offset in 1..parameter.sockets_per_thread {
+            let sock_address = SocketAddr::new(sock_address_in.ip(), sock_address_in.port() + port_offset);
+            let mut socket: Socket = Socket::new(parameter.socket_options)?;
+            socket.bind(sock_address)?;
+            info!("Current mode 'receiver' listening on {} with socketID {}", sock_address, socket.get_socket_id());
+            sockets.push(socket);
+        }
+
+        let epoll = if parameter.io_model == IOModel::Epoll {
+            let epoll = Epoll::new(parameter.epoll_edge_triggered, parameter.epoll_exclusive)?;
+            for (index, socket) in sockets.iter().enumerate() {
+                epoll.add(socket, libc::EPOLLIN, index)?;
+            }
+            Some(epoll)
+        } else {
+            None
+        };
         let packet_buffer = PacketBuffer::new(MsghdrVec::new(parameter.packet_buffer_size, parameter.mss, parameter.datagram_size as usize).with_cmsg_buffer());
+        let expected_payload = if parameter.payload_verification { Some(payload::create_payload(&parameter)?) } else { None };
+        let sock_diag = if parameter.socket_options.rxq_overflow { Some(SockDiag::new(&sockets, parameter.socket_options.ipv6)?) } else { None };
+        let socket_drops = vec![0; sockets.len()];
 
-        Receiver {
+        Ok(Receiver {
             packet_buffer,
-            socket,
+            sockets,
+            current_socket: 0,
+            epoll,
             io_uring_sqpoll_fd: io_uring,
-            next_packet_id: 0,
             parameter: parameter.clone(),
-            measurements: Vec::new(),
-            statistic_interval: StatisticInterval::new(Instant::now(), parameter.output_interval, parameter.test_runtime_length),
-            exchange_function: parameter.exchange_function
-        }
+            measurements: HashMap::new(),
+            expected_payload,
+            socket_drops,
+            reported_socket_drops: 0,
+            socket_rmem_peak: 0,
+            sock_diag,
+            last_sock_diag_instant: Instant::now(),
+            statistic_interval: StatisticInterval::new(Instant::now(), parameter.output_interval, parameter.test_runtime_length, interval_reporter),
+            exchange_function: parameter.exchange_function,
+            stop_measurement
+        })
     }
 
-    fn recv_messages(&mut self) -> Result<(), &'static str> {
-        // The msg_controllen field should be the same for all messages, since it should only contain the GRO enabled control message.
-        // It is only reset after the first message, since the first message is the INIT message, which doesn't contain any control messages.
-
-        if self.parameter.socket_options.gro && self.next_packet_id == 0 {
-            self.packet_buffer.reset_msghdr_fields();
-        }
-
+    fn recv_messages(&mut self) -> Result<(), UdperfError> {
         match self.exchange_function {
             ExchangeFunction::Normal => self.recv(),
             ExchangeFunction::Msg => self.recvmsg(),
@@ -70,24 +122,21 @@ impl Receiver {
     }
 
     #[inline(always)]
-    fn recv(&mut self) -> Result<(), &'static str> {
+    fn recv(&mut self) -> Result<(), UdperfError> {
         // Only one buffer is used, so we can directly access the first element
         let buffer_pointer = self.packet_buffer.get_buffer_pointer_from_index(0).unwrap();
 
-        match self.socket.recv(buffer_pointer) {
+        match self.sockets[self.current_socket].recv(buffer_pointer) {
             Ok(amount_received_bytes) => {
-                let test_id = MessageHeader::get_test_id(buffer_pointer) as usize;
-                let mtype = MessageHeader::get_message_type(buffer_pointer);
-                debug!("Received packet with test id: {}", test_id);
-
-                Self::parse_message_type(mtype, test_id, &mut self.measurements, &self.parameter)?;
-
-                let statistic = &mut self.measurements.get_mut(test_id).expect("Error getting statistic: test id not found").statistic;
-                let datagram_size = self.packet_buffer.datagram_size();
-                let amount_received_packets = util::process_packet_buffer(self.packet_buffer.get_buffer_pointer_from_index(0).unwrap(), datagram_size, self.next_packet_id, statistic);
-                self.next_packet_id += amount_received_packets;
-                statistic.amount_datagrams += amount_received_packets;
-                statistic.amount_data_bytes += amount_received_bytes;
+                if self.parameter.ping_pong && Self::is_measurement_message(buffer_pointer) {
+                    Self::echo(&self.sockets[self.current_socket], &buffer_pointer[..amount_received_bytes])?;
+                }
+
+                // recv() doesn't return the GRO segment size, so the coalesced datagrams are assumed to have the datagram size
+                let segment_size = if self.parameter.socket_options.gro { Some(self.packet_buffer.datagram_size()) } else { None };
+                let buffer_pointer = self.packet_buffer.get_buffer_pointer_from_index(0).unwrap();
+                let amount_received_packets = util::process_packet_buffer(&buffer_pointer[..amount_received_bytes], segment_size, &mut self.measurements, self.expected_payload.as_deref())?;
+                debug!("Received {} packets and total {} Bytes", amount_received_packets, amount_received_bytes);
                 Ok(())
             },
             Err(x) => Err(x)
@@ -95,135 +144,73 @@ impl Receiver {
     }
 
     #[inline(always)]
-    fn recvmsg(&mut self) -> Result<(), &'static str> {
+    fn recvmsg(&mut self) -> Result<(), UdperfError> {
         // Only one buffer is used, so we can directly access the first element
         let msghdr = self.packet_buffer.get_msghdr_from_index(0).unwrap();
 
-        match self.socket.recvmsg(msghdr) {
+        match self.sockets[self.current_socket].recvmsg(msghdr) {
             Ok(amount_received_bytes) => {
-                let buffer_pointer = self.packet_buffer.get_buffer_pointer_from_index(0).unwrap();
-                let test_id = MessageHeader::get_test_id(buffer_pointer) as usize;
-                let mtype = MessageHeader::get_message_type(buffer_pointer);
-        
-                Self::parse_message_type(mtype, test_id, &mut self.measurements, &self.parameter)?;
-        
+                let measurement_message = Self::is_measurement_message(self.packet_buffer.get_buffer_pointer_from_index(0).unwrap());
                 let msghdr = self.packet_buffer.get_msghdr_from_index(0).unwrap();
-                let statistic = &mut self.measurements.get_mut(test_id).expect("Error getting statistic: test id not found").statistic;
-                let absolut_packets_received;
-                (self.next_packet_id, absolut_packets_received) = util::process_packet_msghdr(msghdr, amount_received_bytes, self.next_packet_id, statistic);
-                statistic.amount_datagrams += absolut_packets_received;
-                statistic.amount_data_bytes += amount_received_bytes;
+                if self.parameter.ping_pong && measurement_message {
+                    Self::echo_msghdr(&self.sockets[self.current_socket], msghdr, amount_received_bytes)?;
+                }
+
+                let result = util::process_packet_msghdr(msghdr, amount_received_bytes, &mut self.measurements, self.expected_payload.as_deref(), &mut self.socket_drops[self.current_socket]);
 
                 // Reset msg_flags and msg_controllen fields
-                if self.parameter.socket_options.gro {
+                if self.parameter.socket_options.receives_cmsg() {
                     msghdr.msg_flags = 0;
                     msghdr.msg_controllen = crate::LENGTH_MSGHDR_CONTROL_MESSAGE_BUFFER;
                 }
 
-                debug!("Received {} packets and total {} Bytes, and next packet id should be {}", absolut_packets_received, amount_received_bytes, self.next_packet_id);
-                Ok(())
+                debug!("Received {:?} packets and total {} Bytes", result, amount_received_bytes);
+                result.map(|_| ())
             },
             Err(x) => Err(x)
         }
     }
 
     #[inline(always)]
-    fn recvmmsg(&mut self) -> Result<(), &'static str> {
-        match self.socket.recvmmsg(&mut self.packet_buffer.mmsghdr_vec) {
+    fn recvmmsg(&mut self) -> Result<(), UdperfError> {
+        match self.sockets[self.current_socket].recvmmsg(&mut self.packet_buffer.mmsghdr_vec) {
             Ok(amount_received_mmsghdr) => { 
                 if amount_received_mmsghdr == 0 {
                     debug!("No packets received during this recvmmsg call");
                     return Ok(());
                 }
 
-                let test_id = MessageHeader::get_test_id(self.packet_buffer.get_buffer_pointer_from_index(0).unwrap()) as usize;
-                let mtype = MessageHeader::get_message_type(self.packet_buffer.get_buffer_pointer_from_index(0).unwrap());
-                let amount_received_bytes = util::get_total_bytes(&self.packet_buffer.mmsghdr_vec, amount_received_mmsghdr);
-
-                Self::parse_message_type(mtype, test_id, &mut self.measurements, &self.parameter)?;
-
-                let statistic = &mut self.measurements.get_mut(test_id).expect("Error getting statistic: test id not found").statistic;
-                let mut absolut_datagrams_received = 0;
+                if self.parameter.ping_pong && Self::is_measurement_message(self.packet_buffer.get_buffer_pointer_from_index(0).unwrap()) {
+                    Self::echo_mmsghdr(&mut self.sockets[self.current_socket], &mut self.packet_buffer.mmsghdr_vec[..amount_received_mmsghdr])?;
+                }
 
-                // Check and calculate the amount of received packets and bytes
-                for (index, mmsghdr) in self.packet_buffer.mmsghdr_vec.iter_mut().enumerate() {
-                    if index >= amount_received_mmsghdr {
-                        break;
-                    }
-                    let msghdr = &mut mmsghdr.msg_hdr;
+                // The msghdrs of a single call can contain the datagrams of different flows
+                let mut result = Ok(0);
+                for mmsghdr in self.packet_buffer.mmsghdr_vec.iter_mut().take(amount_received_mmsghdr) {
                     let msghdr_bytes = mmsghdr.msg_len as usize;
+                    let msghdr = &mut mmsghdr.msg_hdr;
 
-                    let datagrams_received;
-                    (self.next_packet_id, datagrams_received) = util::process_packet_msghdr(msghdr, msghdr_bytes, self.next_packet_id, statistic);
-                    absolut_datagrams_received += datagrams_received;
+                    result = util::combine_packet_results(result, util::process_packet_msghdr(msghdr, msghdr_bytes, &mut self.measurements, self.expected_payload.as_deref(), &mut self.socket_drops[self.current_socket]));
                                     
-                    if self.parameter.socket_options.gro {
+                    if self.parameter.socket_options.receives_cmsg() {
                         msghdr.msg_flags = 0;
                         msghdr.msg_controllen = crate::LENGTH_MSGHDR_CONTROL_MESSAGE_BUFFER;
                     }
                 }
 
-                statistic.amount_datagrams += absolut_datagrams_received;
-                statistic.amount_data_bytes += amount_received_bytes;
-                trace!("Sent {} msg_hdr to remote host", amount_received_mmsghdr);
-                Ok(())
+                trace!("Received {} msg_hdr from remote host", amount_received_mmsghdr);
+                result.map(|_| ())
             },
             Err(x) => Err(x)
         }
     }
 
-    fn parse_message_type(mtype: MessageType, test_id: usize, measurements: &mut Vec<Measurement>, parameter: &Parameter) -> Result<(), &'static str> {
-        if test_id >= crate::MAX_TEST_ID {
-            error!("Received test id is greater than the maximum test id: {} > {}!", test_id, crate::MAX_TEST_ID);
-            return Err("Received test id is greater than the maximum test id")
-        }
-        match mtype {
-            MessageType::INIT => {
-                info!("{:?}: INIT packet received from test {}!", thread::current().id(), test_id);
-                // Resize the vector if neeeded, and create a new measurement struct
-                if measurements.len() <= test_id {
-                    measurements.resize(test_id + 1, Measurement::new(parameter.clone()));
-                }
-                Err("INIT_MESSAGE_RECEIVED")
-            },
-            MessageType::MEASUREMENT => { 
-                let measurement = if let Some(x) = measurements.get_mut(test_id) {
-                    x
-                } else {
-                    // No INIT message received before, so we need to resize and create/add a new measurement struct
-                    if measurements.len() <= test_id {
-                        measurements.resize(test_id + 1, Measurement::new(parameter.clone()));
-                    }
-                    measurements.get_mut(test_id).expect("Error getting statistic in measurement message: test id not found")
-                };
-                // Start measurement timer with receiving of the first MEASUREMENT message
-                if !measurement.first_packet_received {
-                    info!("{:?}: First packet received from test {}!", thread::current().id(), test_id);
-                    let start_time = Statistic::get_unix_timestamp();
-                    measurement.start_time = start_time;
-                    measurement.statistic.set_start_timestamp(Some(start_time));
-                    measurement.first_packet_received = true;
-                }
-                Ok(())
-            },
-            MessageType::LAST => {
-                info!("{:?}: LAST packet received from test {}!", thread::current().id(), test_id);
-                // Not checking for measurement length anymore, since we assume that the thread has received at least one MEASUREMENT message before
-                let measurement = measurements.get_mut(test_id).expect("Error getting statistic in last message: test id not found");
-                // Convert to from milliseconds to seconds
-                let end_time = Statistic::get_unix_timestamp() - (crate::WAIT_CONTROL_MESSAGE as f64 / 1000.0); // REMOVE THIS, if you remove the sleep in the sender, before sending last message, as well
-                measurement.last_packet_received = true;
-                measurement.statistic.set_test_duration(Some(measurement.start_time), Some(end_time));
-                measurement.statistic.calculate_statistics();
-                measurement.statistic.set_end_timestamp();
-                Err("LAST_MESSAGE_RECEIVED")
-            }
-        }
+    // Only MEASUREMENT messages are echoed in ping-pong mode
+    fn is_measurement_message(buffer: &[u8]) -> bool {
+        matches!(MessageHeader::get_message_type(buffer), MessageType::MEASUREMENT)
     }
 
-
-
-    fn io_uring_complete_normal(&mut self, io_uring_instance: &mut IoUringNormal) -> Result<u32, &'static str> {
+    fn io_uring_complete_normal(&mut self, io_uring_instance: &mut IoUringNormal, statistic: &mut Statistic) -> Result<u32, UdperfError> {
         let mut completion_count = 0;
         // We do not need to cq.sync the completion queue, since this is done automatically when getting/dropping the cq object
         let cq = io_uring_instance.get_cq();
@@ -242,11 +229,21 @@ impl Receiver {
             let user_data = cqe.user_data();
             debug!("Received completion event with user_data: {}, and received bytes: {}", user_data, amount_received_bytes); 
 
-            completion_count += parse_received_bytes(amount_received_bytes)?;
+            match parse_received_bytes(amount_received_bytes) {
+                Ok(i) => completion_count += i,
+                Err(UdperfError::WouldBlock) => {
+                    // The request completed without data. Its buffer needs to be returned, otherwise the buffers run out and the loop waits forever for completions.
+                    statistic.amount_eagain += 1;
+                    index_pool.push(user_data as usize);
+                    completion_count += 1;
+                    continue;
+                },
+                Err(x) => return Err(x)
+            }
 
             match self.handle_recvmsg_return(amount_received_bytes, None, user_data) {
                 Ok(_) => {},
-                Err("INIT_MESSAGE_RECEIVED") => { // Checking for INIT message, and returning the buffer index to the buffer ring
+                Err(UdperfError::InitMessageReceived) => { // Checking for INIT message, and returning the buffer index to the buffer ring
                     index_pool.push(user_data as usize);
                     continue;
                 },
@@ -264,9 +261,9 @@ impl Receiver {
     }
 
 
-    fn io_uring_complete_provided_buffers(&mut self, io_uring_instance: &mut IoUringProvidedBuffer, statistic: &mut Statistic) -> Result<u32, &'static str> {
+    fn io_uring_complete_provided_buffers(&mut self, io_uring_instance: &mut IoUringProvidedBuffer, statistic: &mut Statistic) -> Result<u32, UdperfError> {
         let mut completion_count = 0;
-        let (buf_ring, cq) = io_uring_instance.get_bufs_and_cq();
+        let (buf_ring, cq, msghdrs) = io_uring_instance.get_bufs_and_cq();
         let mut bufs = buf_ring.submissions();
 
         debug!("BEGIN io_uring_complete: Current cq len: {}/{}", cq.len(), cq.capacity());
@@ -303,10 +300,15 @@ impl Receiver {
                 iov_len: amount_received_bytes as usize
             };
 
+            // The control messages are written into the msghdr of the request, which is identified by the user_data
             let mut msghdr = {
                 let mut hdr = unsafe { std::mem::zeroed::<libc::msghdr>() };
                 hdr.msg_iov = &iovec as *const _ as *mut _;
                 hdr.msg_iovlen = 1;
+                if let Some(request_msghdr) = msghdrs.get(user_data as usize).filter(|request_msghdr| !request_msghdr.msg_control.is_null()) {
+                    hdr.msg_control = request_msghdr.msg_control;
+                    hdr.msg_controllen = request_msghdr.msg_controllen;
+                }
                 hdr
             };
 
@@ -318,7 +320,7 @@ impl Receiver {
     }
 
 
-    fn io_uring_complete_multishot(&mut self,  io_uring_instance: &mut IoUringMultishot) -> Result<bool, &'static str> {
+    fn io_uring_complete_multishot(&mut self,  io_uring_instance: &mut IoUringMultishot) -> Result<bool, UdperfError> {
         let mut multishot_armed = true;
         let msghdr = &io_uring_instance.get_msghdr();
         let (buf_ring, cq) = io_uring_instance.get_bufs_and_cq();
@@ -385,7 +387,7 @@ impl Receiver {
         Ok(multishot_armed)
     }
 
-    fn handle_recvmsg_return(&mut self, amount_received_bytes: i32,  msghdr: Option<&mut libc::msghdr>, msghdr_index: u64) -> Result<(), &'static str> {
+    fn handle_recvmsg_return(&mut self, amount_received_bytes: i32,  msghdr: Option<&mut libc::msghdr>, msghdr_index: u64) -> Result<(), UdperfError> {
         let msghdr = match msghdr {
             Some(msghdr) => msghdr,
             None => self.packet_buffer.get_msghdr_from_index(msghdr_index as usize).unwrap()
@@ -397,35 +399,30 @@ impl Receiver {
             std::slice::from_raw_parts(iov_base as *const u8, iov_len )
         };
         
-        let test_id = MessageHeader::get_test_id(buffer_pointer) as usize;
-        let mtype = MessageHeader::get_message_type(buffer_pointer);
-
-        Self::parse_message_type(mtype, test_id, &mut self.measurements, &self.parameter)?;
+        if self.parameter.ping_pong && Self::is_measurement_message(buffer_pointer) {
+            Self::echo(&self.sockets[self.current_socket], &buffer_pointer[..amount_received_bytes as usize])?;
+        }
 
         let msghdr = match self.parameter.uring_parameter.uring_mode {
             UringMode::Normal => self.packet_buffer.get_msghdr_from_index(msghdr_index as usize).unwrap(),
             _ => msghdr
         };
 
-        let statistic = &mut self.measurements.get_mut(test_id).expect("Error getting statistic: test id not found").statistic;
-        let absolut_packets_received;
-        (self.next_packet_id, absolut_packets_received) = util::process_packet_msghdr(msghdr, amount_received_bytes as usize, self.next_packet_id, statistic);
-        statistic.amount_datagrams += absolut_packets_received;
-        statistic.amount_data_bytes += amount_received_bytes as usize;
+        let result = util::process_packet_msghdr(msghdr, amount_received_bytes as usize, &mut self.measurements, self.expected_payload.as_deref(), &mut self.socket_drops[self.current_socket]);
 
         // Reset msg_flags and msg_controllen fields
-        if self.parameter.socket_options.gro {
+        if self.parameter.socket_options.receives_cmsg() {
             msghdr.msg_flags = 0;
             msghdr.msg_controllen = crate::LENGTH_MSGHDR_CONTROL_MESSAGE_BUFFER;
         }
 
-        debug!("Received {} packets and total {} Bytes, and next packet id should be {}", absolut_packets_received, amount_received_bytes, self.next_packet_id);
-        Ok(())
+        debug!("Received {:?} packets and total {} Bytes", result, amount_received_bytes);
+        result.map(|_| ())
     }
 
 
-    fn io_uring_loop(&mut self) -> Result<Statistic, &'static str> {
-        let socket_fd = self.socket.get_socket_id();
+    fn io_uring_loop(&mut self) -> Result<Statistic, UdperfError> {
+        let socket_fd = self.sockets[0].get_socket_id();
         let mut statistic = Statistic::new(self.parameter.clone());
         let mut amount_inflight = 0;
 
@@ -436,18 +433,21 @@ impl Receiver {
                 let mut armed = false;
 
                 loop {
+                    if self.measurement_stopped() { return Ok(statistic + io_uring_instance.get_statistic()) }
+                    self.sample_socket_memory(false);
                     statistic.amount_io_model_calls += 1;
                     io_uring_instance.fill_sq_and_submit(armed, socket_fd)?;
 
                     // Check if the time elapsed since the last send operation is greater than or equal to self.parameters.interval seconds
                     if self.statistic_interval.output_interval != 0.0 && self.statistic_interval.last_send_instant.elapsed().as_secs_f64() >= self.statistic_interval.output_interval {
-                        let mut statistic_new = self.measurements.iter().fold(statistic.clone(), |acc: Statistic, measurement| acc + measurement.statistic.clone());
+                        let mut statistic_new = Measurement::add_to_statistic(self.measurements.values(), statistic.clone());
                         statistic_new = statistic_new + io_uring_instance.get_statistic();
+                        self.set_socket_statistic(&mut statistic_new);
                         self.statistic_interval.calculate_interval(statistic_new);
 
                         // Reset measurements statistics
-                        for measurement in &mut self.measurements {
-                            measurement.statistic = Statistic::new(self.parameter.clone()); 
+                        for measurement in self.measurements.values_mut() {
+                            measurement.next_interval();
                         }
                         statistic = Statistic::new(self.parameter.clone());
                         io_uring_instance.reset_statistic(self.parameter.clone());
@@ -460,11 +460,11 @@ impl Receiver {
                             }
                             armed = multishot_armed
                         },
-                        Err("INIT_MESSAGE_RECEIVED") => {},
-                        Err("LAST_MESSAGE_RECEIVED") => {
+                        Err(UdperfError::InitMessageReceived) => {},
+                        Err(UdperfError::LastMessageReceived) => {
                             if self.all_measurements_finished() { return Ok(statistic + io_uring_instance.get_statistic()) }
                         },
-                        Err("EAGAIN") => {
+                        Err(UdperfError::WouldBlock) => {
                             statistic.amount_eagain += 1;
                         },
                         Err(x) => {
@@ -478,6 +478,8 @@ impl Receiver {
                 let mut io_uring_instance: IoUringProvidedBuffer = crate::io_uring::provided_buffer::IoUringProvidedBuffer::new(self.parameter.clone(), self.io_uring_sqpoll_fd)?;
 
                 loop {
+                    if self.measurement_stopped() { return Ok(statistic + io_uring_instance.get_statistic()) }
+                    self.sample_socket_memory(false);
                     if let Some(ref mut array) = statistic.uring_inflight_utilization {
                         array[amount_inflight as usize] += 1;
                     }
@@ -485,13 +487,14 @@ impl Receiver {
 
                     // Check if the time elapsed since the last send operation is greater than or equal to self.parameters.interval seconds
                     if self.statistic_interval.output_interval != 0.0 && self.statistic_interval.last_send_instant.elapsed().as_secs_f64() >= self.statistic_interval.output_interval {
-                        let mut statistic_new = self.measurements.iter().fold(statistic.clone(), |acc: Statistic, measurement| acc + measurement.statistic.clone());
+                        let mut statistic_new = Measurement::add_to_statistic(self.measurements.values(), statistic.clone());
                         statistic_new = statistic_new + io_uring_instance.get_statistic();
+                        self.set_socket_statistic(&mut statistic_new);
                         self.statistic_interval.calculate_interval(statistic_new);
 
                         // Reset measurements statistics
-                        for measurement in &mut self.measurements {
-                            measurement.statistic = Statistic::new(self.parameter.clone()); 
+                        for measurement in self.measurements.values_mut() {
+                            measurement.next_interval();
                         }
                         statistic = Statistic::new(self.parameter.clone());
                         io_uring_instance.reset_statistic(self.parameter.clone());
@@ -503,11 +506,11 @@ impl Receiver {
                         Ok(completed) => {
                             amount_inflight -= completed
                         },
-                        Err("INIT_MESSAGE_RECEIVED") => {},
-                        Err("LAST_MESSAGE_RECEIVED") => {
+                        Err(UdperfError::InitMessageReceived) => {},
+                        Err(UdperfError::LastMessageReceived) => {
                             if self.all_measurements_finished() { return Ok(statistic + io_uring_instance.get_statistic()) }
                         },
-                        Err("EAGAIN") => {
+                        Err(UdperfError::WouldBlock) => {
                             statistic.amount_eagain += 1;
                         },
                         Err(x) => {
@@ -521,6 +524,8 @@ impl Receiver {
                 let mut io_uring_instance = crate::io_uring::normal::IoUringNormal::new(self.parameter.clone(), self.io_uring_sqpoll_fd)?;
 
                 loop {
+                    if self.measurement_stopped() { return Ok(statistic + io_uring_instance.get_statistic()) }
+                    self.sample_socket_memory(false);
                     if let Some(ref mut array) = statistic.uring_inflight_utilization {
                         array[amount_inflight as usize] += 1;
                     }
@@ -528,13 +533,14 @@ impl Receiver {
 
                     // Check if the time elapsed since the last send operation is greater than or equal to self.parameters.interval seconds
                     if self.statistic_interval.output_interval != 0.0 && self.statistic_interval.last_send_instant.elapsed().as_secs_f64() >= self.statistic_interval.output_interval {
-                        let mut statistic_new = self.measurements.iter().fold(statistic.clone(), |acc: Statistic, measurement| acc + measurement.statistic.clone());
+                        let mut statistic_new = Measurement::add_to_statistic(self.measurements.values(), statistic.clone());
                         statistic_new = statistic_new + io_uring_instance.get_statistic();
+                        self.set_socket_statistic(&mut statistic_new);
                         self.statistic_interval.calculate_interval(statistic_new);
 
                         // Reset measurements statistics
-                        for measurement in &mut self.measurements {
-                            measurement.statistic = Statistic::new(self.parameter.clone()); 
+                        for measurement in self.measurements.values_mut() {
+                            measurement.next_interval();
                         }
                         statistic = Statistic::new(self.parameter.clone());
                         io_uring_instance.reset_statistic(self.parameter.clone());
@@ -542,15 +548,15 @@ impl Receiver {
 
                     amount_inflight += io_uring_instance.fill_sq_and_submit(self.packet_buffer.get_pool_inflight() as u32, &mut self.packet_buffer, socket_fd)?;
 
-                    match self.io_uring_complete_normal(&mut io_uring_instance) {
+                    match self.io_uring_complete_normal(&mut io_uring_instance, &mut statistic) {
                         Ok(completed) => {
                             amount_inflight -= completed
                         },
-                        Err("INIT_MESSAGE_RECEIVED") => {},
-                        Err("LAST_MESSAGE_RECEIVED") => {
+                        Err(UdperfError::InitMessageReceived) => {},
+                        Err(UdperfError::LastMessageReceived) => {
                             if self.all_measurements_finished() { return Ok(statistic + io_uring_instance.get_statistic()) } 
                         },
-                        Err("EAGAIN") => {
+                        Err(UdperfError::WouldBlock) => {
                             statistic.amount_eagain += 1;
                         },
                         Err(x) => {
@@ -562,13 +568,116 @@ impl Receiver {
             },
             _ => {
                 error!("Invalid io_uring mode selected for receiver!");
-                Err("Invalid io_uring mode selected for receiver!")
+                Err(UdperfError::Other("Invalid io_uring mode selected for receiver!"))
             }
         }
     }
 
+    // In reverse mode the receiver sends the first datagram, which lets the sender learn the address and opens NAT and stateful firewalls for the measurement traffic
+    fn announce(socket: &Socket) -> Result<usize, UdperfError> {
+        let header = MessageHeader::new(MessageType::INIT, 0, 0);
+        info!("Reverse mode: Announcing receiver on socketID {} to the remote sender", socket.get_socket_id());
+        socket.send(header.serialize(), header.len())
+    }
+
+    // In ping-pong mode every datagram is echoed back to the sender, which measures the round-trip time. If the socket buffer is full, only the echo is dropped, which the sender counts as lost.
+    fn echo(socket: &Socket, buffer: &[u8]) -> Result<(), UdperfError> {
+        match socket.send(buffer, buffer.len()) {
+            Ok(_) | Err(UdperfError::WouldBlock) => Ok(()),
+            Err(x) => Err(x)
+        }
+    }
+
+    // The received msghdr is sent back with the length of the received datagram and without the received control messages
+    fn echo_msghdr(socket: &Socket, msghdr: &mut libc::msghdr, amount_received_bytes: usize) -> Result<(), UdperfError> {
+        let (iov_len, msg_controllen) = (unsafe { (*msghdr.msg_iov).iov_len }, msghdr.msg_controllen);
+        unsafe { (*msghdr.msg_iov).iov_len = amount_received_bytes };
+        msghdr.msg_controllen = 0;
+
+        let result = socket.sendmsg(msghdr);
+
+        unsafe { (*msghdr.msg_iov).iov_len = iov_len };
+        msghdr.msg_controllen = msg_controllen;
+
+        match result {
+            Ok(_) | Err(UdperfError::WouldBlock) => Ok(()),
+            Err(x) => Err(x)
+        }
+    }
+
+    fn echo_mmsghdr(socket: &mut Socket, mmsghdr_vec: &mut [libc::mmsghdr]) -> Result<(), UdperfError> {
+        // ASSUMPTION: All msghdrs of the packet buffer have the same iov_len and msg_controllen
+        let (iov_len, msg_controllen) = match mmsghdr_vec.first() {
+            Some(mmsghdr) => (unsafe { (*mmsghdr.msg_hdr.msg_iov).iov_len }, mmsghdr.msg_hdr.msg_controllen),
+            None => return Ok(())
+        };
+        for mmsghdr in mmsghdr_vec.iter_mut() {
+            unsafe { (*mmsghdr.msg_hdr.msg_iov).iov_len = mmsghdr.msg_len as usize };
+            mmsghdr.msg_hdr.msg_controllen = 0;
+        }
+
+        let result = socket.sendmmsg(mmsghdr_vec);
+
+        for mmsghdr in mmsghdr_vec.iter_mut() {
+            unsafe { (*mmsghdr.msg_hdr.msg_iov).iov_len = iov_len };
+            mmsghdr.msg_hdr.msg_controllen = msg_controllen;
+        }
+
+        match result {
+            Ok(_) | Err(UdperfError::WouldBlock) => Ok(()),
+            Err(x) => Err(x)
+        }
+    }
+
+    // The peak of the receive queue is only visible while receiving, so the memory of the sockets is sampled periodically
+    fn sample_socket_memory(&mut self, force: bool) {
+        let Some(sock_diag) = self.sock_diag.as_mut() else { return };
+        if !force && self.last_sock_diag_instant.elapsed().as_millis() < SOCKET_MEMORY_SAMPLE_INTERVAL as u128 {
+            return;
+        }
+        self.last_sock_diag_instant = Instant::now();
+
+        // A failed query only misses a sample, so the measurement continues
+        let socket_memory = match sock_diag.query() {
+            Ok(socket_memory) => socket_memory,
+            Err(x) => {
+                warn!("Error sampling the memory of the sockets: {}", x);
+                return;
+            }
+        };
+
+        for (socket_drops, memory) in self.socket_drops.iter_mut().zip(socket_memory) {
+            if let Some(memory) = memory {
+                *socket_drops = u32::max(*socket_drops, memory.drops);
+                self.socket_rmem_peak = u32::max(self.socket_rmem_peak, memory.rmem_alloc);
+            }
+        }
+    }
+
+    // Adds the socket drops and the peak receive memory since the last call to the statistic
+    fn set_socket_statistic(&mut self, statistic: &mut Statistic) {
+        if !self.parameter.socket_options.rxq_overflow {
+            return;
+        }
+        let socket_drops = self.socket_drops.iter().map(|drops| *drops as u64).sum();
+        statistic.amount_socket_drops = socket_drops - self.reported_socket_drops;
+        statistic.socket_rmem_peak = self.socket_rmem_peak as u64;
+        self.reported_socket_drops = socket_drops;
+        self.socket_rmem_peak = 0;
+    }
+
+    // Set by the control channel, when the sender finished the measurement. Necessary if the LAST message got lost.
+    fn measurement_stopped(&self) -> bool {
+        if self.stop_measurement.load(Ordering::Relaxed) {
+            warn!("{:?}: Measurement stopped by the control channel before the LAST message was received!", thread::current().id());
+            true
+        } else {
+            false
+        }
+    }
+
     fn all_measurements_finished(&self) -> bool {
-        for measurement in self.measurements.iter() {
+        for measurement in self.measurements.values() {
             if !measurement.last_packet_received && measurement.first_packet_received {
                 debug!("{:?}: Last message received, but not all measurements are finished!", thread::current().id());
                 return false;
@@ -582,16 +691,34 @@ impl Receiver {
 
 
 impl Node for Receiver { 
-    fn run(&mut self, io_model: IOModel) -> Result<(Statistic, Vec<Statistic>), &'static str> {
+    fn run(&mut self, io_model: IOModel) -> Result<(Statistic, Vec<Statistic>), UdperfError> {
         info!("Start receiver loop...");
         let mut statistic = Statistic::new(self.parameter.clone());
 
         // Timeout waiting for first message 
         // With communication channel in future, the measure thread is only started if the sender starts a measurement. Then timeout can be further reduced to 1-2s.
-        let mut pollfd = self.socket.create_pollfd(libc::POLLIN);
-        match self.socket.poll(&mut pollfd, INITIAL_POLL_TIMEOUT) {
+        // In reverse mode the announcement is resent until the first datagram arrives, since the remote sender can't start, if the announcement got lost (e.g. during ARP resolution)
+        let initial_poll_timeout = if self.parameter.reverse { ANNOUNCE_INTERVAL } else { INITIAL_POLL_TIMEOUT };
+        let initial_wait_start = Instant::now();
+        let initial_wait = loop {
+            let result = if let Some(epoll) = self.epoll.as_mut() {
+                epoll.wait(initial_poll_timeout)
+            } else {
+                let mut pollfd = self.sockets[0].create_pollfd(libc::POLLIN);
+                self.sockets[0].poll(&mut pollfd, initial_poll_timeout)
+            };
+
+            match result {
+                Err(UdperfError::Timeout) if self.parameter.reverse && initial_wait_start.elapsed().as_millis() < INITIAL_POLL_TIMEOUT as u128 => {
+                    debug!("{:?}: No datagram received yet. Announcing the receiver again", thread::current().id());
+                    Self::announce(&self.sockets[0])?;
+                },
+                result => break result
+            }
+        };
+        match initial_wait {
             Ok(_) => {},
-            Err("TIMEOUT") => {
+            Err(UdperfError::Timeout) => {
                 // If port sharding is used, not every receiver thread gets packets due to the load balancing of REUSEPORT.
                 // To avoid that the thread waits forever, we need to return here.
                 warn!("{:?}: Timeout waiting for sender to send first packet!", thread::current().id());
@@ -602,34 +729,54 @@ impl Node for Receiver {
             }
         };
         statistic.start_timestamp = self.statistic_interval.last_send_timestamp;
-        self.statistic_interval.last_send_instant = Instant::now() + std::time::Duration::from_millis(crate::WAIT_CONTROL_MESSAGE);
-        self.statistic_interval.last_send_timestamp = Statistic::get_unix_timestamp() + (crate::WAIT_CONTROL_MESSAGE as f64 / 1000.0);
+        if let Some(index) = self.epoll.as_mut().and_then(|epoll| epoll.next_ready()) {
+            self.current_socket = index;
+        }
+
+        if self.parameter.ping_pong {
+            // The echoes are sent to the source address of the first datagram
+            let socket = &mut self.sockets[self.current_socket];
+            let remote = socket.peek_source()?;
+            info!("Ping-pong mode: Echoing datagrams to remote host {}", remote);
+            socket.connect(remote)?;
+        }
+
+        self.statistic_interval.last_send_instant = Instant::now() + std::time::Duration::from_millis(self.parameter.control_message_delay());
+        self.statistic_interval.last_send_timestamp = Statistic::get_unix_timestamp() + (self.parameter.control_message_delay() as f64 / 1000.0);
 
         if io_model == IOModel::IoUring {
             statistic = self.io_uring_loop()?;
         } else {
             loop {
+                if self.measurement_stopped() { break }
+                self.sample_socket_memory(false);
                 statistic.amount_syscalls += 1;
 
                 // Check if the time elapsed since the last send operation is greater than or equal to self.parameters.interval seconds
                 if self.statistic_interval.output_interval != 0.0 && self.statistic_interval.last_send_instant.elapsed().as_secs_f64() >= self.statistic_interval.output_interval {
-                    let statistic_new = self.measurements.iter().fold(statistic.clone(), |acc: Statistic, measurement| acc + measurement.statistic.clone());
+                    let mut statistic_new = Measurement::add_to_statistic(self.measurements.values(), statistic.clone());
+                    self.set_socket_statistic(&mut statistic_new);
                     self.statistic_interval.calculate_interval(statistic_new);
                     // Reset measurements statistics
-                    for measurement in &mut self.measurements {
-                        measurement.statistic = Statistic::new(self.parameter.clone()); 
+                    for measurement in self.measurements.values_mut() {
+                        measurement.next_interval();
                     }
                     statistic = Statistic::new(self.parameter.clone());
                 }
 
                 match self.recv_messages() {
                     Ok(_) => {},
-                    Err("EAGAIN") => {
-                        statistic.amount_io_model_calls += 1;
+                    Err(UdperfError::WouldBlock) => {
                         statistic.amount_eagain += 1;
+                        // Serve the other sockets, which are already known to be ready, before waiting again
+                        if let Some(index) = self.epoll.as_mut().and_then(|epoll| epoll.next_ready()) {
+                            self.current_socket = index;
+                            continue;
+                        }
+                        statistic.amount_io_model_calls += 1;
                         match self.io_wait(io_model) {
                             Ok(_) => {},
-                            Err("TIMEOUT") => {
+                            Err(UdperfError::Timeout) => {
                                 // If port sharing is used, or single connection not every thread receives the LAST message. 
                                 // To avoid that the thread waits forever, we need to return here.
                                 warn!("{:?}: Timeout waiting for a subsequent packet from the sender!", thread::current().id());
@@ -640,8 +787,9 @@ impl Node for Receiver {
                             }
                         }
                     },
-                    Err("INIT_MESSAGE_RECEIVED") => {},
-                    Err("LAST_MESSAGE_RECEIVED") => {
+                    Err(UdperfError::InitMessageReceived) => {},
+                    Err(UdperfError::LastMessageReceived) => {
+                        // Every flow (and socket) sends its own LAST message, so the datagrams still queued for the other flows are counted as well
                         if self.all_measurements_finished() { break }
                     },
                     Err(x) => {
@@ -652,6 +800,9 @@ impl Node for Receiver {
             }
         }
 
+        // The drop counter of the sockets is also updated by drops after the last received datagram
+        self.sample_socket_memory(true);
+
         if self.parameter.multiplex_port_receiver != MultiplexPort::Sharing {
             // If a thread finishes (closes the socket) before the others, the hash mapping of SO_REUSEPORT changes. 
             // Then all threads would receive packets from other connections (test_ids).
@@ -659,39 +810,53 @@ impl Node for Receiver {
             if self.parameter.multiplex_port_receiver == MultiplexPort::Sharding {
                 sleep(std::time::Duration::from_millis(crate::WAIT_CONTROL_MESSAGE * 2));
             }
-            self.socket.close()?;
+            for socket in self.sockets.iter() {
+                socket.close()?;
+            }
         }
 
         debug!("{:?}: Finished receiving data from remote host", thread::current().id());
 
         let mut final_statistic = Statistic::new(self.parameter.clone());
         if self.statistic_interval.statistics.is_empty() {
-            final_statistic = self.measurements.iter().fold(statistic, |acc: Statistic, measurement| acc + measurement.statistic.clone());
-            final_statistic.set_test_duration(Some(self.statistic_interval.last_send_timestamp), Some(Statistic::get_unix_timestamp() - (crate::WAIT_CONTROL_MESSAGE as f64 / 1000.0)));
+            final_statistic = Measurement::add_to_statistic(self.measurements.values(), statistic);
+            self.set_socket_statistic(&mut final_statistic);
+            final_statistic.set_test_duration(Some(self.statistic_interval.last_send_timestamp), Some(Statistic::get_unix_timestamp() - (self.parameter.control_message_delay() as f64 / 1000.0)));
         } else {
+            // The drops of the sockets since the last interval (e.g. sampled after the last datagram) aren't part of any interval
+            self.set_socket_statistic(&mut final_statistic);
             for statistic in self.statistic_interval.statistics.iter() {
                 final_statistic = final_statistic + statistic.clone();
             }
         }
 
+        final_statistic.set_flow_statistics(&self.measurements);
         final_statistic.set_test_duration(None, None);
         final_statistic.calculate_statistics();
 
         Ok((final_statistic, self.statistic_interval.statistics.clone()))
     }
 
-    fn io_wait(&mut self, io_model: IOModel) -> Result<(), &'static str> {
-        // Normally we would need to iterate over FDs and check which socket is ready
-        // Since we only have one socket, we directly call recv_messages after io_wait returns
+    fn io_wait(&mut self, io_model: IOModel) -> Result<(), UdperfError> {
+        // Select and poll only wait for a single socket, so we directly call recv_messages after io_wait returns
+        // Epoll returns the ready sockets, from which the next socket to receive on is taken
         match io_model {
             IOModel::Select => {
-                let mut read_fds: libc::fd_set = unsafe { self.socket.create_fdset() };
-                self.socket.select(Some(&mut read_fds), None, IN_MEASUREMENT_POLL_TIMEOUT)
+                let mut read_fds: libc::fd_set = unsafe { self.sockets[0].create_fdset() };
+                self.sockets[0].select(Some(&mut read_fds), None, IN_MEASUREMENT_POLL_TIMEOUT)
 
             },
             IOModel::Poll => {
-                let mut pollfd = self.socket.create_pollfd(libc::POLLIN);
-                self.socket.poll(&mut pollfd, IN_MEASUREMENT_POLL_TIMEOUT)
+                let mut pollfd = self.sockets[0].create_pollfd(libc::POLLIN);
+                self.sockets[0].poll(&mut pollfd, IN_MEASUREMENT_POLL_TIMEOUT)
+            },
+            IOModel::Epoll => {
+                let epoll = self.epoll.as_mut().expect("Epoll instance not created");
+                epoll.wait(IN_MEASUREMENT_POLL_TIMEOUT)?;
+                if let Some(index) = epoll.next_ready() {
+                    self.current_socket = index;
+                }
+                Ok(())
             },
             _ => Ok(())
         }
@@ -1,186 +1,333 @@
-use std::net::SocketAddrV4;
+use std::net::SocketAddr;
 use std::os::fd::RawFd;
-use std::{thread::sleep, time::Instant};
+use std::sync::{mpsc, Arc};
+use std::{thread::sleep, time::{Duration, Instant}};
 use log::{debug, trace, info, warn, error};
 
+use crate::io_uring::ping_pong::{IoUringPingPong, USER_DATA_RECV, USER_DATA_SEND};
 use crate::io_uring::send::IoUringSend;
-use crate::io_uring::{check_multishot_status, IoUringOperatingModes, UringMode};
-use crate::net::{MessageHeader, MessageType, socket::Socket};
+use crate::io_uring::{check_multishot_status, parse_received_bytes, IoUringOperatingModes, UringMode};
+use crate::net::{self, epoll::Epoll, MessageHeader, MessageType, socket::Socket};
 use crate::util::msghdr_vec::MsghdrVec;
 use crate::util::packet_buffer::PacketBuffer;
+use crate::util::datagram_size::DatagramSizes;
+use crate::util::payload;
+use crate::util::pacer::Pacer;
+use crate::util::tx_timestamps::TxTimestamps;
 use crate::util::{self, ExchangeFunction, IOModel, statistic::*, msghdr::WrapperMsghdr};
 use super::Node;
+use crate::UdperfError;
 
 pub struct Sender {
     test_id: u64,
     packet_buffer: PacketBuffer,
-    socket: Socket,
+    // With the epoll IO model or flows with their own 4-tuple a thread can send on multiple sockets
+    sockets: Vec<Socket>,
+    current_socket: usize,
+    epoll: Option<Epoll>,
     parameter: Parameter,
     io_uring_sqpoll_fd: Option<RawFd>,
     statistic: Statistic,
     statistic_interval: StatisticInterval,
     run_time_length: u64,
-    next_packet_id: u64,
+    // Every flow has its own test ID (test_id + index) and sequence of packet ids
+    next_packet_ids: Vec<u64>,
+    // Flows distinguished only by the test ID are interleaved over the msghdrs. The flow of the next msghdr to send.
+    interleaved_flows: bool,
+    next_flow: usize,
     exchange_function: ExchangeFunction,
+    tx_timestamps: Option<TxTimestamps>,
+    pacer: Option<Arc<Pacer>>,
 }
 
 impl Sender {
-    pub fn new(test_id: u64, local_port: Option<u16>, sock_address_out: SocketAddrV4, socket: Option<Socket>, io_uring: Option<RawFd>, parameter: Parameter) -> Self {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(test_id: u64, local_port: Option<u16>, sock_address_out: SocketAddr, socket: Option<Socket>, io_uring: Option<RawFd>, interval_reporter: Option<mpsc::Sender<Statistic>>, pacer: Option<Arc<Pacer>>, parameter: Parameter) -> Result<Self, UdperfError> {
+        let local_ip = net::unspecified_address(sock_address_out.ip());
         let socket = if socket.is_none() {
-            let mut socket: Socket = Socket::new(parameter.socket_options).expect("Error creating socket");
-            if let Some(port) = local_port {
-                socket.bind(SocketAddrV4::new(crate::DEFAULT_SENDER_IP, port)).expect("Error binding socket");
+            let mut socket: Socket = Socket::new(parameter.socket_options)?;
+            if parameter.reverse {
+                // In reverse mode the address of the remote host is only known after it announced itself, so the socket listens on the receiver port instead
+                socket.bind(sock_address_out)?;
+            } else {
+                if let Some(port) = local_port {
+                    socket.bind(SocketAddr::new(local_ip, port))?;
+                }
+                socket.connect(sock_address_out)?;
             }
-            socket.connect(sock_address_out).expect("Error connecting to remote host");
             socket
         } else {
             let mut socket = socket.unwrap();
-            socket.set_sock_addr_out(sock_address_out); // Set socket address out for the remote host
+            socket.set_sock_addr_out(sock_address_out)?; // Set socket address out for the remote host
             socket
         };
 
-        info!("Current mode 'sender' sending to remote host {}:{} from {}:{} with test ID {} on socketID {}", sock_address_out.ip(), sock_address_out.port(), crate::DEFAULT_SENDER_IP, local_port.unwrap_or(0), test_id, socket.get_socket_id());
+        if parameter.reverse {
+            info!("Current mode 'sender' (reverse) listening on {} for the remote receiver with test ID {} on socketID {}", sock_address_out, test_id, socket.get_socket_id());
+        } else {
+            info!("Current mode 'sender' sending to remote host {} from {} with test ID {} on socketID {}", sock_address_out, SocketAddr::new(local_ip, local_port.unwrap_or(0)), test_id, socket.get_socket_id());
+        }
+
+        let packet_buffer = Self::create_packet_buffer(&parameter, test_id, &socket)?;
+
+        // The additional sockets send to the ports following the receiver port of the first socket.
+        // Flows with their own 4-tuple send to the same receiver port from different source ports.
+        let four_tuple_flows = parameter.flows_per_thread > 1 && parameter.flow_mode == FlowMode::FourTuple;
+        let amount_sockets = if four_tuple_flows { parameter.flows_per_thread } else { parameter.sockets_per_thread };
+        let mut sockets = vec![socket];
+        for socket_index in 1..amount_sockets {
+            let port_offset = if four_tuple_flows { 0 } else { socket_index };
+            let sock_address = SocketAddr::new(sock_address_out.ip(), sock_address_out.port() + port_offset);
+            let mut socket: Socket = Socket::new(parameter.socket_options)?;
+            socket.connect(sock_address)?;
+            info!("Current mode 'sender' sending to remote host {} with test ID {} on socketID {}", sock_address, test_id + socket_index as u64, socket.get_socket_id());
+            sockets.push(socket);
+        }
 
-        let packet_buffer = Self::create_packet_buffer(&parameter, test_id, &socket); 
+        let epoll = if parameter.io_model == IOModel::Epoll {
+            // In ping-pong mode the sender mostly waits for the echoes
+            let events = if parameter.ping_pong { libc::EPOLLIN } else { libc::EPOLLOUT };
+            let epoll = Epoll::new(parameter.epoll_edge_triggered, parameter.epoll_exclusive)?;
+            for (index, socket) in sockets.iter().enumerate() {
+                epoll.add(socket, events, index)?;
+            }
+            Some(epoll)
+        } else {
+            None
+        };
 
-        Sender {
+        Ok(Sender {
             test_id,
             packet_buffer,
-            socket,
+            next_packet_ids: vec![0; parameter.amount_flows() as usize],
+            interleaved_flows: parameter.flows_per_thread > 1 && parameter.flow_mode == FlowMode::ConnectionId,
+            next_flow: 0,
+            sockets,
+            current_socket: 0,
+            epoll,
             parameter: parameter.clone(),
             io_uring_sqpoll_fd: io_uring,
             statistic: Statistic::new(parameter.clone()),
-            statistic_interval: StatisticInterval::new(Instant::now(), parameter.output_interval, parameter.test_runtime_length),
+            statistic_interval: StatisticInterval::new(Instant::now(), parameter.output_interval, parameter.test_runtime_length, interval_reporter),
             run_time_length: parameter.test_runtime_length,
-            next_packet_id: 0,
-            exchange_function: parameter.exchange_function
-        }
+            exchange_function: parameter.exchange_function,
+            tx_timestamps: if parameter.socket_options.tx_timestamps { Some(TxTimestamps::new()) } else { None },
+            pacer
+        })
+    }
+
+    // Wait for the announcement datagram of the remote receiver and connect to its source address. Since the remote host sent the first datagram, the measurement traffic passes NAT and stateful firewalls.
+    fn wait_for_remote_receiver(&mut self) -> Result<(), UdperfError> {
+        info!("Reverse mode: Waiting for remote receiver to announce itself on socketID {}", self.sockets[0].get_socket_id());
+        let mut pollfd = self.sockets[0].create_pollfd(libc::POLLIN);
+        self.sockets[0].poll(&mut pollfd, crate::CONTROL_CHANNEL_TIMEOUT as i32)?;
+
+        let mut buffer = [0_u8; crate::DEFAULT_UDP_DATAGRAM_SIZE as usize];
+        let (_, remote) = self.sockets[0].recv_from(&mut buffer)?;
+        info!("Reverse mode: Sending to remote host {} with test ID {}", remote, self.test_id);
+        self.sockets[0].connect(remote)
     }
 
-    fn send_control_message(&mut self, mtype: MessageType) -> Result<(), &'static str> {
+    // The control message is sent for every flow on its socket, since the receiver waits for the LAST message of all flows
+    fn send_control_message(&mut self, mtype: MessageType) -> Result<(), UdperfError> {
         let header = MessageHeader::new(mtype, self.test_id, 0);
         debug!("Coordination message: {:?}", header);
 
         let packet_buffer = WrapperMsghdr::new(header.len() as u32, header.len() as u32);
 
         if let Some(mut packet_buffer) = packet_buffer {
-            packet_buffer.copy_buffer(header.serialize());
-            let sockaddr = self.socket.get_sockaddr_out().unwrap();
-            packet_buffer.set_address(sockaddr);
-            let msghdr = packet_buffer.get_msghdr();
-
-            match self.socket.sendmsg(msghdr) {
-                Ok(_) => { Ok(()) },
-                Err("ECONNREFUSED") => Err("Start the receiver first! Abort measurement..."),
-                Err(x) => Err(x)
+            for flow in 0..self.next_packet_ids.len() {
+                let header = MessageHeader::new(mtype, self.test_id + flow as u64, 0);
+                packet_buffer.copy_buffer(header.serialize());
+
+                let socket = &self.sockets[flow % self.sockets.len()];
+                let (sockaddr, sockaddr_len) = socket.get_sockaddr_out().unwrap();
+                packet_buffer.set_address(sockaddr, sockaddr_len);
+                let msghdr = packet_buffer.get_msghdr();
+
+                loop {
+                    match socket.sendmsg(msghdr) {
+                        Ok(_) => {
+                            if let Some(tx_timestamps) = self.tx_timestamps.as_mut() {
+                                tx_timestamps.record_send(1, util::get_timestamp_ns());
+                            }
+                            break;
+                        },
+                        Err(UdperfError::WouldBlock) => {
+                            // The control messages of many flows can fill the send buffer
+                            let mut pollfd = socket.create_pollfd(libc::POLLOUT);
+                            socket.poll(&mut pollfd, crate::WAIT_CONTROL_MESSAGE as i32)?;
+                        },
+                        Err(UdperfError::Os { errno: libc::ECONNREFUSED, .. }) => return Err(UdperfError::from_errno("Start the receiver first! Abort measurement...", libc::ECONNREFUSED)),
+                        Err(x) => return Err(x)
+                    }
+                }
             }
+            Ok(())
         } else {
-            Err("Error creating buffer")
+            Err(UdperfError::Other("Error creating buffer"))
         }
     }
 
-    fn send_messages(&mut self) -> Result<(), &'static str> {
-        match self.exchange_function {
+    fn datagrams_of_msghdrs(&self, amount_msghdr: usize) -> u64 {
+        (amount_msghdr * self.packet_buffer.packets_amount_per_msghdr()) as u64
+    }
+
+    fn bytes_of_msghdrs(&self, first_index: usize, amount_msghdr: usize) -> usize {
+        self.packet_buffer.amount_bytes(first_index, amount_msghdr)
+    }
+
+    // The traffic pattern of the pacer is shared by all threads, so every thread reports its share of the offered data since the start of the interval
+    fn set_offered_data(&mut self) {
+        if let Some(pacer) = self.pacer.as_ref() {
+            self.statistic.amount_offered_data_bytes = pacer.offered_bytes(self.statistic_interval.last_send_instant, Instant::now()) / self.parameter.amount_threads as u64;
+        }
+    }
+
+    // Adds the test IDs and packet IDs of the flows to all msghdrs. With multiple sockets, the datagrams belong to the flow of the current socket.
+    fn add_packet_ids(&mut self) -> u64 {
+        let first_flow = if self.interleaved_flows { self.next_flow } else { self.current_socket };
+        self.packet_buffer.add_flow_packet_ids(self.test_id, &mut self.next_packet_ids, first_flow, self.interleaved_flows)
+    }
+
+    // Resets the packet IDs of the msghdrs, which weren't sent. The interleaving continues with the flow following the last sent msghdr.
+    fn reset_packet_ids(&mut self, amount_sent_msghdr: usize) {
+        let first_flow = if self.interleaved_flows { self.next_flow } else { self.current_socket };
+        self.packet_buffer.reset_flow_packet_ids(&mut self.next_packet_ids, first_flow, self.interleaved_flows, amount_sent_msghdr);
+        if self.interleaved_flows {
+            self.next_flow = (self.next_flow + amount_sent_msghdr) % self.next_packet_ids.len();
+        }
+    }
+
+    fn send_messages(&mut self) -> Result<(), UdperfError> {
+        // Every call sends all msghdrs of the packet buffer
+        let amount_msghdr = self.packet_buffer.mmsghdr_vec.len();
+        self.packet_buffer.sample_datagram_sizes(0, amount_msghdr);
+        if let Some(pacer) = self.pacer.as_ref() {
+            pacer.acquire(self.datagrams_of_msghdrs(amount_msghdr), self.bytes_of_msghdrs(0, amount_msghdr));
+        }
+        let send_call_timestamp = if self.tx_timestamps.is_some() { util::get_timestamp_ns() } else { 0 };
+
+        let result = match self.exchange_function {
             ExchangeFunction::Normal => self.send(),
             ExchangeFunction::Msg => self.sendmsg(),
             ExchangeFunction::Mmsg => self.sendmmsg(),
+        };
+
+        // The tokens of the msghdrs, which weren't sent, are returned to the pacer
+        if let Some(pacer) = self.pacer.as_ref() {
+            let amount_sent_msghdr = *result.as_ref().unwrap_or(&0);
+            let amount_not_sent_msghdr = amount_msghdr - amount_sent_msghdr;
+            if amount_not_sent_msghdr > 0 {
+                pacer.release(self.datagrams_of_msghdrs(amount_not_sent_msghdr), self.bytes_of_msghdrs(amount_sent_msghdr, amount_not_sent_msghdr));
+            }
+        }
+
+        if let Some(tx_timestamps) = self.tx_timestamps.as_mut() {
+            if let Ok(amount_messages) = result {
+                tx_timestamps.record_send(amount_messages, send_call_timestamp);
+            }
+            tx_timestamps.drain(&self.sockets[0], &mut self.statistic)?;
         }
+        result.map(|_| ())
     }
 
-    fn send(&mut self) -> Result<(), &'static str> {
-        let amount_datagrams = self.packet_buffer.add_packet_ids(self.next_packet_id, None)?;
-        self.next_packet_id += amount_datagrams;
+    // Returns the amount of messages sent
+    fn send(&mut self) -> Result<usize, UdperfError> {
+        let amount_datagrams = self.add_packet_ids();
 
         // Only one buffer is used, so we can directly access the first element
-        let buffer_length = self.packet_buffer.datagram_size();
+        let datagram_size = self.packet_buffer.datagram_size();
         let buffer_pointer = self.packet_buffer.get_buffer_pointer_from_index(0).unwrap();
+        // With a size distribution, the buffer is shortened to the size of the datagram
+        let buffer_length = usize::min(datagram_size, buffer_pointer.len());
 
-        match self.socket.send(buffer_pointer , buffer_length) {
+        match self.sockets[self.current_socket].send(buffer_pointer , buffer_length) {
             Ok(amount_send_bytes) => {
                 // For UDP, either the whole datagram is sent or nothing (due to an error e.g. full buffer). So we can assume that the whole datagram was sent.
+                self.reset_packet_ids(1);
                 self.statistic.amount_datagrams += amount_datagrams;
                 self.statistic.amount_data_bytes += amount_send_bytes;
                 trace!("Sent datagram to remote host");
-                Ok(())
+                Ok(1)
             },
-            Err("EAGAIN") => {
+            Err(UdperfError::WouldBlock) => {
                 // Reset next_packet_id to the last packet_id that was sent
-                self.next_packet_id -= amount_datagrams;
-                Err("EAGAIN") 
+                self.reset_packet_ids(0);
+                Err(UdperfError::WouldBlock) 
             },
-            Err("ECONNREFUSED") => Err("Start the receiver first! Abort measurement..."),
+            Err(UdperfError::Os { errno: libc::ECONNREFUSED, .. }) => Err(UdperfError::from_errno("Start the receiver first! Abort measurement...", libc::ECONNREFUSED)),
             Err(x) => Err(x) 
         }
     }
 
-    fn sendmsg(&mut self) -> Result<(), &'static str> {
-        let amount_datagrams = self.packet_buffer.add_packet_ids(self.next_packet_id, None)?;
-        self.next_packet_id += amount_datagrams;
+    fn sendmsg(&mut self) -> Result<usize, UdperfError> {
+        let amount_datagrams = self.add_packet_ids();
 
         // Only one buffer is used, so we can directly access the first element
         let msghdr = self.packet_buffer.get_msghdr_from_index(0).unwrap();
 
-        match self.socket.sendmsg(msghdr) {
+        match self.sockets[self.current_socket].sendmsg(msghdr) {
             Ok(amount_sent_bytes) => {
                 // Since we are using UDP, we can assume that the whole datagram was sent like in send().
+                self.reset_packet_ids(1);
                 self.statistic.amount_datagrams += amount_datagrams;
                 self.statistic.amount_data_bytes += amount_sent_bytes;
                 trace!("Sent datagram to remote host");
-                Ok(())
+                Ok(1)
             },
-            Err("ECONNREFUSED") => Err("Start the receiver first! Abort measurement..."),
-            Err("EAGAIN") => {
+            Err(UdperfError::Os { errno: libc::ECONNREFUSED, .. }) => Err(UdperfError::from_errno("Start the receiver first! Abort measurement...", libc::ECONNREFUSED)),
+            Err(UdperfError::WouldBlock) => {
                 // Reset next_packet_id to the last packet_id that was sent
-                self.next_packet_id -= amount_datagrams;
-                Err("EAGAIN") 
+                self.reset_packet_ids(0);
+                Err(UdperfError::WouldBlock) 
             },
             Err(x) => Err(x) 
         }
     }
 
-    fn sendmmsg(&mut self) -> Result<(), &'static str> {
-        let amount_datagrams = self.packet_buffer.add_packet_ids(self.next_packet_id, None)?;
-        self.next_packet_id += amount_datagrams;
+    fn sendmmsg(&mut self) -> Result<usize, UdperfError> {
+        let amount_datagrams = self.add_packet_ids();
 
-        match self.socket.sendmmsg(&mut self.packet_buffer.mmsghdr_vec) {
+        match self.sockets[self.current_socket].sendmmsg(&mut self.packet_buffer.mmsghdr_vec) {
             Ok(amount_sent_mmsghdr) => { 
                 let amount_packets_per_msghdr = self.packet_buffer.packets_amount_per_msghdr();
 
                 if amount_sent_mmsghdr != self.packet_buffer.mmsghdr_vec.len() {
                     // Check until which index the packets were sent. Either all packets in a msghdr are sent or none.
-                    // Reset self.next_packet_id to the last packet_id that was sent
                     warn!("Not all packets were sent! Sent: {}, Expected: {}", amount_sent_mmsghdr, amount_datagrams);
-                    let amount_not_sent_packets = (self.packet_buffer.mmsghdr_vec.len() - amount_sent_mmsghdr) * amount_packets_per_msghdr;
-                    self.next_packet_id -= amount_not_sent_packets as u64;
                 }
+                // Reset the packet ids of the flows to the last packet_id that was sent
+                self.reset_packet_ids(amount_sent_mmsghdr);
                 self.statistic.amount_datagrams += (amount_sent_mmsghdr * amount_packets_per_msghdr) as u64;
                 self.statistic.amount_data_bytes += util::get_total_bytes(&self.packet_buffer.mmsghdr_vec, amount_sent_mmsghdr);
                 trace!("Sent {} msg_hdr to remote host", amount_sent_mmsghdr);
-                Ok(())
+                Ok(amount_sent_mmsghdr)
             },
-            Err("ECONNREFUSED") => Err("Start the receiver first! Abort measurement..."),
-            Err("EAGAIN") => {
+            Err(UdperfError::Os { errno: libc::ECONNREFUSED, .. }) => Err(UdperfError::from_errno("Start the receiver first! Abort measurement...", libc::ECONNREFUSED)),
+            Err(UdperfError::WouldBlock) => {
                 // Reset next_packet_id to the last packet_id that was sent
-                self.next_packet_id -= amount_datagrams;
-                Err("EAGAIN") 
+                self.reset_packet_ids(0);
+                Err(UdperfError::WouldBlock) 
             },
             Err(x) => Err(x)
         }
     }
 
-    fn create_packet_buffer(parameter: &Parameter, test_id: u64, socket: &Socket) -> PacketBuffer {
-        let mut packet_buffer = MsghdrVec::new(parameter.packet_buffer_size, parameter.mss, parameter.datagram_size as usize).with_random_payload().with_message_header(test_id);
+    fn create_packet_buffer(parameter: &Parameter, test_id: u64, socket: &Socket) -> Result<PacketBuffer, UdperfError> {
+        let payload = payload::create_payload(parameter)?;
+        let mut packet_buffer = MsghdrVec::new(parameter.packet_buffer_size, parameter.mss, parameter.datagram_size as usize).with_payload(&payload).with_message_header(test_id);
 
         if parameter.multiplex_port == MultiplexPort::Sharing && parameter.multiplex_port_receiver == MultiplexPort::Individual {
-            if let Some(sockaddr) = socket.get_sockaddr_out() {
-                packet_buffer = packet_buffer.with_target_address(sockaddr);
+            if let Some((sockaddr, sockaddr_len)) = socket.get_sockaddr_out() {
+                packet_buffer = packet_buffer.with_target_address(sockaddr, sockaddr_len);
             } 
         }
 
-        PacketBuffer::new(packet_buffer)
+        Ok(PacketBuffer::new(packet_buffer).with_datagram_sizes(DatagramSizes::new(parameter)).with_transmit_timestamps(parameter.one_way_delay).with_echo_timestamps(parameter.ping_pong))
     }
 
-    fn io_uring_complete_send(&mut self, io_uring_instance: &mut IoUringSend) -> Result<usize, &'static str> {
+    fn io_uring_complete_send(&mut self, io_uring_instance: &mut IoUringSend, send_call_timestamp: u64) -> Result<usize, UdperfError> {
         let mut completion_count = 0;
         let amount_datagrams = self.packet_buffer.packets_amount_per_msghdr() as u64;
         // We do not need to cq.sync the completion queue, since this is done automatically when getting/dropping the cq object
@@ -206,16 +353,19 @@ impl Sender {
                     self.statistic.amount_omitted_datagrams += amount_datagrams as i64; // Currently we don't resend the packets
                 },
                 -111 => { // libc::ECONNREFUSED == 111
-                    return Err("Start the receiver first! Abort measurement...");
+                    return Err(UdperfError::from_errno("Start the receiver first! Abort measurement...", libc::ECONNREFUSED));
                 },
                 _ if amount_bytes < 0 => {
                     error!("Error receiving message! Negated error code: {}", amount_bytes);
-                    return Err("Failed to receive data!")
+                    return Err(UdperfError::from_errno("Failed to send data with io_uring", -amount_bytes))
                 },
                 _ => { // Positive amount of bytes received
                     self.statistic.amount_datagrams += amount_datagrams;
                     self.statistic.amount_data_bytes += amount_bytes as usize;
                     completion_count += 1;
+                    if let Some(tx_timestamps) = self.tx_timestamps.as_mut() {
+                        tx_timestamps.record_send(1, send_call_timestamp);
+                    }
                     trace!("Sent datagram to remote host");
                 }
             }
@@ -225,7 +375,7 @@ impl Sender {
         Ok(completion_count)
     }
 
-    fn io_uring_complete_send_zc(&mut self, io_uring_instance: &mut IoUringSend) -> Result<usize, &'static str> {
+    fn io_uring_complete_send_zc(&mut self, io_uring_instance: &mut IoUringSend, send_call_timestamp: u64) -> Result<usize, UdperfError> {
         let mut completion_count = 0;
         let amount_datagrams = self.packet_buffer.packets_amount_per_msghdr() as u64;
         // We do not need to cq.sync the completion queue, since this is done automatically when getting/dropping the cq object
@@ -253,7 +403,7 @@ impl Sender {
                     self.statistic.amount_omitted_datagrams += amount_datagrams as i64; // Currently we don't resend the packets
                 },
                 -111 => { // libc::ECONNREFUSED == 111
-                    return Err("Start the receiver first! Abort measurement...");
+                    return Err(UdperfError::from_errno("Start the receiver first! Abort measurement...", libc::ECONNREFUSED));
                 }
                 -2147483648 => { // IORING_NOTIF_USAGE_ZC_COPIED -> Error returned if data was copied in zero copy mode
                     // Check if the error code is set in second cqe event
@@ -267,7 +417,7 @@ impl Sender {
                 }
                 _ if amount_bytes < 0 => {
                     error!("Error receiving message! Negated error code: {}", amount_bytes);
-                    return Err("Failed to receive data!")
+                    return Err(UdperfError::from_errno("Failed to send data with io_uring", -amount_bytes))
                 },
                 0 => {
                     // Send zero copy will publish two cqe events for the same buffer. The first one will have the amount of bytes sent, and confirmes that the request is queued. It has the flag IORING_CQE_F_MORE set.
@@ -281,6 +431,9 @@ impl Sender {
                 _ => { // Positive amount of bytes received
                     self.statistic.amount_datagrams += amount_datagrams;
                     self.statistic.amount_data_bytes += amount_bytes as usize;
+                    if let Some(tx_timestamps) = self.tx_timestamps.as_mut() {
+                        tx_timestamps.record_send(1, send_call_timestamp);
+                    }
                     trace!("Sent datagram to remote host");
                 }
             }
@@ -294,8 +447,8 @@ impl Sender {
     }
 
 
-    fn io_uring_loop(&mut self, start_time: Instant) -> Result<(), &'static str> {
-        let socket_fd = self.socket.get_socket_id();
+    fn io_uring_loop(&mut self, start_time: Instant) -> Result<(), UdperfError> {
+        let socket_fd = self.sockets[0].get_socket_id();
         let uring_mode = self.parameter.uring_parameter.uring_mode;
         let mut amount_inflight: usize = 0;
 
@@ -311,19 +464,23 @@ impl Sender {
 
                     // Check if the time elapsed since the last send operation is greater than or equal to self.parameters.interval seconds
                     if self.statistic_interval.output_interval != 0.0 && self.statistic_interval.last_send_instant.elapsed().as_secs_f64() >= self.statistic_interval.output_interval  {
+                        self.set_offered_data();
                         self.statistic_interval.calculate_interval(self.statistic.clone());
                         self.statistic = Statistic::new(self.parameter.clone());
                     }
 
-                    let submitted = io_uring_instance.fill_sq_and_submit(self.packet_buffer.get_pool_inflight(), &mut self.packet_buffer, self.next_packet_id, socket_fd)?;
+                    let send_call_timestamp = if self.tx_timestamps.is_some() { util::get_timestamp_ns() } else { 0 };
+                    let submitted = io_uring_instance.fill_sq_and_submit(self.packet_buffer.get_pool_inflight(), &mut self.packet_buffer, self.next_packet_ids[self.current_socket], socket_fd, self.pacer.as_deref())?;
                     amount_inflight += submitted;
-                    self.next_packet_id += (submitted * self.packet_buffer.packets_amount_per_msghdr()) as u64;
+                    self.next_packet_ids[self.current_socket] += (submitted * self.packet_buffer.packets_amount_per_msghdr()) as u64;
 
-                    match if uring_mode == UringMode::Zerocopy { self.io_uring_complete_send_zc(&mut io_uring_instance) } else { self.io_uring_complete_send(&mut io_uring_instance) } {
+                    // The requests are mostly executed inline during the submission, so their completions get the timestamp of the last submission.
+                    // The kernel only counts the key of the transmit timestamps up for successful requests, so the keys are recorded on their completions in the order of execution.
+                    match if uring_mode == UringMode::Zerocopy { self.io_uring_complete_send_zc(&mut io_uring_instance, send_call_timestamp) } else { self.io_uring_complete_send(&mut io_uring_instance, send_call_timestamp) } {
                         Ok(completed) => {
                             amount_inflight -= completed
                         },
-                        Err("EAGAIN") => {
+                        Err(UdperfError::WouldBlock) => {
                             self.statistic.amount_eagain += 1;
                         },
                         Err(x) => {
@@ -331,48 +488,289 @@ impl Sender {
                             return Err(x);
                         }
                     };
+
+                    if let Some(tx_timestamps) = self.tx_timestamps.as_mut() {
+                        tx_timestamps.drain(&self.sockets[0], &mut self.statistic)?;
+                    }
                 }
             },
-            _ => return Err("Invalid io_uring mode for sender"),
+            _ => return Err(UdperfError::Other("Invalid io_uring mode for sender")),
         }
         Ok(())
     }
+
+    // Sends the datagrams of the packet buffer and waits for their echoes, before the next datagrams are sent
+    fn ping_pong_loop(&mut self, start_time: Instant, io_model: IOModel) -> Result<(), UdperfError> {
+        let mut echo_buffer = PacketBuffer::new(MsghdrVec::new(self.parameter.packet_buffer_size, self.parameter.mss, self.parameter.datagram_size as usize));
+
+        while start_time.elapsed().as_secs() < self.run_time_length {
+            // Check if the time elapsed since the last send operation is greater than or equal to self.parameters.interval seconds
+            if self.statistic_interval.output_interval != 0.0 && self.statistic_interval.last_send_instant.elapsed().as_secs_f64() >= self.statistic_interval.output_interval {
+                self.statistic_interval.calculate_interval(self.statistic.clone());
+                self.statistic = Statistic::new(self.parameter.clone());
+            }
+
+            let first_packet_id = self.next_packet_ids[self.current_socket];
+
+            match self.send_messages() {
+                Ok(_) => {},
+                Err(UdperfError::WouldBlock) => {
+                    self.statistic.amount_io_model_calls += 1;
+                    self.statistic.amount_eagain += 1;
+                    self.io_wait(io_model)?;
+                    continue;
+                },
+                Err(x) => {
+                    error!("Error sending message! Aborting measurement...");
+                    return Err(x)
+                }
+            }
+            self.statistic.amount_syscalls += 1;
+
+            let amount_requests = self.next_packet_ids[self.current_socket] - first_packet_id;
+            let mut amount_echoes = 0;
+            let deadline = Instant::now() + Duration::from_millis(crate::PING_PONG_TIMEOUT);
+
+            while amount_echoes < amount_requests {
+                match self.recv_echoes(&mut echo_buffer, first_packet_id) {
+                    Ok(amount) => amount_echoes += amount,
+                    Err(UdperfError::WouldBlock) => {
+                        self.statistic.amount_io_model_calls += 1;
+                        match self.io_wait_echo(io_model, deadline) {
+                            Ok(_) => {},
+                            Err(UdperfError::Timeout) => {
+                                debug!("Timeout waiting for echoes! {} of {} datagrams are lost", amount_requests - amount_echoes, amount_requests);
+                                self.statistic.amount_omitted_datagrams += (amount_requests - amount_echoes) as i64;
+                                break;
+                            },
+                            Err(x) => return Err(x)
+                        }
+                    },
+                    Err(x) => {
+                        error!("Error receiving echo! Aborting measurement...");
+                        return Err(x)
+                    }
+                }
+                self.statistic.amount_syscalls += 1;
+            }
+        }
+        Ok(())
+    }
+
+    fn recv_echoes(&mut self, echo_buffer: &mut PacketBuffer, first_packet_id: u64) -> Result<u64, UdperfError> {
+        match self.exchange_function {
+            ExchangeFunction::Normal => {
+                let buffer = echo_buffer.get_buffer_pointer_from_index(0)?;
+                self.sockets[self.current_socket].recv(buffer)?;
+                Ok(Self::process_echo(buffer, first_packet_id, &mut self.statistic))
+            },
+            ExchangeFunction::Msg => {
+                self.sockets[self.current_socket].recvmsg(echo_buffer.get_msghdr_from_index(0)?)?;
+                Ok(Self::process_echo(echo_buffer.get_buffer_pointer_from_index(0)?, first_packet_id, &mut self.statistic))
+            },
+            ExchangeFunction::Mmsg => {
+                let amount_received_mmsghdr = self.sockets[self.current_socket].recvmmsg(&mut echo_buffer.mmsghdr_vec)?;
+                Ok(echo_buffer.mmsghdr_vec.iter_mut()
+                    .take(amount_received_mmsghdr)
+                    .map(|mmsghdr| Self::process_echo(PacketBuffer::get_buffer_pointer_from_mmsghdr(mmsghdr), first_packet_id, &mut self.statistic))
+                    .sum())
+            }
+        }
+    }
+
+    // Records the round-trip time of an echoed datagram. Echoes of previous rounds arrived after the timeout and are already counted as lost.
+    fn process_echo(buffer: &[u8], first_packet_id: u64, statistic: &mut Statistic) -> u64 {
+        let packet_id = MessageHeader::get_packet_id(buffer);
+        if packet_id < first_packet_id {
+            debug!("Received late echo of packet {}, while waiting for packet {}", packet_id, first_packet_id);
+            return 0;
+        }
+
+        let rtt = util::get_monotonic_ns().saturating_sub(MessageHeader::get_send_time(buffer));
+        trace!("Received echo of packet {} with RTT {}ns", packet_id, rtt);
+        statistic.rtt_histogram.record(rtt);
+        1
+    }
+
+    fn io_wait_echo(&mut self, io_model: IOModel, deadline: Instant) -> Result<(), UdperfError> {
+        let timeout = deadline.saturating_duration_since(Instant::now()).as_millis() as i32;
+        if timeout == 0 {
+            return Err(UdperfError::Timeout);
+        }
+
+        match io_model {
+            IOModel::Select => {
+                let mut read_fds: libc::fd_set = unsafe { self.sockets[0].create_fdset() };
+                self.sockets[0].select(Some(&mut read_fds), None, timeout)
+            },
+            IOModel::Poll => {
+                let mut pollfd = self.sockets[0].create_pollfd(libc::POLLIN);
+                self.sockets[0].poll(&mut pollfd, timeout)
+            },
+            IOModel::Epoll => {
+                // In ping-pong mode the socket is registered for EPOLLIN
+                let epoll = self.epoll.as_mut().expect("Epoll instance not created");
+                epoll.wait(timeout)?;
+                epoll.next_ready();
+                Ok(())
+            },
+            _ => Ok(())
+        }
+    }
+
+    fn io_uring_ping_pong_loop(&mut self, start_time: Instant) -> Result<(), UdperfError> {
+        let socket_fd = self.sockets[0].get_socket_id();
+        let mut echo_buffer = PacketBuffer::new(MsghdrVec::new(1, self.parameter.mss, self.parameter.datagram_size as usize));
+        let mut io_uring_instance = IoUringPingPong::new(self.parameter.clone(), self.io_uring_sqpoll_fd)?;
+        let zerocopy = io_uring_instance.zerocopy;
+        // The receive request stays armed over multiple rounds, if an echo got lost
+        let mut recv_armed = false;
+
+        while start_time.elapsed().as_secs() < self.run_time_length {
+            // Check if the time elapsed since the last send operation is greater than or equal to self.parameters.interval seconds
+            if self.statistic_interval.output_interval != 0.0 && self.statistic_interval.last_send_instant.elapsed().as_secs_f64() >= self.statistic_interval.output_interval {
+                self.statistic_interval.calculate_interval(self.statistic.clone());
+                self.statistic = Statistic::new(self.parameter.clone());
+            }
+
+            let first_packet_id = self.next_packet_ids[self.current_socket];
+            self.next_packet_ids[self.current_socket] += self.packet_buffer.add_packet_ids(first_packet_id, Some(1))?;
+            io_uring_instance.submit_send(self.packet_buffer.get_msghdr_from_index(0)?, socket_fd)?;
+
+            let mut send_inflight = true;
+            let mut echo_received = false;
+            let deadline = Instant::now() + Duration::from_millis(crate::PING_PONG_TIMEOUT);
+
+            while send_inflight || !echo_received {
+                if !recv_armed && !echo_received {
+                    io_uring_instance.submit_recv(echo_buffer.get_msghdr_from_index(0)?, socket_fd)?;
+                    recv_armed = true;
+                }
+
+                // The buffer of the request can only be reused, after the send request completed
+                if !send_inflight && Instant::now() >= deadline {
+                    debug!("Timeout waiting for echo of packet {}!", first_packet_id);
+                    self.statistic.amount_omitted_datagrams += 1;
+                    break;
+                }
+
+                self.statistic.amount_io_model_calls += 1;
+                io_uring_instance.submit_and_wait()?;
+
+                for cqe in io_uring_instance.get_cq() {
+                    let amount_bytes = cqe.result();
+                    debug!("Received completion event with user_data: {}, and bytes: {}", cqe.user_data(), amount_bytes);
+
+                    match cqe.user_data() {
+                        USER_DATA_SEND if cqe.flags() & crate::io_uring::IORING_CQE_F_NOTIF != 0 => {
+                            // Second cqe of send zero copy, which confirms that the buffer can be reused
+                            if amount_bytes == i32::MIN {
+                                self.statistic.uring_copied_zc += 1;
+                            }
+                            send_inflight = false;
+                        },
+                        USER_DATA_SEND => {
+                            // Send zero copy publishes a second cqe, if the flag IORING_CQE_F_MORE is set
+                            send_inflight = zerocopy && check_multishot_status(cqe.flags());
+                            match amount_bytes {
+                                -11 => { // libc::EAGAIN == 11
+                                    debug!("EAGAIN: No messages can be send at the socket!");
+                                    self.statistic.amount_eagain += 1;
+                                    echo_received = true; // Nothing was sent, so no echo can be received
+                                },
+                                -111 => { // libc::ECONNREFUSED == 111
+                                    return Err(UdperfError::from_errno("Start the receiver first! Abort measurement...", libc::ECONNREFUSED));
+                                },
+                                _ if amount_bytes < 0 => {
+                                    error!("Error sending message! Negated error code: {}", amount_bytes);
+                                    return Err(UdperfError::from_errno("Failed to send data with io_uring", -amount_bytes))
+                                },
+                                _ => {
+                                    self.statistic.amount_datagrams += 1;
+                                    self.statistic.amount_data_bytes += amount_bytes as usize;
+                                }
+                            }
+                        },
+                        USER_DATA_RECV => {
+                            recv_armed = false;
+                            match parse_received_bytes(amount_bytes) {
+                                Ok(0) => {},
+                                Ok(_) => {
+                                    echo_received |= Self::process_echo(echo_buffer.get_buffer_pointer_from_index(0)?, first_packet_id, &mut self.statistic) == 1;
+                                },
+                                Err(UdperfError::WouldBlock) => {
+                                    self.statistic.amount_eagain += 1;
+                                },
+                                Err(x) => return Err(x)
+                            }
+                        },
+                        _ => warn!("Received completion event with unknown user_data {}", cqe.user_data())
+                    }
+                }
+            }
+        }
+
+        self.statistic = self.statistic.clone() + io_uring_instance.get_statistic();
+        Ok(())
+    }
 }
 
 
 impl Node for Sender {
-    fn run(&mut self, io_model: IOModel) -> Result<(Statistic, Vec<Statistic>), &'static str> {
+    fn run(&mut self, io_model: IOModel) -> Result<(Statistic, Vec<Statistic>), UdperfError> {
+        if self.parameter.reverse {
+            self.wait_for_remote_receiver()?;
+        }
+
         if self.parameter.multiplex_port != MultiplexPort::Sharing {
-            if let Ok(mss) = self.socket.get_mss() {
+            if let Ok(mss) = self.sockets[0].get_mss() {
                 info!("On the current socket the MSS is {}", mss);
             }
         }
         
-        self.send_control_message(MessageType::INIT)?;
-        // Wait some time to ensure the receiver is ready to receive messages
-        sleep(std::time::Duration::from_millis(crate::WAIT_CONTROL_MESSAGE));
+
+        if !self.parameter.control_channel {
+            self.send_control_message(MessageType::INIT)?;
+            // Wait some time to ensure the receiver is ready to receive messages
+            sleep(std::time::Duration::from_millis(self.parameter.control_message_delay()));
+        }
 
         info!("Start measurement...");
         let start_time = Instant::now();
         self.statistic_interval.start(None);
 
-        if io_model == IOModel::IoUring {
+        if self.parameter.ping_pong {
+            if io_model == IOModel::IoUring {
+                self.io_uring_ping_pong_loop(start_time)?;
+            } else {
+                self.ping_pong_loop(start_time, io_model)?;
+            }
+        } else if io_model == IOModel::IoUring {
             self.io_uring_loop(start_time)?;
         } else {
 
             while start_time.elapsed().as_secs() < self.run_time_length {
                 // Check if the time elapsed since the last send operation is greater than or equal to self.parameters.interval seconds
                 if self.statistic_interval.output_interval != 0.0 && self.statistic_interval.last_send_instant.elapsed().as_secs_f64() >= self.statistic_interval.output_interval {
+                    self.set_offered_data();
                     self.statistic_interval.calculate_interval(self.statistic.clone());
                     self.statistic = Statistic::new(self.parameter.clone());
                 }
 
                 match self.send_messages() {
-                    Ok(_) => {},
-                    Err("EAGAIN") => {
-                        self.statistic.amount_io_model_calls += 1;
+                    Ok(_) => {
+                        // The datagrams are distributed round-robin over the sockets of the thread
+                        self.current_socket = (self.current_socket + 1) % self.sockets.len();
+                    },
+                    Err(UdperfError::WouldBlock) => {
                         self.statistic.amount_eagain += 1;
-                        self.io_wait(io_model)?;
+                        // Continue with the sockets, which are already known to be writable, before waiting again
+                        if let Some(index) = self.epoll.as_mut().and_then(|epoll| epoll.next_ready()) {
+                            self.current_socket = index;
+                        } else {
+                            self.statistic.amount_io_model_calls += 1;
+                            self.io_wait(io_model)?;
+                        }
                     },
                     Err(x) => {
                         error!("Error sending message! Aborting measurement...");
@@ -384,6 +782,7 @@ impl Node for Sender {
         }
 
         // Print last interval
+        self.set_offered_data();
         if self.statistic_interval.output_interval != 0.0 && !self.statistic_interval.finished() {
             self.statistic_interval.calculate_interval(self.statistic.clone());
         }
@@ -401,7 +800,7 @@ impl Node for Sender {
         }
 
         // Ensures that the buffers are empty again, so that the last message actually arrives at the receiver
-        sleep(std::time::Duration::from_millis(crate::WAIT_CONTROL_MESSAGE));
+        sleep(std::time::Duration::from_millis(self.parameter.control_message_delay()));
         self.send_control_message(MessageType::LAST)?;
 
         final_statistic.set_test_duration(None, None);
@@ -410,18 +809,36 @@ impl Node for Sender {
         Ok((final_statistic, self.statistic_interval.statistics.clone()))
     }
 
-    fn io_wait(&mut self, io_model: IOModel) -> Result<(), &'static str> {
-        // Normally we would need to iterate over FDs and check which socket is ready
-        // Since we only have one socket, we directly call send_messages after io_wait returns
+    fn io_wait(&mut self, io_model: IOModel) -> Result<(), UdperfError> {
+        // Select and poll only wait for a single socket, so we directly call send_messages after io_wait returns
+        // Epoll returns the writable sockets, from which the next socket to send on is taken
         match io_model {
             IOModel::Select => {
-                let mut write_fds: libc::fd_set = unsafe { self.socket.create_fdset() };
-                self.socket.select(None, Some(&mut write_fds), -1)
+                let mut write_fds: libc::fd_set = unsafe { self.sockets[self.current_socket].create_fdset() };
+                self.sockets[self.current_socket].select(None, Some(&mut write_fds), -1)
 
             },
             IOModel::Poll => {
-                let mut pollfd = self.socket.create_pollfd(libc::POLLOUT);
-                self.socket.poll(&mut pollfd, -1)
+                let mut pollfd = self.sockets[self.current_socket].create_pollfd(libc::POLLOUT);
+                self.sockets[self.current_socket].poll(&mut pollfd, -1)
+            },
+            IOModel::Epoll => {
+                let socket = self.sockets[self.current_socket];
+                let epoll = self.epoll.as_mut().expect("Epoll instance not created");
+                if self.parameter.ping_pong {
+                    // The socket is registered for the echoes, so it is only registered for EPOLLOUT while waiting for a full send buffer
+                    epoll.modify(&socket, libc::EPOLLOUT, self.current_socket)?;
+                    let result = epoll.wait(-1);
+                    epoll.modify(&socket, libc::EPOLLIN, self.current_socket)?;
+                    epoll.next_ready();
+                    return result;
+                }
+
+                epoll.wait(-1)?;
+                if let Some(index) = epoll.next_ready() {
+                    self.current_socket = index;
+                }
+                Ok(())
             }
             _ => Ok(())
         }
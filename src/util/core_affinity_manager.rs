@@ -4,6 +4,7 @@ use hwlocality::{cpu::{binding::CpuBindingFlags, cpuset::CpuSet}, object::types:
 use log::{info, warn};
 
 use super::UDPerfMode;
+use crate::UdperfError;
 
 pub struct CoreAffinityManager {
     topology: hwlocality::Topology,
@@ -11,7 +12,9 @@ pub struct CoreAffinityManager {
     numa_affinity: bool,
     amount_cpus: usize,
     next_numa_node: u64,
-    next_core_id: usize 
+    next_core_id: usize,
+    // Cores the threads of the measurement are bound to
+    bound_core_ids: Vec<usize>
 }
 
 impl CoreAffinityManager {
@@ -58,27 +61,29 @@ impl CoreAffinityManager {
             numa_affinity,
             amount_cpus,
             next_numa_node: 0,
-            next_core_id
+            next_core_id,
+            bound_core_ids: Vec::new()
         }
     }
 
-    pub fn set_affinity(&mut self) -> Result<(), &'static str> {
+    // Returns the ID of the core the thread is bound to
+    pub fn set_affinity(&mut self) -> Result<usize, UdperfError> {
         let mut core_id = self.get_core_id();
 
         core_id = if self.numa_affinity {
             // Get the NUMA node to schedule thread on
             let numa_nodes = self.topology.objects_with_type(ObjectType::NUMANode).collect::<Vec<_>>();
             if self.next_numa_node as usize >= numa_nodes.len() {
-                return Err("NUMA node index out of bounds");
+                return Err(UdperfError::Other("NUMA node index out of bounds"));
             }
             // In NUMA, the returned core_id is relativ for the NUMA node. We need to get the absolut core ID
             let numa_node = &numa_nodes.get(self.next_numa_node as usize).unwrap();
-            let core_bitmap = numa_node.cpuset().ok_or("Failed to get CPU set for NUMA node")?;
+            let core_bitmap = numa_node.cpuset().ok_or(UdperfError::Other("Failed to get CPU set for NUMA node"))?;
             match core_bitmap.iter_set().nth(core_id) {
                 Some(absolute_core_id) => {
                 usize::from(absolute_core_id) 
                 },
-                None => return Err("Failed to get core ID from CPU set")
+                None => return Err(UdperfError::Other("Failed to get core ID from CPU set"))
             }
         } else {
             core_id
@@ -87,7 +92,9 @@ impl CoreAffinityManager {
         info!("Binding thread {:?} to core ID: {}", thread::current().id(), core_id);
         let mut core_cpuset = CpuSet::new();
         core_cpuset.set(core_id);
-        self.bind_to_cpuset(core_cpuset)
+        self.bind_to_cpuset(core_cpuset)?;
+        self.bound_core_ids.push(core_id);
+        Ok(core_id)
     }
 
     fn get_core_id(&mut self) -> usize {
@@ -113,20 +120,27 @@ impl CoreAffinityManager {
         self.next_numa_node
     }
 
-    pub fn bind_to_core(&mut self, core_id: usize) -> Result<(), &'static str> {
+    pub fn bound_core_ids(&self) -> Vec<usize> {
+        let mut core_ids = self.bound_core_ids.clone();
+        core_ids.sort_unstable();
+        core_ids.dedup();
+        core_ids
+    }
+
+    pub fn bind_to_core(&mut self, core_id: usize) -> Result<(), UdperfError> {
         let mut core_cpuset = CpuSet::new();
         core_cpuset.set(core_id);
         self.bind_to_cpuset(core_cpuset)
     }
 
-    fn bind_to_cpuset(&mut self, core_cpuset: CpuSet) -> Result<(), &'static str> {
+    fn bind_to_cpuset(&mut self, core_cpuset: CpuSet) -> Result<(), UdperfError> {
         match self.topology.bind_cpu(&core_cpuset, CpuBindingFlags::THREAD) {
             Ok(_) => {
                 Ok(())
             },
             Err(x) => {
                 warn!("Error binding thread to core: {}", x);
-                Err("Error binding thread to core")
+                Err(UdperfError::Other("Error binding thread to core"))
             }
         }
     }
@@ -1,60 +1,218 @@
-use libc::{getrusage, rusage, RUSAGE_SELF};
+use libc::{getrusage, rusage, RUSAGE_SELF, RUSAGE_THREAD};
+use log::warn;
+use std::collections::HashMap;
+use std::fs;
 use std::time::Instant;
 
+// User and system time in microseconds and the other counters of rusage
+#[derive(Debug, Clone, Copy)]
+struct CpuTime {
+    user: f64,
+    system: f64,
+    voluntary_context_switches: u64,
+    involuntary_context_switches: u64,
+    minor_page_faults: u64,
+    major_page_faults: u64,
+    max_rss: u64
+}
+
+// CPU utilization in percent and the context switches and page faults in the measured period. The maximum resident set size (in KiB) is always the one of the process.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CpuUsage {
+    pub user: f64,
+    pub system: f64,
+    pub total: f64,
+    pub voluntary_context_switches: u64,
+    pub involuntary_context_switches: u64,
+    pub minor_page_faults: u64,
+    pub major_page_faults: u64,
+    pub max_rss: u64
+}
+
+#[derive(Debug, Clone)]
 pub struct CpuUtil {
+    who: libc::c_int,
     last_instant: Instant,
-    last_usage: rusage,
+    last_usage: CpuTime,
     first_instant: Instant,
-    first_usage: rusage
+    first_usage: CpuTime
 }
 
 impl CpuUtil {
+    // CPU utilization of the whole process (all threads)
     pub fn new() -> Self {
-        let mut last_usage = unsafe { std::mem::zeroed() };
-        unsafe {
-            getrusage(RUSAGE_SELF, &mut last_usage);
-        }
+        Self::with_target(RUSAGE_SELF)
+    }
+
+    // CPU utilization of the calling thread. Has to be used by the same thread, which created it.
+    pub fn thread() -> Self {
+        Self::with_target(RUSAGE_THREAD)
+    }
+
+    fn with_target(who: libc::c_int) -> Self {
+        let usage = Self::get_usage(who);
         CpuUtil {
+            who,
             last_instant: Instant::now(),
-            last_usage,
+            last_usage: usage,
             first_instant: Instant::now(),
-            first_usage: unsafe { std::mem::zeroed() }
+            first_usage: usage
         }
     }
 
-    // Very similar code to iperf3, but with some modifications to rustify it
-    fn get_cpu_util(&mut self, rusage: libc::rusage, instant: Instant) -> (f64, f64, f64) {
-        let now = Instant::now();
-        let mut current_usage = unsafe { std::mem::zeroed() };
+    fn get_usage(who: libc::c_int) -> CpuTime {
+        let mut usage: rusage = unsafe { std::mem::zeroed() };
         unsafe {
-            getrusage(RUSAGE_SELF, &mut current_usage);
+            getrusage(who, &mut usage);
         }
+        CpuTime {
+            user: usage.ru_utime.tv_sec as f64 * 1_000_000.0 + usage.ru_utime.tv_usec as f64,
+            system: usage.ru_stime.tv_sec as f64 * 1_000_000.0 + usage.ru_stime.tv_usec as f64,
+            voluntary_context_switches: usage.ru_nvcsw as u64,
+            involuntary_context_switches: usage.ru_nivcsw as u64,
+            minor_page_faults: usage.ru_minflt as u64,
+            major_page_faults: usage.ru_majflt as u64,
+            max_rss: usage.ru_maxrss as u64
+        }
+    }
+
+    // Very similar code to iperf3, but with some modifications to rustify it
+    fn get_cpu_util(&mut self, usage: CpuTime, instant: Instant) -> CpuUsage {
+        let now = Instant::now();
+        let current_usage = Self::get_usage(self.who);
 
         let timediff = now.duration_since(instant).as_micros() as f64;
-        let userdiff = (current_usage.ru_utime.tv_sec as f64 * 1_000_000.0 + current_usage.ru_utime.tv_usec as f64)
-            - (rusage.ru_utime.tv_sec as f64 * 1_000_000.0 + rusage.ru_utime.tv_usec as f64);
-        let systemdiff = (current_usage.ru_stime.tv_sec as f64 * 1_000_000.0 + current_usage.ru_stime.tv_usec as f64)
-            - (rusage.ru_stime.tv_sec as f64 * 1_000_000.0 + rusage.ru_stime.tv_usec as f64);
+        let userdiff = current_usage.user - usage.user;
+        let systemdiff = current_usage.system - usage.system;
         // Calculate total CPU usage: Sum of user and system time differences
         let totaldiff = userdiff + systemdiff; 
 
         // Update last measurements
         self.last_instant = now;
         self.last_usage = current_usage;
-        if self.first_usage.ru_utime.tv_sec == 0 && self.first_usage.ru_stime.tv_sec == 0 {
-            self.first_instant = now;
-            self.first_usage = current_usage;
-        }
 
-        // userspace, system, total cpu time
-        ((userdiff / timediff) * 100.0, (systemdiff / timediff) * 100.0, (totaldiff / timediff) * 100.0)
+        let mut cpu_usage = CpuUsage {
+            voluntary_context_switches: current_usage.voluntary_context_switches - usage.voluntary_context_switches,
+            involuntary_context_switches: current_usage.involuntary_context_switches - usage.involuntary_context_switches,
+            minor_page_faults: current_usage.minor_page_faults - usage.minor_page_faults,
+            major_page_faults: current_usage.major_page_faults - usage.major_page_faults,
+            max_rss: current_usage.max_rss,
+            ..Default::default()
+        };
+        if timediff != 0.0 {
+            // userspace, system, total cpu time
+            (cpu_usage.user, cpu_usage.system, cpu_usage.total) = ((userdiff / timediff) * 100.0, (systemdiff / timediff) * 100.0, (totaldiff / timediff) * 100.0);
+        }
+        cpu_usage
     }
 
-    pub fn get_relative_cpu_util(&mut self) -> (f64, f64, f64) {
+    // Utilization since the last call
+    pub fn get_relative_cpu_util(&mut self) -> CpuUsage {
         self.get_cpu_util(self.last_usage, self.last_instant)
     }
 
-    pub fn get_absolut_cpu_util(&mut self) -> (f64, f64, f64) {
+    // Utilization since the creation
+    pub fn get_absolut_cpu_util(&mut self) -> CpuUsage {
         self.get_cpu_util(self.first_usage, self.first_instant)
     }
-}
\ No newline at end of file
+}
+
+// Time of a single core in clock ticks
+#[derive(Debug, Clone, Copy, Default)]
+struct CoreTime {
+    user: u64,
+    system: u64,
+    irq: u64,
+    softirq: u64,
+    total: u64
+}
+
+// CPU utilization of the cores from /proc/stat. In contrast to getrusage, it includes the time the kernel spends in interrupt and softirq context, where most of the receive path of UDP runs.
+// The utilization is reported in percent of a single core and summed up over the cores, like the utilization of the process.
+#[derive(Debug, Clone)]
+pub struct SystemCpuUtil {
+    last_usage: HashMap<usize, CoreTime>,
+    first_usage: HashMap<usize, CoreTime>
+}
+
+impl SystemCpuUtil {
+    pub fn new() -> Self {
+        let usage = Self::get_usage();
+        SystemCpuUtil {
+            last_usage: usage.clone(),
+            first_usage: usage
+        }
+    }
+
+    fn get_usage() -> HashMap<usize, CoreTime> {
+        match fs::read_to_string("/proc/stat") {
+            Ok(content) => Self::parse_proc_stat(&content),
+            Err(x) => {
+                warn!("Error reading /proc/stat: {}", x);
+                HashMap::new()
+            }
+        }
+    }
+
+    // Lines of the single cores: cpu<ID> user nice system idle iowait irq softirq steal guest guest_nice
+    fn parse_proc_stat(content: &str) -> HashMap<usize, CoreTime> {
+        let mut usage = HashMap::new();
+        for line in content.lines() {
+            let mut fields = line.split_whitespace();
+            let Some(core_id) = fields.next().and_then(|name| name.strip_prefix("cpu")).and_then(|core_id| core_id.parse::<usize>().ok()) else {
+                continue;
+            };
+            let values: Vec<u64> = fields.take(8).map(|value| value.parse().unwrap_or(0)).collect();
+            if values.len() < 7 {
+                continue;
+            }
+            usage.insert(core_id, CoreTime {
+                user: values[0] + values[1],
+                system: values[2],
+                irq: values[5],
+                softirq: values[6],
+                total: values.iter().sum()
+            });
+        }
+        usage
+    }
+
+    // Without core IDs, all cores of the system are measured
+    fn get_cpu_util(&mut self, usage: &HashMap<usize, CoreTime>, core_ids: Option<&[usize]>) -> (f64, f64, f64, f64) {
+        let current_usage = Self::get_usage();
+        let (mut user, mut system, mut irq, mut softirq) = (0.0, 0.0, 0.0, 0.0);
+
+        for (core_id, current) in current_usage.iter().filter(|(core_id, _)| core_ids.is_none_or(|core_ids| core_ids.contains(core_id))) {
+            let Some(previous) = usage.get(core_id) else {
+                continue;
+            };
+            let total = current.total.saturating_sub(previous.total) as f64;
+            if total == 0.0 {
+                continue;
+            }
+            user += current.user.saturating_sub(previous.user) as f64 / total * 100.0;
+            system += current.system.saturating_sub(previous.system) as f64 / total * 100.0;
+            irq += current.irq.saturating_sub(previous.irq) as f64 / total * 100.0;
+            softirq += current.softirq.saturating_sub(previous.softirq) as f64 / total * 100.0;
+        }
+
+        self.last_usage = current_usage;
+        // userspace, system, irq, softirq cpu time
+        (user, system, irq, softirq)
+    }
+
+    // Description of the measured cores for the output
+    pub fn cores(core_ids: Option<&[usize]>) -> String {
+        core_ids.map_or(String::from("all"), |core_ids| super::statistic::format_id_ranges(core_ids.iter().map(|&core_id| core_id as u64)))
+    }
+
+    pub fn get_relative_cpu_util(&mut self, core_ids: Option<&[usize]>) -> (f64, f64, f64, f64) {
+        let usage = std::mem::take(&mut self.last_usage);
+        self.get_cpu_util(&usage, core_ids)
+    }
+
+    pub fn get_absolut_cpu_util(&mut self, core_ids: Option<&[usize]>) -> (f64, f64, f64, f64) {
+        let usage = self.first_usage.clone();
+        self.get_cpu_util(&usage, core_ids)
+    }
+}
@@ -0,0 +1,140 @@
+use crate::UdperfError;
+use super::random::SplitMix64;
+use super::statistic::{DatagramSizeDistribution, Parameter};
+
+// IMIX with the IP packet sizes 64/576/1500 in the ratio 7:4:1
+const IMIX: [WeightedSize; 3] = [
+    WeightedSize { size: 64, weight: 7 },
+    WeightedSize { size: 576, weight: 4 },
+    WeightedSize { size: 1500, weight: 1 },
+];
+const IPV4_UDP_HEADER_LENGTH: u32 = 20 + 8;
+const IPV6_UDP_HEADER_LENGTH: u32 = 40 + 8;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WeightedSize {
+    pub size: u32,
+    pub weight: u32,
+}
+
+// Draws the size of every datagram of a batch from the distribution. Every sender thread has its own generator.
+pub struct DatagramSizes {
+    distribution: DatagramSizeDistribution,
+    min: u64,
+    max: u64,
+    // Weighted list: Size and cumulative weight
+    sizes: Vec<(usize, u64)>,
+    random: SplitMix64,
+}
+
+impl DatagramSizes {
+    // Returns None for fixed datagram sizes
+    pub fn new(parameter: &Parameter) -> Option<DatagramSizes> {
+        if parameter.datagram_size_distribution == DatagramSizeDistribution::Fixed {
+            return None;
+        }
+
+        let mut total_weight = 0;
+        let sizes = parameter.datagram_sizes.iter().map(|x| {
+            total_weight += x.weight as u64;
+            (x.size as usize, total_weight)
+        }).collect();
+
+        Some(DatagramSizes {
+            distribution: parameter.datagram_size_distribution,
+            min: parameter.datagram_size_min as u64,
+            max: parameter.datagram_size as u64,
+            sizes,
+            random: SplitMix64::from_time(),
+        })
+    }
+
+    pub fn next_size(&mut self) -> usize {
+        match self.distribution {
+            DatagramSizeDistribution::Fixed => self.max as usize,
+            DatagramSizeDistribution::Uniform => (self.min + self.random.next_below(self.max - self.min + 1)) as usize,
+            DatagramSizeDistribution::Imix | DatagramSizeDistribution::File => {
+                let total_weight = self.sizes.last().map_or(1, |(_, weight)| *weight);
+                let value = self.random.next_below(total_weight);
+                self.sizes.iter().find(|(_, weight)| value < *weight).map_or(self.max as usize, |(size, _)| *size)
+            }
+        }
+    }
+}
+
+// Mean size of the datagrams in bytes, which is used to convert datagrams into bytes (e.g. the offered data of the pacer)
+pub fn mean_datagram_size(parameter: &Parameter) -> f64 {
+    match parameter.datagram_size_distribution {
+        DatagramSizeDistribution::Fixed => parameter.datagram_size as f64,
+        DatagramSizeDistribution::Uniform => (parameter.datagram_size_min as f64 + parameter.datagram_size as f64) / 2.0,
+        DatagramSizeDistribution::Imix | DatagramSizeDistribution::File => {
+            let total_weight: u64 = parameter.datagram_sizes.iter().map(|x| x.weight as u64).sum();
+            let total_size: u64 = parameter.datagram_sizes.iter().map(|x| x.size as u64 * x.weight as u64).sum();
+            total_size as f64 / total_weight.max(1) as f64
+        }
+    }
+}
+
+// IMIX is defined with IP packet sizes, so the IP and UDP headers are subtracted to get the size of the datagrams. Datagrams can't be smaller than the message header.
+pub fn imix(ipv6: bool, min_size: u32) -> Vec<WeightedSize> {
+    let header_length = if ipv6 { IPV6_UDP_HEADER_LENGTH } else { IPV4_UDP_HEADER_LENGTH };
+    IMIX.iter().map(|x| WeightedSize { size: u32::max(x.size - header_length, min_size), weight: x.weight }).collect()
+}
+
+// Reads the weighted list of datagram sizes from a file. Every line contains the datagram size in bytes and its weight (e.g. "1472 1"). Empty lines and lines starting with # are ignored.
+pub fn read_datagram_size_file(path: &std::path::Path) -> Result<Vec<WeightedSize>, UdperfError> {
+    let content = std::fs::read_to_string(path).map_err(|x| UdperfError::from_io_error("Unable to read datagram size file", &x))?;
+
+    let mut sizes = Vec::new();
+    for line in content.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+        let values: Vec<&str> = line.split_whitespace().collect();
+        let size = match values[..] {
+            [size, weight] => match (size.parse(), weight.parse()) {
+                (Ok(size), Ok(weight)) => WeightedSize { size, weight },
+                _ => return Err(UdperfError::InvalidParameter("Datagram size file contains a line, which isn't a size in bytes and a weight!"))
+            },
+            _ => return Err(UdperfError::InvalidParameter("Datagram size file contains a line, which isn't a size in bytes and a weight!"))
+        };
+
+        if size.weight == 0 {
+            return Err(UdperfError::InvalidParameter("Datagram size file contains a size with a weight of 0!"));
+        }
+        sizes.push(size);
+    }
+
+    if sizes.is_empty() {
+        return Err(UdperfError::InvalidParameter("Datagram size file doesn't contain any size!"));
+    }
+    Ok(sizes)
+}
+
+// The weighted sizes are serialized as a single string (e.g. "36:7;548:4;1472:1"), since the CSV output can't contain nested lists
+pub mod weighted_sizes {
+    use super::WeightedSize;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S>(sizes: &[WeightedSize], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let sizes: Vec<String> = sizes.iter().map(|x| format!("{}:{}", x.size, x.weight)).collect();
+        serializer.serialize_str(&sizes.join(";"))
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<WeightedSize>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = String::deserialize(deserializer)?;
+        value.split(';')
+            .filter(|x| !x.is_empty())
+            .map(|x| {
+                let (size, weight) = x.split_once(':').ok_or_else(|| serde::de::Error::custom("Invalid weighted datagram size"))?;
+                Ok(WeightedSize {
+                    size: size.parse().map_err(serde::de::Error::custom)?,
+                    weight: weight.parse().map_err(serde::de::Error::custom)?,
+                })
+            })
+            .collect()
+    }
+}
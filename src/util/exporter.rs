@@ -0,0 +1,151 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use log::{debug, error};
+use serde_json::{Map, Value};
+
+use super::statistic::Statistic;
+
+// Fields of the statistic, which identify a thread and are therefore used as labels/tags instead of values
+const THREAD_LABELS: [&str; 3] = ["thread_id", "core_id", "port"];
+const MEASUREMENT_NAME: &str = "udperf";
+
+// The textfile collector of Prometheus reads the whole file, so the latest samples of every series (mode, record type and thread) are kept per output file until the next measurement starts
+#[allow(clippy::type_complexity)]
+static PROMETHEUS_SERIES: Mutex<BTreeMap<PathBuf, BTreeMap<String, Vec<(String, String)>>>> = Mutex::new(BTreeMap::new());
+
+// Kind of the printed statistic: Interval of all threads, statistic of a single thread or the summary of all threads
+fn record_type(statistic: &Statistic, interval_print: bool) -> &'static str {
+    if interval_print {
+        "interval"
+    } else if statistic.thread_id.is_some() {
+        "thread"
+    } else {
+        "summary"
+    }
+}
+
+// The labels of benchmark automation, the direction and the thread, which are attached to every record
+fn labels(statistic: &Statistic, interval_print: bool) -> Vec<(&'static str, Value)> {
+    let mut labels = vec![
+        ("label_test", Value::from(statistic.parameter.test_name.clone())),
+        ("label_run", Value::from(statistic.parameter.run_name.clone())),
+        ("repetition_id", Value::from(statistic.parameter.repetition_id)),
+        ("mode", Value::from(format!("{:?}", statistic.parameter.mode).to_lowercase())),
+        ("record", Value::from(record_type(statistic, interval_print))),
+    ];
+    if let Some(thread_id) = statistic.thread_id {
+        labels.push(("thread_id", Value::from(thread_id)));
+    }
+    if let Some(core_id) = statistic.core_id {
+        labels.push(("core_id", Value::from(core_id)));
+    }
+    if let Some(port) = statistic.port {
+        labels.push(("port", Value::from(port)));
+    }
+    labels
+}
+
+// Labels of the text based formats don't have a type
+fn label_text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        _ => value.to_string()
+    }
+}
+
+// The measured fields of the statistic without the parameters of the measurement, which are the same for every record
+fn statistic_fields(statistic: &Statistic) -> Map<String, Value> {
+    let Ok(Value::Object(mut fields)) = serde_json::to_value(statistic) else {
+        error!("Failed to serialize statistic");
+        return Map::new();
+    };
+    if let Ok(Value::Object(parameter)) = serde_json::to_value(&statistic.parameter) {
+        parameter.keys().for_each(|key| { fields.remove(key); });
+    }
+    THREAD_LABELS.iter().for_each(|key| { fields.remove(*key); });
+    fields
+}
+
+// A single JSON object per line with the labels and the measured fields, which can be streamed into a log pipeline
+pub fn print_json_line(statistic: &Statistic, interval_print: bool) {
+    let mut record: Map<String, Value> = labels(statistic, interval_print).into_iter()
+        .map(|(label, value)| (label.to_string(), value))
+        .collect();
+    record.extend(statistic_fields(statistic));
+    println!("{}", Value::Object(record));
+}
+
+// InfluxDB line protocol: measurement,tags fields timestamp. Integers are suffixed with i, so the type of a field doesn't change between records.
+pub fn print_influx_line(statistic: &Statistic, interval_print: bool) {
+    let tags: Vec<String> = labels(statistic, interval_print).iter()
+        .map(|(label, value)| format!("{}={}", label, escape_influx_tag(&label_text(value))))
+        .collect();
+    let fields: Vec<String> = statistic_fields(statistic).iter()
+        .filter_map(|(name, value)| match value {
+            Value::Number(number) if number.is_f64() => Some(format!("{}={}", name, number)),
+            Value::Number(number) => Some(format!("{}={}i", name, number)),
+            _ => None
+        })
+        .collect();
+
+    let timestamp = (statistic.end_timestamp * 1_000_000_000.0) as u64;
+    if timestamp == 0 {
+        println!("{},{} {}", MEASUREMENT_NAME, tags.join(","), fields.join(","));
+    } else {
+        println!("{},{} {} {}", MEASUREMENT_NAME, tags.join(","), fields.join(","), timestamp);
+    }
+}
+
+fn escape_influx_tag(value: &str) -> String {
+    value.replace('\\', "\\\\").replace(',', "\\,").replace('=', "\\=").replace(' ', "\\ ")
+}
+
+// Series of a previous measurement (e.g. with more threads) would otherwise stay in the textfile of the next measurement
+pub fn reset_prometheus_series(output_file_path: &Path) {
+    PROMETHEUS_SERIES.lock().unwrap().remove(output_file_path);
+}
+
+// Prometheus exposition format for the textfile collector of the node exporter. The file is replaced atomically, so the collector never reads a partial file.
+pub fn write_prometheus_file(statistic: &Statistic, interval_print: bool) {
+    let labels = labels(statistic, interval_print).iter()
+        .map(|(label, value)| format!("{}=\"{}\"", label, label_text(value).replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")))
+        .collect::<Vec<String>>()
+        .join(",");
+    let samples = statistic_fields(statistic).iter()
+        .filter_map(|(name, value)| value.as_f64().map(|number| (format!("{}_{}", MEASUREMENT_NAME, name), number.to_string())))
+        .collect();
+
+    let mut files = PROMETHEUS_SERIES.lock().unwrap();
+    let series = files.entry(statistic.parameter.output_file_path.clone()).or_default();
+    series.insert(labels, samples);
+
+    // All samples of a metric have to be grouped below its TYPE line
+    let mut metrics: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    for (labels, samples) in series.iter() {
+        for (metric, value) in samples {
+            metrics.entry(metric).or_default().push(format!("{}{{{}}} {}", metric, labels, value));
+        }
+    }
+    let mut exposition = String::new();
+    for (metric, samples) in metrics {
+        exposition.push_str(&format!("# TYPE {} gauge\n", metric));
+        samples.iter().for_each(|sample| { exposition.push_str(sample); exposition.push('\n'); });
+    }
+
+    let mut output_file = statistic.parameter.output_file_path.clone();
+    output_file.set_extension("prom");
+    let mut temporary_file = output_file.clone();
+    temporary_file.set_extension("prom.tmp");
+    if let Some(parent_dir) = output_file.parent().filter(|parent_dir| !parent_dir.as_os_str().is_empty() && !parent_dir.exists()) {
+        if let Err(err) = std::fs::create_dir_all(parent_dir) {
+            error!("Failed to create output directory: {:?}", err);
+            return;
+        }
+    }
+
+    match std::fs::write(&temporary_file, exposition).and_then(|_| std::fs::rename(&temporary_file, &output_file)) {
+        Ok(_) => debug!("Metrics written to {}", output_file.display()),
+        Err(err) => error!("Failed to write metrics to {}: {}", output_file.display(), err)
+    }
+}
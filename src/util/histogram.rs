@@ -0,0 +1,113 @@
+// Log-linear histogram in the style of HdrHistogram (http://hdrhistogram.org/).
+// Values smaller than 2^SUB_BUCKET_BITS are recorded exactly. Larger values are split into SUB_BUCKET_HALF linear sub-buckets per power of two, which keeps the relative error below 1/SUB_BUCKET_HALF (< 1%).
+const SUB_BUCKET_BITS: u32 = 8;
+const SUB_BUCKET_HALF: usize = 1 << (SUB_BUCKET_BITS - 1);
+const AMOUNT_SEGMENTS: usize = 64 - SUB_BUCKET_BITS as usize + 2;
+
+#[derive(Debug, Clone, Default)]
+pub struct Histogram {
+    // The buckets are split into segments of SUB_BUCKET_HALF buckets, which are only allocated with the first value recorded in them.
+    // Most statistics never record any value and the values of a single flow only span a few powers of two, so thousands of flows keep their histograms small.
+    counts: Vec<Option<Box<[u64; SUB_BUCKET_HALF]>>>,
+    total: u64,
+    min: u64,
+    max: u64,
+}
+
+impl Histogram {
+    pub fn new() -> Histogram {
+        Histogram::default()
+    }
+
+    pub fn record(&mut self, value: u64) {
+        if self.counts.is_empty() {
+            self.counts = vec![None; AMOUNT_SEGMENTS];
+            self.min = value;
+        }
+
+        let index = Self::index_of(value);
+        Self::segment(&mut self.counts[index / SUB_BUCKET_HALF])[index % SUB_BUCKET_HALF] += 1;
+        self.total += 1;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+    }
+
+    pub fn merge(&mut self, other: &Histogram) {
+        if other.is_empty() {
+            return;
+        }
+        if self.is_empty() {
+            *self = other.clone();
+            return;
+        }
+
+        for (segment, other_segment) in self.counts.iter_mut().zip(other.counts.iter()) {
+            if let Some(other_segment) = other_segment {
+                for (count, other_count) in Self::segment(segment).iter_mut().zip(other_segment.iter()) {
+                    *count += other_count;
+                }
+            }
+        }
+        self.total += other.total;
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.total == 0
+    }
+
+    pub fn len(&self) -> u64 {
+        self.total
+    }
+
+    pub fn min(&self) -> u64 {
+        self.min
+    }
+
+    pub fn max(&self) -> u64 {
+        self.max
+    }
+
+    // Returns the highest value of the bucket, in which the given percentile (0-100) of all recorded values lies
+    pub fn percentile(&self, percentile: f64) -> u64 {
+        if self.is_empty() {
+            return 0;
+        }
+
+        let target = ((percentile / 100.0 * self.total as f64).ceil() as u64).clamp(1, self.total);
+        let mut amount_values = 0;
+
+        for (segment_index, segment) in self.counts.iter().enumerate() {
+            let Some(segment) = segment else { continue };
+            for (index, count) in segment.iter().enumerate() {
+                amount_values += count;
+                if amount_values >= target {
+                    return Self::highest_value_of(segment_index * SUB_BUCKET_HALF + index).clamp(self.min, self.max);
+                }
+            }
+        }
+        self.max
+    }
+
+    fn segment(segment: &mut Option<Box<[u64; SUB_BUCKET_HALF]>>) -> &mut [u64; SUB_BUCKET_HALF] {
+        segment.get_or_insert_with(|| Box::new([0; SUB_BUCKET_HALF]))
+    }
+
+    fn index_of(value: u64) -> usize {
+        if value < (1 << SUB_BUCKET_BITS) {
+            return value as usize;
+        }
+        let shift = (63 - value.leading_zeros()) - (SUB_BUCKET_BITS - 1);
+        shift as usize * SUB_BUCKET_HALF + (value >> shift) as usize
+    }
+
+    fn highest_value_of(index: usize) -> u64 {
+        if index < (1 << SUB_BUCKET_BITS) {
+            return index as u64;
+        }
+        let shift = index / SUB_BUCKET_HALF - 1;
+        let sub_bucket = (index - shift * SUB_BUCKET_HALF) as u128;
+        (((sub_bucket + 1) << shift) - 1).min(u64::MAX as u128) as u64
+    }
+}
@@ -0,0 +1,125 @@
+use std::collections::BTreeMap;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use log::{debug, info};
+
+use super::core_affinity_manager::CoreAffinityManager;
+use super::cpu_util::SystemCpuUtil;
+use super::statistic::{OutputFormat, Statistic};
+use super::udp_counters::UdpCounterSnapshot;
+use super::UDPerfMode;
+
+// Interval statistics of the same direction and interval ID, which are merged until every thread of the direction reported it
+struct PendingInterval {
+    statistic: Statistic,
+    amount_reports: u16,
+    deadline: Instant,
+}
+
+// Collects the interval statistics of all threads while the measurement is running and prints them as soon as an interval is complete.
+// If a thread doesn't report an interval (e.g. it stopped early), the interval is printed incomplete after another interval length passed.
+pub struct IntervalReporter {
+    receiver: mpsc::Receiver<Statistic>,
+    output_format: OutputFormat,
+    amount_threads: u16,
+    output_interval: Duration,
+    pending: BTreeMap<(u64, bool), PendingInterval>,
+    reported: Vec<Statistic>,
+    // Utilization of the cores for the receiving and sending direction. With core affinity only the pinned cores are measured.
+    system_cpu_util: [SystemCpuUtil; 2],
+    pinned_cores: Option<Arc<Mutex<CoreAffinityManager>>>,
+    // Kernel UDP counters for the receiving and sending direction
+    udp_counters: [UdpCounterSnapshot; 2],
+}
+
+impl IntervalReporter {
+    // Returns the handle of the reporter thread and the channel the nodes send their interval statistics to. The thread returns all reported intervals.
+    pub fn spawn(output_format: OutputFormat, amount_threads: u16, output_interval: f64, pinned_cores: Option<Arc<Mutex<CoreAffinityManager>>>, ipv6: bool) -> (JoinHandle<Vec<Statistic>>, mpsc::Sender<Statistic>) {
+        let (sender, receiver) = mpsc::channel();
+        let reporter = IntervalReporter {
+            receiver,
+            output_format,
+            amount_threads,
+            output_interval: Duration::from_secs_f64(output_interval),
+            pending: BTreeMap::new(),
+            reported: Vec::new(),
+            system_cpu_util: [SystemCpuUtil::new(), SystemCpuUtil::new()],
+            pinned_cores,
+            udp_counters: [UdpCounterSnapshot::new(ipv6), UdpCounterSnapshot::new(ipv6)],
+        };
+
+        (thread::spawn(move || reporter.run()), sender)
+    }
+
+    fn run(mut self) -> Vec<Statistic> {
+        loop {
+            let next_deadline = self.pending.values().map(|pending| pending.deadline).min();
+            let result = match next_deadline {
+                Some(deadline) => self.receiver.recv_timeout(deadline.saturating_duration_since(Instant::now())),
+                None => self.receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            };
+
+            match result {
+                Ok(statistic) => self.add(statistic),
+                Err(RecvTimeoutError::Timeout) => self.print_overdue(),
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+
+        // All nodes finished: Print the remaining intervals in order
+        debug!("Interval reporter finished. Printing {} remaining intervals", self.pending.len());
+        for (_, pending) in std::mem::take(&mut self.pending) {
+            self.print(pending.statistic);
+        }
+        self.reported
+    }
+
+    fn add(&mut self, statistic: Statistic) {
+        // In bidirectional mode both directions report the same interval IDs
+        let key = (statistic.interval_id, statistic.parameter.mode == UDPerfMode::Sender);
+        let pending = match self.pending.remove(&key) {
+            Some(pending) => PendingInterval {
+                statistic: pending.statistic + statistic,
+                amount_reports: pending.amount_reports + 1,
+                deadline: pending.deadline,
+            },
+            None => PendingInterval {
+                statistic,
+                amount_reports: 1,
+                deadline: Instant::now() + self.output_interval,
+            },
+        };
+
+        if pending.amount_reports >= self.amount_threads {
+            self.print(pending.statistic);
+        } else {
+            self.pending.insert(key, pending);
+        }
+    }
+
+    fn print_overdue(&mut self) {
+        let now = Instant::now();
+        let overdue: Vec<(u64, bool)> = self.pending.iter().filter(|(_, pending)| pending.deadline <= now).map(|(key, _)| *key).collect();
+
+        for key in overdue {
+            if let Some(pending) = self.pending.remove(&key) {
+                info!("Interval {} only reported by {}/{} threads", key.0, pending.amount_reports, self.amount_threads);
+                self.print(pending.statistic);
+            }
+        }
+    }
+
+    fn print(&mut self, mut statistic: Statistic) {
+        let core_ids = self.pinned_cores.as_ref().map(|core_affinity_manager| core_affinity_manager.lock().unwrap().bound_core_ids());
+        let direction = usize::from(statistic.parameter.mode == UDPerfMode::Sender);
+        (statistic.cores_user_time, statistic.cores_system_time, statistic.cores_irq_time, statistic.cores_softirq_time) = self.system_cpu_util[direction].get_relative_cpu_util(core_ids.as_deref());
+        statistic.cores = SystemCpuUtil::cores(core_ids.as_deref());
+        statistic.set_udp_counters(self.udp_counters[direction].get_relative_counters());
+        if statistic.amount_datagrams != 0 {
+            statistic.print(self.output_format, true);
+        }
+        self.reported.push(statistic);
+    }
+}
@@ -4,22 +4,36 @@ pub mod msghdr_vec;
 pub mod packet_buffer;
 pub mod core_affinity_manager;
 pub mod cpu_util;
+pub mod histogram;
+pub mod interval_reporter;
+pub mod parameter_builder;
+pub mod tx_timestamps;
+pub mod pacer;
+pub mod random;
+pub mod datagram_size;
+pub mod payload;
+pub mod udp_counters;
+pub mod perf_counters;
+pub mod exporter;
 
+use std::collections::HashMap;
 use std::io::IoSlice;
-use log::{debug, trace};
-use serde::Serialize;
+use std::time::{SystemTime, UNIX_EPOCH};
+use log::{debug, info, trace};
+use serde::{Deserialize, Serialize};
 
-use statistic::Statistic;
-use crate::net::MessageHeader;
+use statistic::Measurement;
+use crate::net::{MessageHeader, MessageType};
+use crate::UdperfError;
 
-#[derive(clap::ValueEnum, PartialEq, Default, Debug, Copy, Clone, Serialize)]
+#[derive(clap::ValueEnum, PartialEq, Default, Debug, Copy, Clone, Serialize, Deserialize)]
 pub enum UDPerfMode {
     #[default]
     Receiver,
     Sender,
 }
 
-#[derive(clap::ValueEnum, Default, PartialEq, Debug, Copy, Clone, Serialize)]
+#[derive(clap::ValueEnum, Default, PartialEq, Debug, Copy, Clone, Serialize, Deserialize)]
 pub enum ExchangeFunction {
     Normal,
     #[default]
@@ -27,50 +41,104 @@ pub enum ExchangeFunction {
     Mmsg
 }
 
-#[derive(clap::ValueEnum, Default, PartialEq, Debug, Copy, Clone, Serialize)]
+#[derive(clap::ValueEnum, Default, PartialEq, Debug, Copy, Clone, Serialize, Deserialize)]
 pub enum IOModel {
     #[default]
     Select,
     Poll,
     BusyWaiting,
-    IoUring
+    IoUring,
+    Epoll
 }
 
 
-pub fn process_packet_buffer(buffer: &[u8], datagram_size: usize, next_packet_id: u64, statistic: &mut Statistic) -> u64 {
-    let mut amount_received_packets = 0;
-    for packet in buffer.chunks(datagram_size) {
-        amount_received_packets += process_packet(packet, next_packet_id, statistic);
+// Every datagram is assigned to the measurement of its flow (test ID), since a buffer can contain the datagrams of multiple flows.
+// Without a segment size (GRO), the buffer contains a single datagram of any size.
+pub fn process_packet_buffer(buffer: &[u8], segment_size: Option<usize>, measurements: &mut HashMap<usize, Measurement>, expected_payload: Option<&[u8]>) -> Result<u64, UdperfError> {
+    let receive_timestamp = get_timestamp_ns();
+    let mut result = Ok(0);
+    for packet in buffer.chunks(segment_size.unwrap_or(buffer.len()).max(1)) {
+        result = combine_packet_results(result, process_packet(packet, receive_timestamp, None, measurements, expected_payload));
     }
-    amount_received_packets
+    result
 }
 
-pub fn process_packet(buffer: &[u8], next_packet_id: u64, statistic: &mut Statistic) -> u64 {
-    let packet_id = MessageHeader::get_packet_id(buffer);
-    debug!("Received packet number: {}", packet_id);
-    process_packet_number(packet_id, next_packet_id, statistic)
+// Returns 1 for a MEASUREMENT message. The control messages are returned as InitMessageReceived and LastMessageReceived.
+// With an expected payload, the payload of the MEASUREMENT messages is verified.
+fn process_packet(buffer: &[u8], receive_timestamp: u64, rx_timestamp: Option<u64>, measurements: &mut HashMap<usize, Measurement>, expected_payload: Option<&[u8]>) -> Result<u64, UdperfError> {
+    let test_id = MessageHeader::get_test_id(buffer) as usize;
+    let measurement = Measurement::get(measurements, test_id)?;
+
+    match MessageHeader::get_message_type(buffer) {
+        MessageType::INIT => {
+            info!("{:?}: INIT packet received from test {}!", std::thread::current().id(), test_id);
+            Err(UdperfError::InitMessageReceived)
+        },
+        MessageType::MEASUREMENT => {
+            measurement.start(test_id);
+            let packet_id = MessageHeader::get_packet_id(buffer);
+            debug!("Received packet number: {}", packet_id);
+            // All datagrams of a GRO buffer share the timestamp of the first segment
+            if let Some(rx_timestamp) = rx_timestamp {
+                measurement.rx_latency_histogram.record(receive_timestamp.saturating_sub(rx_timestamp));
+            }
+            process_packet_timestamp(MessageHeader::get_timestamp(buffer), receive_timestamp, measurement);
+            measurement.next_packet_id += process_packet_number(packet_id, measurement);
+            if expected_payload.is_some_and(|expected_payload| !payload::verify_payload(buffer, expected_payload)) {
+                debug!("Received corrupted datagram with packet number {}", packet_id);
+                measurement.amount_corrupted_datagrams += 1;
+            }
+            measurement.amount_datagrams += 1;
+            measurement.amount_data_bytes += buffer.len();
+            Ok(1)
+        },
+        MessageType::LAST => {
+            measurement.finish(test_id);
+            Err(UdperfError::LastMessageReceived)
+        }
+    }
+}
+
+// Combines the results of multiple received datagrams. A LAST message is returned, even if MEASUREMENT messages of other flows were received in the same call.
+pub fn combine_packet_results(first: Result<u64, UdperfError>, second: Result<u64, UdperfError>) -> Result<u64, UdperfError> {
+    match (first, second) {
+        (Ok(first), Ok(second)) => Ok(first + second),
+        (Ok(amount), Err(UdperfError::InitMessageReceived)) | (Err(UdperfError::InitMessageReceived), Ok(amount)) => Ok(amount),
+        (Err(UdperfError::LastMessageReceived), Ok(_) | Err(UdperfError::InitMessageReceived)) | (Ok(_) | Err(UdperfError::InitMessageReceived), Err(UdperfError::LastMessageReceived)) => Err(UdperfError::LastMessageReceived),
+        (Err(x), _) | (_, Err(x)) => Err(x)
+    }
+}
+
+// The one-way delay is only meaningful with synchronized clocks on both hosts. Negative delays due to clock offsets are recorded as 0. The jitter only depends on the difference of the delays, so it isn't affected by a constant clock offset.
+fn process_packet_timestamp(send_timestamp: u64, receive_timestamp: u64, measurement: &mut Measurement) {
+    if send_timestamp == 0 {
+        return;
+    }
+    measurement.owd_histogram.record(receive_timestamp.saturating_sub(send_timestamp));
+    measurement.jitter_state.update(send_timestamp, receive_timestamp);
 }
 
 // Packet reordering taken from iperf3 and rperf https://github.com/opensource-3d-p/rperf/blob/14d382683715594b7dce5ca0b3af67181098698f/src/stream/udp.rs#L225
 // https://github.com/opensource-3d-p/rperf/blob/14d382683715594b7dce5ca0b3af67181098698f/src/stream/udp.rs#L225 
-fn process_packet_number(packet_id: u64, next_packet_id: u64, statistic: &mut Statistic) -> u64 {
+fn process_packet_number(packet_id: u64, measurement: &mut Measurement) -> u64 {
+    let next_packet_id = measurement.next_packet_id;
     match packet_id {
         _ if packet_id == next_packet_id => {
             1
         },
         _ if packet_id > next_packet_id => {
             let lost_packet_count = packet_id - next_packet_id;
-            statistic.amount_omitted_datagrams += lost_packet_count as i64;
+            measurement.amount_omitted_datagrams += lost_packet_count as i64;
             debug!("Reordered or lost packet received! Expected number {}, but received {}. {} packets are currently missing", next_packet_id, packet_id, lost_packet_count);
             lost_packet_count + 1 // This is the next packet id that we expect, since we assume that the missing packets are lost
         },
         _ => { // If the received packet_id is smaller than the expected, it means that we received a reordered (or duplicated) packet.
-            if statistic.amount_omitted_datagrams > 0 { 
-                statistic.amount_omitted_datagrams -= 1;
-                statistic.amount_reordered_datagrams  += 1;
+            if measurement.amount_omitted_datagrams > 0 { 
+                measurement.amount_omitted_datagrams -= 1;
+                measurement.amount_reordered_datagrams  += 1;
                 debug!("Received reordered packet number {}, but expected {}", packet_id, next_packet_id);
             } else { 
-                statistic.amount_duplicated_datagrams += 1;
+                measurement.amount_duplicated_datagrams += 1;
                 debug!("Received duplicated packet: {}", packet_id);
             }
             0
@@ -78,7 +146,17 @@ fn process_packet_number(packet_id: u64, next_packet_id: u64, statistic: &mut St
     }
 }
 
-pub fn get_gso_size_from_cmsg(msghdr: &mut libc::msghdr) -> Option<u32> {
+// Control messages of a received msghdr, which are evaluated by udperf
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ControlMessages {
+    pub gso_size: Option<u32>,
+    pub timestamp: Option<u64>, // Software timestamp of the kernel in nanoseconds (realtime clock). Either the receive timestamp or a transmit timestamp of the error queue.
+    pub tx_timestamp_id: Option<(u32, u32)>, // Type (SCM_TSTAMP_*) and key (SOF_TIMESTAMPING_OPT_ID) of a transmit timestamp
+    pub socket_drops: Option<u32>, // Total amount of datagrams dropped by the socket (SO_RXQ_OVFL), when the datagram was queued
+}
+
+pub fn parse_cmsg(msghdr: &mut libc::msghdr) -> ControlMessages {
+    let mut control_messages = ControlMessages::default();
     let mut cmsg: *mut libc::cmsghdr = unsafe { libc::CMSG_FIRSTHDR(msghdr) };
     while !cmsg.is_null() {
         let level = unsafe { (*cmsg).cmsg_level };
@@ -90,17 +168,43 @@ pub fn get_gso_size_from_cmsg(msghdr: &mut libc::msghdr) -> Option<u32> {
             let data_ptr = unsafe { libc::CMSG_DATA(cmsg) };
             let gso_size = unsafe { *(data_ptr as *const u32) };
             debug!("Received GSO size in cmsg: {}", gso_size);
-            return Some(gso_size);
+            control_messages.gso_size = Some(gso_size);
+        } else if level == libc::SOL_SOCKET && cmsg_type == libc::SCM_TIMESTAMPING {
+            // struct scm_timestamping contains three timestamps: software, deprecated and hardware. Only the software timestamp is requested.
+            let data_ptr = unsafe { libc::CMSG_DATA(cmsg) };
+            let timestamp = unsafe { std::ptr::read_unaligned(data_ptr as *const libc::timespec) };
+            debug!("Received software timestamp in cmsg: {}.{:09}", timestamp.tv_sec, timestamp.tv_nsec);
+            if timestamp.tv_sec != 0 || timestamp.tv_nsec != 0 {
+                control_messages.timestamp = Some(timestamp.tv_sec as u64 * 1_000_000_000 + timestamp.tv_nsec as u64);
+            }
+        } else if level == libc::SOL_SOCKET && cmsg_type == libc::SO_RXQ_OVFL {
+            let data_ptr = unsafe { libc::CMSG_DATA(cmsg) };
+            let socket_drops = unsafe { std::ptr::read_unaligned(data_ptr as *const u32) };
+            debug!("Received drop counter of the socket in cmsg: {}", socket_drops);
+            control_messages.socket_drops = Some(socket_drops);
+        } else if (level == libc::SOL_IP && cmsg_type == libc::IP_RECVERR) || (level == libc::SOL_IPV6 && cmsg_type == libc::IPV6_RECVERR) {
+            // Transmit timestamps of the error queue are identified by the extended error
+            let data_ptr = unsafe { libc::CMSG_DATA(cmsg) };
+            let extended_error = unsafe { std::ptr::read_unaligned(data_ptr as *const libc::sock_extended_err) };
+            if extended_error.ee_errno == libc::ENOMSG as u32 && extended_error.ee_origin == tx_timestamps::SO_EE_ORIGIN_TIMESTAMPING {
+                debug!("Received transmit timestamp of type {} with key {}", extended_error.ee_info, extended_error.ee_data);
+                control_messages.tx_timestamp_id = Some((extended_error.ee_info, extended_error.ee_data));
+            }
         }
 
         cmsg = unsafe { libc::CMSG_NXTHDR(msghdr, cmsg) };
     }
-    None
+    control_messages
 }
 
-pub fn process_packet_msghdr(msghdr: &mut libc::msghdr, amount_received_bytes: usize, mut next_packet_id: u64, statistic: &mut Statistic) -> (u64, u64) {
-    let mut absolut_packets_received = 0;
-    let single_packet_size = match get_gso_size_from_cmsg(msghdr) {
+// The drop counter of the socket is only updated, if the control message is present. Since the kernel counter only increases, the maximum is kept for completions out of order.
+pub fn process_packet_msghdr(msghdr: &mut libc::msghdr, amount_received_bytes: usize, measurements: &mut HashMap<usize, Measurement>, expected_payload: Option<&[u8]>, socket_drops: &mut u32) -> Result<u64, UdperfError> {
+    let receive_timestamp = get_timestamp_ns();
+    let control_messages = parse_cmsg(msghdr);
+    if let Some(drops) = control_messages.socket_drops {
+        *socket_drops = u32::max(*socket_drops, drops);
+    }
+    let single_packet_size = match control_messages.gso_size {
         Some(gso_size) => gso_size,
         None => {
             debug!("No GSO size received in cmsg. Assuming that only one packet was received with size {}", amount_received_bytes);
@@ -120,16 +224,30 @@ pub fn process_packet_msghdr(msghdr: &mut libc::msghdr, amount_received_bytes: u
         IoSlice::new(std::slice::from_raw_parts(iovec.iov_base as *const u8, amount_received_bytes))
     };
 
-    for packet in datagrams.chunks(single_packet_size as usize) {
-        next_packet_id += process_packet(packet, next_packet_id, statistic);
-        absolut_packets_received += 1;
-        trace!("iovec buffer: {:?} with now absolut packets received {} and next packet id: {}", packet, next_packet_id, absolut_packets_received);
+    let mut result = Ok(0);
+    for packet in datagrams.chunks(single_packet_size.max(1) as usize) {
+        result = combine_packet_results(result, process_packet(packet, receive_timestamp, control_messages.timestamp, measurements, expected_payload));
+        trace!("iovec buffer: {:?} with now packets received {:?}", packet, result);
     }
-
-    (next_packet_id, absolut_packets_received)
+    result
 } 
 
 
+// Timestamps in the message header are taken from the realtime clock, so that they can be compared between two hosts with synchronized clocks
+pub fn get_timestamp_ns() -> u64 {
+    match SystemTime::now().duration_since(UNIX_EPOCH) {
+        Ok(n) => n.as_nanos() as u64,
+        Err(_) => panic!("Error getting the current time"),
+    }
+}
+
+// The round-trip time is measured on the same host, so the monotonic clock is used, which isn't affected by steps of the realtime clock (e.g. NTP)
+pub fn get_monotonic_ns() -> u64 {
+    let mut timespec = libc::timespec { tv_sec: 0, tv_nsec: 0 };
+    unsafe { libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut timespec) };
+    timespec.tv_sec as u64 * 1_000_000_000 + timespec.tv_nsec as u64
+}
+
 pub fn get_total_bytes(mmsghdr_vec: &[libc::mmsghdr], amount_msghdr: usize) -> usize {
     let mut amount_bytes = 0;
     for (index, mmsghdr) in mmsghdr_vec.iter().enumerate() {
@@ -2,13 +2,14 @@ use std::mem::MaybeUninit;
 
 use log::debug;
 use crate::net::{MessageHeader, MessageType};
+use crate::UdperfError;
 
 #[allow(non_camel_case_types)]
 pub struct WrapperMsghdr {
     msghdr: libc::msghdr,
     buffer_length: usize,
     with_cmsg: bool,
-    sockaddr: libc::sockaddr_in,
+    sockaddr: libc::sockaddr_storage,
     pub datagram_size: u32,
     pub packets_amount: usize,
 }
@@ -32,7 +33,7 @@ impl WrapperMsghdr {
         let iov = Self::create_iovec(buffer);
 
         let msghdr = Self::create_msghdr(iov);
-        let sockaddr: libc::sockaddr_in = unsafe { MaybeUninit::zeroed().assume_init() };
+        let sockaddr: libc::sockaddr_storage = unsafe { MaybeUninit::zeroed().assume_init() };
 
         Some(WrapperMsghdr {
             msghdr,
@@ -44,21 +45,16 @@ impl WrapperMsghdr {
         })
     }
 
-    // Similar to iperf3's fill_with_repeating_pattern
-    pub fn fill_with_repeating_pattern(&mut self) {
-        let mut counter: u8 = 0;
-        for i in self.get_buffer_pointer().iter_mut() {
-            *i = (48 + counter).to_ascii_lowercase();
-
-            if counter > 9 {
-                counter = 0;
-            } else {
-                counter += 1;
-            }
+    // Every datagram of the buffer starts with the payload, so it doesn't depend on the position of the datagram in the buffer
+    pub fn fill_with_payload(&mut self, payload: &[u8]) {
+        let datagram_size = self.datagram_size as usize;
+        for datagram in self.get_buffer_pointer().chunks_mut(datagram_size) {
+            let length = usize::min(datagram.len(), payload.len());
+            datagram[..length].copy_from_slice(&payload[..length]);
         }
     }
 
-    pub fn add_message_header(&mut self, test_id: u64, packet_id: u64) -> Result<u64, &'static str> {
+    pub fn add_message_header(&mut self, test_id: u64, packet_id: u64) -> Result<u64, UdperfError> {
         let mut amount_used_packet_ids: u64 = 0;
         let mut header = MessageHeader::new(MessageType::MEASUREMENT, test_id, packet_id);
 
@@ -88,10 +84,10 @@ impl WrapperMsghdr {
         msghdr
     }
 
-    pub fn set_address(&mut self, address: libc::sockaddr_in) {
+    pub fn set_address(&mut self, address: libc::sockaddr_storage, address_len: libc::socklen_t) {
         self.sockaddr = address;
         self.msghdr.msg_name = (&mut self.sockaddr) as *mut _ as *mut libc::c_void;
-        self.msghdr.msg_namelen = std::mem::size_of::<libc::sockaddr_in>() as u32;
+        self.msghdr.msg_namelen = address_len;
     }
 
     pub fn add_cmsg_buffer(&mut self) {
@@ -137,6 +133,10 @@ impl WrapperMsghdr {
         self.msghdr
     }
 
+    pub fn buffer_length(&self) -> usize {
+        self.buffer_length
+    }
+
     pub fn get_buffer_pointer(&mut self) -> &mut [u8] {
         let iov_base = unsafe { (*self.msghdr.msg_iov).iov_base as *mut u8 };
         let iov_len = unsafe { (*self.msghdr.msg_iov).iov_len };
@@ -31,15 +31,15 @@ impl MsghdrVec {
         self
     }
 
-    pub fn with_random_payload(mut self) -> MsghdrVec {
+    pub fn with_payload(mut self, payload: &[u8]) -> MsghdrVec {
         for msghdr in self.msghdr_vec.iter_mut() {
-            msghdr.fill_with_repeating_pattern();
+            msghdr.fill_with_payload(payload);
         }
         self
     }
 
-    pub fn with_target_address(mut self, sockaddr: libc::sockaddr_in) -> MsghdrVec {
-        self.msghdr_vec.iter_mut().for_each(|wrapper_msghdr| wrapper_msghdr.set_address(sockaddr));
+    pub fn with_target_address(mut self, sockaddr: libc::sockaddr_storage, sockaddr_len: libc::socklen_t) -> MsghdrVec {
+        self.msghdr_vec.iter_mut().for_each(|wrapper_msghdr| wrapper_msghdr.set_address(sockaddr, sockaddr_len));
         self
     }
 
@@ -48,6 +48,10 @@ impl MsghdrVec {
         self.datagram_size
     }
 
+    pub fn buffer_length(&self) -> usize {
+        self.msghdr_vec.first().map_or(0, |msghdr| msghdr.buffer_length())
+    }
+
     pub fn packets_amount_per_msghdr(&self) -> usize {
         self.packets_amount_per_msghdr
     }
@@ -0,0 +1,287 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+use log::info;
+
+use crate::UdperfError;
+use super::random;
+use super::statistic::{Parameter, TrafficPattern};
+
+const PICOSECONDS_PER_SECOND: u128 = 1_000_000_000_000;
+// Tokens, which weren't used while the threads were not sending, can be used in a burst of at most this duration
+const MAX_BURST: u64 = 1_000_000_000; // picoseconds (1ms)
+// Waits shorter than this are done by spinning, since sleeping is too inaccurate
+const SPIN_THRESHOLD: Duration = Duration::from_micros(100);
+const PICOSECONDS_PER_MILLISECOND: u64 = 1_000_000_000;
+
+// Unit of the tokens in the bucket
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PacingUnit {
+    Bytes,
+    Datagrams,
+}
+
+// Step of the ramp schedule: From start (seconds since the start of the measurement) on, the bandwidth (Mbit/s) is used
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RampStep {
+    pub start: u64,
+    pub bandwidth: u64,
+}
+
+// Token bucket in userspace, which is shared by all sender threads of a direction. It doesn't depend on the fq qdisc like SO_MAX_PACING_RATE.
+// The bucket is implemented as virtual scheduling (GCRA): Every send call reserves the transmission time of its tokens and waits until the reserved time is reached.
+// The traffic pattern changes the reserved time: Poisson scales it with an exponentially distributed factor, on/off moves it out of the pauses and the ramp changes the rate over time.
+pub struct Pacer {
+    // Set with the first send call, so the pattern starts with the measurement and not with the INIT message
+    start: OnceLock<Instant>,
+    // Time in picoseconds since start, at which the next send call is allowed
+    next_send_time: AtomicU64,
+    // 0 sends as fast as possible (only on/off bursts)
+    rate: u64,
+    unit: PacingUnit,
+    // Mean size of the datagrams to convert datagrams per second into bytes
+    datagram_size: f64,
+    pattern: TrafficPattern,
+    // On/off: Length of a burst and of a whole period (burst and pause) in picoseconds
+    burst_length: u64,
+    burst_period: u64,
+    // Ramp: Start in picoseconds and rate in bytes/s of every step
+    ramp_schedule: Vec<(u64, u64)>,
+    random_state: AtomicU64,
+}
+
+impl Pacer {
+    // Returns None, if userspace pacing isn't enabled
+    pub fn new(parameter: &Parameter) -> Option<Pacer> {
+        if !parameter.userspace_pacing {
+            return None;
+        }
+
+        let (rate, unit) = if parameter.pacing_rate_pps > 0 {
+            (parameter.pacing_rate_pps, PacingUnit::Datagrams)
+        } else {
+            (parameter.pacing_rate, PacingUnit::Bytes)
+        };
+        info!("Userspace pacing of all threads with {} {:?}/s and traffic pattern {:?}", rate, unit, parameter.traffic_pattern);
+
+        let seed = super::get_timestamp_ns();
+
+        Some(Pacer {
+            start: OnceLock::new(),
+            next_send_time: AtomicU64::new(0),
+            rate,
+            unit,
+            datagram_size: super::datagram_size::mean_datagram_size(parameter),
+            pattern: parameter.traffic_pattern,
+            burst_length: parameter.burst_length * PICOSECONDS_PER_MILLISECOND,
+            burst_period: (parameter.burst_length + parameter.burst_pause) * PICOSECONDS_PER_MILLISECOND,
+            ramp_schedule: parameter.ramp_schedule.iter().map(|step| (step.start * PICOSECONDS_PER_SECOND as u64, step.bandwidth * 1000 * 1000 / 8)).collect(),
+            random_state: AtomicU64::new(seed),
+        })
+    }
+
+    // Blocks until the datagrams can be sent with the rate of the bucket
+    pub fn acquire(&self, amount_datagrams: u64, amount_bytes: usize) {
+        let start = *self.start.get_or_init(Instant::now);
+        let factor = if self.pattern == TrafficPattern::Poisson { self.exponential_factor(amount_datagrams) } else { 1.0 };
+        let now = self.elapsed();
+
+        let mut current = self.next_send_time.load(Ordering::Relaxed);
+        let send_time = loop {
+            let send_time = self.next_burst_time(u64::max(current, now.saturating_sub(MAX_BURST)));
+            let cost = (self.cost(send_time, amount_datagrams, amount_bytes) as f64 * factor) as u64;
+            match self.next_send_time.compare_exchange_weak(current, send_time + cost, Ordering::Relaxed, Ordering::Relaxed) {
+                Ok(_) => break send_time,
+                Err(x) => current = x
+            }
+        };
+
+        if send_time <= now {
+            return;
+        }
+
+        let deadline = start + Duration::from_nanos(send_time / 1000);
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return;
+            } else if remaining > SPIN_THRESHOLD {
+                std::thread::sleep(remaining - SPIN_THRESHOLD);
+            } else {
+                std::hint::spin_loop();
+            }
+        }
+    }
+
+    // Returns the tokens of datagrams, which couldn't be sent (e.g. EAGAIN)
+    pub fn release(&self, amount_datagrams: u64, amount_bytes: usize) {
+        let _ = self.next_send_time.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |current| {
+            Some(current.saturating_sub(self.cost(current, amount_datagrams, amount_bytes)))
+        });
+    }
+
+    // Bytes, which the traffic pattern offered in total (all threads) between both instants
+    pub fn offered_bytes(&self, from: Instant, to: Instant) -> u64 {
+        let start = match self.start.get() {
+            Some(start) => *start,
+            None => return 0
+        };
+        let from = from.saturating_duration_since(start).as_secs_f64();
+        let to = to.saturating_duration_since(start).as_secs_f64();
+        (self.cumulative_bytes(to) - self.cumulative_bytes(from)).max(0.0) as u64
+    }
+
+    // Bytes, which the traffic pattern offered from the start until the time in seconds
+    fn cumulative_bytes(&self, time: f64) -> f64 {
+        let rate = match self.unit {
+            PacingUnit::Bytes => self.rate as f64,
+            PacingUnit::Datagrams => self.rate as f64 * self.datagram_size,
+        };
+
+        match self.pattern {
+            TrafficPattern::Cbr | TrafficPattern::Poisson => rate * time,
+            TrafficPattern::OnOff => {
+                let burst_length = self.burst_length as f64 / PICOSECONDS_PER_SECOND as f64;
+                let burst_period = self.burst_period as f64 / PICOSECONDS_PER_SECOND as f64;
+                let periods = (time / burst_period).floor();
+                rate * (periods * burst_length + f64::min(time - periods * burst_period, burst_length))
+            },
+            TrafficPattern::Ramp => {
+                let mut bytes = 0.0;
+                for (index, &(step_start, step_rate)) in self.ramp_schedule.iter().enumerate() {
+                    let step_start = step_start as f64 / PICOSECONDS_PER_SECOND as f64;
+                    let step_end = self.ramp_schedule.get(index + 1).map_or(f64::MAX, |&(x, _)| x as f64 / PICOSECONDS_PER_SECOND as f64);
+                    if time > step_start {
+                        bytes += step_rate as f64 * (f64::min(time, step_end) - step_start);
+                    }
+                }
+                bytes
+            }
+        }
+    }
+
+    // On/off: Moves the time out of a pause to the start of the next burst
+    fn next_burst_time(&self, time: u64) -> u64 {
+        if self.pattern != TrafficPattern::OnOff {
+            return time;
+        }
+
+        let time_in_period = time % self.burst_period;
+        if time_in_period < self.burst_length {
+            time
+        } else {
+            time - time_in_period + self.burst_period
+        }
+    }
+
+    // Rate in tokens/s at the time in picoseconds
+    fn rate_at(&self, time: u64) -> u64 {
+        if self.pattern != TrafficPattern::Ramp {
+            return self.rate;
+        }
+
+        // Before the first step, the rate of the first step is used
+        self.ramp_schedule.iter()
+            .take_while(|(step_start, _)| *step_start <= time)
+            .last()
+            .or(self.ramp_schedule.first())
+            .map_or(0, |&(_, rate)| rate)
+    }
+
+    // Transmission time of the tokens in picoseconds
+    fn cost(&self, time: u64, amount_datagrams: u64, amount_bytes: usize) -> u64 {
+        let rate = self.rate_at(time);
+        if rate == 0 {
+            return 0;
+        }
+
+        let tokens = match self.unit {
+            PacingUnit::Bytes => amount_bytes as u128,
+            PacingUnit::Datagrams => amount_datagrams as u128,
+        };
+        (tokens * PICOSECONDS_PER_SECOND / rate as u128) as u64
+    }
+
+    // Poisson: Every datagram waits an exponentially distributed time with mean 1. The factor is the mean of the waiting times of the datagrams.
+    fn exponential_factor(&self, amount_datagrams: u64) -> f64 {
+        if amount_datagrams == 0 {
+            return 1.0;
+        }
+
+        let mut sum = 0.0;
+        for _ in 0..amount_datagrams {
+            sum -= self.next_random().ln();
+        }
+        sum / amount_datagrams as f64
+    }
+
+    // Uniformly distributed in (0, 1]. SplitMix64 only needs an atomic counter as state to be shared by the threads.
+    fn next_random(&self) -> f64 {
+        random::to_f64(random::next_shared_u64(&self.random_state))
+    }
+
+    fn elapsed(&self) -> u64 {
+        self.start.get().map_or(0, |start| start.elapsed().as_nanos() as u64 * 1000)
+    }
+}
+
+// Reads the ramp schedule from a file. Every line contains the start of the step in seconds and the bandwidth in Mbit/s (e.g. "5 200"). Empty lines and lines starting with # are ignored.
+pub fn read_ramp_file(path: &std::path::Path) -> Result<Vec<RampStep>, UdperfError> {
+    let content = std::fs::read_to_string(path).map_err(|x| UdperfError::from_io_error("Unable to read ramp file", &x))?;
+
+    let mut schedule: Vec<RampStep> = Vec::new();
+    for line in content.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+        let values: Vec<&str> = line.split_whitespace().collect();
+        let step = match values[..] {
+            [start, bandwidth] => match (start.parse(), bandwidth.parse()) {
+                (Ok(start), Ok(bandwidth)) => RampStep { start, bandwidth },
+                _ => return Err(UdperfError::InvalidParameter("Ramp file contains a line, which isn't a start in seconds and a bandwidth in Mbit/s!"))
+            },
+            _ => return Err(UdperfError::InvalidParameter("Ramp file contains a line, which isn't a start in seconds and a bandwidth in Mbit/s!"))
+        };
+
+        if step.bandwidth == 0 {
+            return Err(UdperfError::InvalidParameter("Ramp file contains a step with a bandwidth of 0!"));
+        }
+        if schedule.last().is_some_and(|last| last.start >= step.start) {
+            return Err(UdperfError::InvalidParameter("The steps of the ramp file have to be sorted by their start!"));
+        }
+        schedule.push(step);
+    }
+
+    if schedule.is_empty() {
+        return Err(UdperfError::InvalidParameter("Ramp file doesn't contain any step!"));
+    }
+    Ok(schedule)
+}
+
+// The ramp schedule is serialized as a single string (e.g. "0:100;5:200"), since the CSV output can't contain nested lists
+pub mod ramp_schedule {
+    use super::RampStep;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S>(schedule: &[RampStep], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let steps: Vec<String> = schedule.iter().map(|step| format!("{}:{}", step.start, step.bandwidth)).collect();
+        serializer.serialize_str(&steps.join(";"))
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<RampStep>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = String::deserialize(deserializer)?;
+        value.split(';')
+            .filter(|step| !step.is_empty())
+            .map(|step| {
+                let (start, bandwidth) = step.split_once(':').ok_or_else(|| serde::de::Error::custom("Invalid ramp step"))?;
+                Ok(RampStep {
+                    start: start.parse().map_err(serde::de::Error::custom)?,
+                    bandwidth: bandwidth.parse().map_err(serde::de::Error::custom)?,
+                })
+            })
+            .collect()
+    }
+}
@@ -1,12 +1,18 @@
-use log::debug;
+use log::{debug, warn};
 
 use crate::net::MessageHeader;
+use super::datagram_size::DatagramSizes;
 use super::msghdr_vec::MsghdrVec;
+use crate::UdperfError;
 
 pub struct PacketBuffer {
     pub mmsghdr_vec: Vec<libc::mmsghdr>,
     datagram_size: usize, // ASSUMPTION: It's the same for all msghdrs
     packets_amount_per_msghdr: usize, // ASSUMPTION: It's the same for all msghdrs
+    buffer_length: usize, // Length of the buffer of every msghdr, which limits the datagram sizes
+    datagram_sizes: Option<DatagramSizes>,
+    transmit_timestamps: bool, // Transmit timestamp for the one-way delay and jitter on the receiver
+    echo_timestamps: bool, // The send time is echoed back in ping-pong mode
     index_pool: Vec<usize> // When buffers are used for io_uring, we need to know which buffers can be reused. VecDeque (RingBuffer) would be more logical, but is less performant.
 }
 
@@ -16,6 +22,7 @@ impl PacketBuffer {
         let mut mmsghdr_vec = Vec::with_capacity(msghdr_vec.msghdr_vec.len());
         let datagram_size = msghdr_vec.datagram_size();
         let packets_amount_per_msghdr = msghdr_vec.packets_amount_per_msghdr();
+        let buffer_length = msghdr_vec.buffer_length();
 
         for wrapper_msghdr in msghdr_vec.msghdr_vec {
             let msghdr = wrapper_msghdr.move_msghdr();
@@ -30,15 +37,63 @@ impl PacketBuffer {
             index_pool: (0..mmsghdr_vec.len()).collect(),
             mmsghdr_vec,
             datagram_size,
-            packets_amount_per_msghdr
+            packets_amount_per_msghdr,
+            buffer_length,
+            datagram_sizes: None,
+            transmit_timestamps: false,
+            echo_timestamps: false
         }
     }
 
-    pub fn get_buffer_pointer_from_index(&mut self, index: usize) -> Result<&mut [u8], &'static str> {
+    // Stamps the datagrams with the transmit time on the realtime clock, which is compared with the receive time of the receiver
+    pub fn with_transmit_timestamps(mut self, transmit_timestamps: bool) -> PacketBuffer {
+        self.transmit_timestamps = transmit_timestamps;
+        self
+    }
+
+    // Stamps the datagrams with the monotonic send time, which is used for the round-trip time in ping-pong mode
+    pub fn with_echo_timestamps(mut self, echo_timestamps: bool) -> PacketBuffer {
+        self.echo_timestamps = echo_timestamps;
+        self
+    }
+
+    fn transmit_timestamp(&self) -> u64 {
+        if self.transmit_timestamps { super::get_timestamp_ns() } else { 0 }
+    }
+
+    fn send_time(&self) -> u64 {
+        if self.echo_timestamps { super::get_monotonic_ns() } else { 0 }
+    }
+
+    // The datagram sizes are drawn from the distribution, if every msghdr carries a single datagram
+    pub fn with_datagram_sizes(mut self, datagram_sizes: Option<DatagramSizes>) -> PacketBuffer {
+        if datagram_sizes.is_some() && self.packets_amount_per_msghdr != 1 {
+            warn!("Datagram size distributions require a single datagram per msghdr, but every msghdr carries {} datagrams! Using fixed datagram sizes", self.packets_amount_per_msghdr);
+            return self;
+        }
+        self.datagram_sizes = datagram_sizes;
+        self
+    }
+
+    // Draws the sizes of the datagrams of the next batch. Without a distribution, the datagram sizes stay fixed.
+    pub fn sample_datagram_sizes(&mut self, first_index: usize, amount_msghdr: usize) {
+        if let Some(datagram_sizes) = self.datagram_sizes.as_mut() {
+            for mmsghdr in self.mmsghdr_vec.iter_mut().skip(first_index).take(amount_msghdr) {
+                unsafe { (*mmsghdr.msg_hdr.msg_iov).iov_len = usize::min(datagram_sizes.next_size(), self.buffer_length) };
+            }
+        }
+    }
+
+    // Bytes of the datagrams of the msghdrs
+    pub fn amount_bytes(&self, first_index: usize, amount_msghdr: usize) -> usize {
+        self.mmsghdr_vec.iter().skip(first_index).take(amount_msghdr).map(|mmsghdr| unsafe { (*mmsghdr.msg_hdr.msg_iov).iov_len }).sum()
+    }
+
+    pub fn get_buffer_pointer_from_index(&mut self, index: usize) -> Result<&mut [u8], UdperfError> {
         if let Some(mmsghdr) = self.mmsghdr_vec.get_mut(index) {
             Ok(Self::get_buffer_pointer_from_mmsghdr(mmsghdr))
         } else {
-            Err("Getting buffer pointer of msghdr is out of bounds!")
+            Err(UdperfError::Other("Getting buffer pointer of msghdr is out of bounds!"))
         }
     }
 
@@ -48,11 +103,11 @@ impl PacketBuffer {
         unsafe { std::slice::from_raw_parts_mut(iov_base, iov_len) }
     }
 
-    pub fn get_msghdr_from_index(&mut self, index: usize) -> Result<&mut libc::msghdr, &'static str> {
+    pub fn get_msghdr_from_index(&mut self, index: usize) -> Result<&mut libc::msghdr, UdperfError> {
         if let Some(mmsghdr) = self.mmsghdr_vec.get_mut(index) {
             Ok(&mut mmsghdr.msg_hdr)
         } else {
-            Err("Getting msghdr is out of bounds!")
+            Err(UdperfError::Other("Getting msghdr is out of bounds!"))
         }
     }
 
@@ -65,9 +120,12 @@ impl PacketBuffer {
         });
     }
 
-    pub fn add_packet_ids(&mut self, packet_id: u64, amount_packets: Option<usize>) -> Result<u64, &'static str> {
+    // Adds the packet IDs and the transmit timestamp to the message headers of the datagrams
+    pub fn add_packet_ids(&mut self, packet_id: u64, amount_packets: Option<usize>) -> Result<u64, UdperfError> {
         let mut amount_used_packet_ids: u64 = 0;
         let mmsghdr_vec_len = self.mmsghdr_vec.len();
+        let timestamp = self.transmit_timestamp();
+        let send_time = self.send_time();
 
         // Iterate over all mmsghdr structs (or up to amount_packets if specified)
         for mmsghdr in self.mmsghdr_vec.iter_mut().take(amount_packets.unwrap_or(mmsghdr_vec_len)) {
@@ -76,6 +134,8 @@ impl PacketBuffer {
             for i in 0..self.packets_amount_per_msghdr {
                 let start_of_packet = i * self.datagram_size;
                 MessageHeader::set_packet_id_raw(&mut msghdr_buffer[start_of_packet..], packet_id + amount_used_packet_ids);
+                MessageHeader::set_timestamp_raw(&mut msghdr_buffer[start_of_packet..], timestamp);
+                MessageHeader::set_send_time_raw(&mut msghdr_buffer[start_of_packet..], send_time);
                 amount_used_packet_ids += 1;
             }
         }
@@ -85,15 +145,52 @@ impl PacketBuffer {
         Ok(amount_used_packet_ids)
     }
 
-    pub fn add_packet_ids_to_msghdr(&mut self, packet_id: u64, index: usize) -> Result<u64, &'static str> {
+    // Every flow has its own test ID (first_test_id + flow index) and sequence of packet IDs. If the flows are interleaved, every msghdr carries the datagrams of the next flow, otherwise all msghdrs belong to first_flow.
+    pub fn add_flow_packet_ids(&mut self, first_test_id: u64, next_packet_ids: &mut [u64], first_flow: usize, interleaved: bool) -> u64 {
+        let mut amount_used_packet_ids: u64 = 0;
+        let timestamp = self.transmit_timestamp();
+        let send_time = self.send_time();
+
+        for (index, mmsghdr) in self.mmsghdr_vec.iter_mut().enumerate() {
+            let flow = if interleaved { (first_flow + index) % next_packet_ids.len() } else { first_flow };
+            let msghdr_buffer = Self::get_buffer_pointer_from_mmsghdr(mmsghdr);
+
+            for i in 0..self.packets_amount_per_msghdr {
+                let start_of_packet = i * self.datagram_size;
+                MessageHeader::set_test_id_raw(&mut msghdr_buffer[start_of_packet..], first_test_id + flow as u64);
+                MessageHeader::set_packet_id_raw(&mut msghdr_buffer[start_of_packet..], next_packet_ids[flow]);
+                MessageHeader::set_timestamp_raw(&mut msghdr_buffer[start_of_packet..], timestamp);
+                MessageHeader::set_send_time_raw(&mut msghdr_buffer[start_of_packet..], send_time);
+                next_packet_ids[flow] += 1;
+                amount_used_packet_ids += 1;
+            }
+        }
+
+        debug!("Added packet IDs of {} flows to buffer! Used packet IDs: {}", next_packet_ids.len(), amount_used_packet_ids);
+        amount_used_packet_ids
+    }
+
+    // Returns the packet IDs of the msghdrs, which weren't sent, to their flows. Only the last msghdrs of a call can fail, so these are the highest packet IDs of every flow.
+    pub fn reset_flow_packet_ids(&self, next_packet_ids: &mut [u64], first_flow: usize, interleaved: bool, amount_sent_msghdr: usize) {
+        for index in amount_sent_msghdr..self.mmsghdr_vec.len() {
+            let flow = if interleaved { (first_flow + index) % next_packet_ids.len() } else { first_flow };
+            next_packet_ids[flow] -= self.packets_amount_per_msghdr as u64;
+        }
+    }
+
+    pub fn add_packet_ids_to_msghdr(&mut self, packet_id: u64, index: usize) -> Result<u64, UdperfError> {
         let mut amount_used_packet_ids: u64 = 0;
         let datagram_size = self.datagram_size;
         let packets_amount_per_msghdr = self.packets_amount_per_msghdr;
+        let timestamp = self.transmit_timestamp();
+        let send_time = self.send_time();
         let msghdr_buffer = self.get_buffer_pointer_from_index(index)?;
 
         for i in 0..packets_amount_per_msghdr {
             let start_of_packet = i * datagram_size;
             MessageHeader::set_packet_id_raw(&mut msghdr_buffer[start_of_packet..], packet_id + amount_used_packet_ids);
+            MessageHeader::set_timestamp_raw(&mut msghdr_buffer[start_of_packet..], timestamp);
+            MessageHeader::set_send_time_raw(&mut msghdr_buffer[start_of_packet..], send_time);
             amount_used_packet_ids += 1;
         }
 
@@ -109,10 +206,10 @@ impl PacketBuffer {
         self.datagram_size
     }
 
-    pub fn get_buffer_index(&mut self) -> Result<usize, &'static str> {
+    pub fn get_buffer_index(&mut self) -> Result<usize, UdperfError> {
         match self.index_pool.pop() {
             Some(index) => Ok(index),
-            None => Err("No buffers left in packet_buffer")
+            None => Err(UdperfError::Other("No buffers left in packet_buffer"))
         }
     }
 
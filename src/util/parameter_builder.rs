@@ -0,0 +1,951 @@
+use std::{net::IpAddr, path};
+use log::{error, info, warn};
+
+use crate::io_uring::{UringMode, UringSqFillingMode, UringTaskWork};
+use crate::net::{self, socket_options::SocketOptions};
+use super::datagram_size::{self, WeightedSize};
+use super::pacer;
+use super::statistic::{FlowMode, MultiplexPort, OutputFormat, Parameter, SimulateConnection, TrafficPattern, DatagramSizeDistribution, PayloadMode, UringParameter};
+use super::{ExchangeFunction, IOModel, UDPerfMode};
+use crate::UdperfError;
+
+// Builder for the socket options, which can be set by the user. The options depending on the other parameters (IPv6, GSO/GRO, SO_REUSEPORT and pacing rate) are set by the ParameterBuilder.
+#[derive(Debug, Clone, Copy)]
+pub struct SocketOptionsBuilder {
+    nonblocking: bool,
+    ip_fragmentation: bool,
+    recv_buffer_size: Option<u32>,
+    send_buffer_size: Option<u32>,
+    hop_limit: Option<u32>,
+    flow_label: Option<u32>,
+    rx_timestamps: bool,
+    tx_timestamps: bool,
+    rxq_overflow: bool,
+}
+
+impl Default for SocketOptionsBuilder {
+    fn default() -> Self {
+        SocketOptionsBuilder {
+            nonblocking: true,
+            ip_fragmentation: false,
+            recv_buffer_size: None,
+            send_buffer_size: None,
+            hop_limit: None,
+            flow_label: None,
+            rx_timestamps: false,
+            tx_timestamps: false,
+            rxq_overflow: false,
+        }
+    }
+}
+
+impl SocketOptionsBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn nonblocking(mut self, nonblocking: bool) -> Self {
+        self.nonblocking = nonblocking;
+        self
+    }
+
+    pub fn ip_fragmentation(mut self, ip_fragmentation: bool) -> Self {
+        self.ip_fragmentation = ip_fragmentation;
+        self
+    }
+
+    // Sets SO_RCVBUF and SO_SNDBUF in bytes. The kernel doubles the value to account for overhead.
+    pub fn socket_buffer_size(mut self, size: u32) -> Self {
+        self.recv_buffer_size = Some(size);
+        self.send_buffer_size = Some(size);
+        self
+    }
+
+    pub fn hop_limit(mut self, hop_limit: u32) -> Self {
+        self.hop_limit = Some(hop_limit);
+        self
+    }
+
+    pub fn flow_label(mut self, flow_label: u32) -> Self {
+        self.flow_label = Some(flow_label);
+        self
+    }
+
+    // Enables kernel software receive timestamps (SO_TIMESTAMPING) to measure the latency until the datagram is processed by udperf
+    pub fn rx_timestamps(mut self, rx_timestamps: bool) -> Self {
+        self.rx_timestamps = rx_timestamps;
+        self
+    }
+
+    // Enables kernel software transmit timestamps (SO_TIMESTAMPING) to measure the delay of the qdisc and the driver
+    pub fn tx_timestamps(mut self, tx_timestamps: bool) -> Self {
+        self.tx_timestamps = tx_timestamps;
+        self
+    }
+
+    // Enables the drop counter of the receive queue (SO_RXQ_OVFL) and samples the socket memory with sock_diag on the receiver
+    pub fn rxq_overflow(mut self, rxq_overflow: bool) -> Self {
+        self.rxq_overflow = rxq_overflow;
+        self
+    }
+
+    fn build(self, ipv6: bool, reuseport: bool, gso: Option<u32>, gro: bool, socket_pacing_rate: u64) -> SocketOptions {
+        SocketOptions::new(
+            self.nonblocking,
+            self.ip_fragmentation,
+            reuseport,
+            gso,
+            gro,
+            socket_pacing_rate,
+            self.recv_buffer_size,
+            self.send_buffer_size,
+            ipv6,
+            self.hop_limit,
+            self.flow_label,
+            self.rx_timestamps,
+            self.tx_timestamps,
+            self.rxq_overflow
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct UringParameterBuilder {
+    uring_mode: UringMode,
+    ring_size: u32,
+    burst_size: Option<u32>,
+    sqpoll: bool,
+    sqpoll_shared: bool,
+    sq_filling_mode: UringSqFillingMode,
+    task_work: UringTaskWork,
+    record_utilization: bool,
+}
+
+impl Default for UringParameterBuilder {
+    fn default() -> Self {
+        UringParameterBuilder {
+            uring_mode: UringMode::default(),
+            ring_size: crate::DEFAULT_URING_RING_SIZE,
+            burst_size: None,
+            sqpoll: false,
+            sqpoll_shared: false,
+            sq_filling_mode: UringSqFillingMode::default(),
+            task_work: UringTaskWork::default(),
+            record_utilization: false,
+        }
+    }
+}
+
+impl UringParameterBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn uring_mode(mut self, uring_mode: UringMode) -> Self {
+        self.uring_mode = uring_mode;
+        self
+    }
+
+    pub fn ring_size(mut self, ring_size: u32) -> Self {
+        self.ring_size = ring_size;
+        self
+    }
+
+    // If not set, the burst size is a fraction of the ring size
+    pub fn burst_size(mut self, burst_size: u32) -> Self {
+        self.burst_size = Some(burst_size);
+        self
+    }
+
+    pub fn sqpoll(mut self, sqpoll: bool) -> Self {
+        self.sqpoll = sqpoll;
+        self
+    }
+
+    pub fn sqpoll_shared(mut self, sqpoll_shared: bool) -> Self {
+        self.sqpoll_shared = sqpoll_shared;
+        self
+    }
+
+    pub fn sq_filling_mode(mut self, sq_filling_mode: UringSqFillingMode) -> Self {
+        self.sq_filling_mode = sq_filling_mode;
+        self
+    }
+
+    pub fn task_work(mut self, task_work: UringTaskWork) -> Self {
+        self.task_work = task_work;
+        self
+    }
+
+    pub fn record_utilization(mut self, record_utilization: bool) -> Self {
+        self.record_utilization = record_utilization;
+        self
+    }
+
+    pub fn build(self) -> Result<UringParameter, UdperfError> {
+        if !self.ring_size.is_power_of_two() {
+            return Err(UdperfError::InvalidParameter("Uring ring size must be a power of 2!"));
+        }
+
+        if self.ring_size > crate::URING_MAX_RING_SIZE {
+            error!("Uring ring size is too big! Maximum is {}", crate::URING_MAX_RING_SIZE);
+            return Err(UdperfError::InvalidParameter("Uring ring size is too big!"));
+        }
+
+        let burst_size = self.burst_size.unwrap_or((self.ring_size as f32 / crate::URING_BURST_SIZE_DIVIDEND as f32).ceil() as u32);
+        if burst_size > self.ring_size {
+            error!("Uring burst size {} must be smaller than the ring size {}!", burst_size, self.ring_size);
+            return Err(UdperfError::InvalidParameter("Uring burst size must be smaller than the ring size!"));
+        }
+
+        let mut uring_parameter = UringParameter {
+            uring_mode: self.uring_mode,
+            ring_size: self.ring_size,
+            burst_size,
+            buffer_size: self.ring_size * crate::URING_BUFFER_SIZE_MULTIPLICATOR,
+            sqpoll: self.sqpoll,
+            sqpoll_shared: self.sqpoll_shared,
+            sq_filling_mode: self.sq_filling_mode,
+            task_work: self.task_work,
+            record_utilization: self.record_utilization
+        };
+
+        if uring_parameter.sqpoll_shared && !uring_parameter.sqpoll {
+            warn!("Uring sqpoll_shared can't be used without sqpoll!");
+            warn!("Setting sqpoll to true!");
+            uring_parameter.sqpoll = true;
+        }
+
+        if uring_parameter.sqpoll && uring_parameter.task_work != UringTaskWork::Default {
+            warn!("Neither DEFER nor COOP can be used with SQ_POLL! Setting task_work to Default!");
+            uring_parameter.task_work = UringTaskWork::Default;
+        }
+
+        Ok(uring_parameter)
+    }
+}
+
+// Builds the parameters of a measurement. Values, which are derived from other parameters (e.g. the MSS with GSO/GRO), are calculated in build().
+#[derive(Debug, Clone)]
+pub struct ParameterBuilder {
+    mode: UDPerfMode,
+    ip: IpAddr,
+    port: u16,
+    sender_port: u16,
+    amount_threads: u16,
+    sockets_per_thread: u16,
+    flows_per_thread: u16,
+    flow_mode: FlowMode,
+    output_interval: f64,
+    output_format: OutputFormat,
+    output_file_path: Option<path::PathBuf>,
+    thread_statistics: bool,
+    perf_counters: bool,
+    test_name: String,
+    run_name: String,
+    repetition_id: u16,
+    io_model: IOModel,
+    epoll_edge_triggered: bool,
+    epoll_exclusive: bool,
+    test_runtime_length: u64,
+    mss: u32,
+    datagram_size: u32,
+    datagram_size_distribution: DatagramSizeDistribution,
+    datagram_size_min: u32,
+    datagram_size_file_path: Option<path::PathBuf>,
+    payload_mode: PayloadMode,
+    payload_seed: u64,
+    payload_file_path: Option<path::PathBuf>,
+    payload_verification: bool,
+    gsro: bool,
+    gso_buffer_size: u32,
+    bandwidth: u64,
+    bandwidth_pps: u64,
+    userspace_pacing: bool,
+    traffic_pattern: TrafficPattern,
+    burst_length: u64,
+    burst_pause: u64,
+    ramp_file_path: Option<path::PathBuf>,
+    exchange_function: ExchangeFunction,
+    mmsg_amount: usize,
+    multiplex_port: MultiplexPort,
+    multiplex_port_receiver: MultiplexPort,
+    simulate_connection: Option<SimulateConnection>,
+    core_affinity: bool,
+    numa_affinity: bool,
+    control_channel: bool,
+    reverse: bool,
+    bidirectional: bool,
+    ping_pong: bool,
+    one_way_delay: bool,
+    socket_options: SocketOptionsBuilder,
+    uring_parameter: UringParameterBuilder,
+}
+
+impl ParameterBuilder {
+    pub fn new(mode: UDPerfMode) -> Self {
+        ParameterBuilder {
+            mode,
+            ip: IpAddr::from([0, 0, 0, 0]),
+            port: crate::DEFAULT_RECEIVER_PORT,
+            sender_port: crate::DEFAULT_SENDER_PORT,
+            amount_threads: 1,
+            sockets_per_thread: 1,
+            flows_per_thread: 1,
+            flow_mode: FlowMode::default(),
+            output_interval: crate::DEFAULT_INTERVAL,
+            output_format: OutputFormat::default(),
+            output_file_path: None,
+            thread_statistics: false,
+            perf_counters: false,
+            test_name: String::from("udperf-test"),
+            run_name: String::from("run-udperf"),
+            repetition_id: 1,
+            io_model: IOModel::default(),
+            epoll_edge_triggered: false,
+            epoll_exclusive: false,
+            test_runtime_length: crate::DEFAULT_DURATION,
+            mss: crate::DEFAULT_MSS,
+            datagram_size: crate::DEFAULT_UDP_DATAGRAM_SIZE,
+            datagram_size_distribution: DatagramSizeDistribution::default(),
+            datagram_size_min: crate::DEFAULT_UDP_DATAGRAM_SIZE_MIN,
+            datagram_size_file_path: None,
+            payload_mode: PayloadMode::default(),
+            payload_seed: crate::DEFAULT_PAYLOAD_SEED,
+            payload_file_path: None,
+            payload_verification: false,
+            gsro: false,
+            gso_buffer_size: crate::DEFAULT_GSO_BUFFER_SIZE,
+            bandwidth: crate::DEFAULT_BANDWIDTH,
+            bandwidth_pps: 0,
+            userspace_pacing: false,
+            traffic_pattern: TrafficPattern::default(),
+            burst_length: crate::DEFAULT_BURST_LENGTH,
+            burst_pause: crate::DEFAULT_BURST_PAUSE,
+            ramp_file_path: None,
+            exchange_function: ExchangeFunction::default(),
+            mmsg_amount: crate::DEFAULT_AMOUNT_MSG_WHEN_SENDMMSG,
+            multiplex_port: MultiplexPort::default(),
+            multiplex_port_receiver: MultiplexPort::default(),
+            simulate_connection: None,
+            core_affinity: false,
+            numa_affinity: false,
+            control_channel: false,
+            reverse: false,
+            bidirectional: false,
+            ping_pong: false,
+            one_way_delay: false,
+            socket_options: SocketOptionsBuilder::default(),
+            uring_parameter: UringParameterBuilder::default(),
+        }
+    }
+
+    pub fn ip(mut self, ip: IpAddr) -> Self {
+        self.ip = ip;
+        self
+    }
+
+    // Port the receiver listens on. The control channel uses the same port number.
+    pub fn port(mut self, port: u16) -> Self {
+        self.port = port;
+        self
+    }
+
+    // Port the sender binds to, which is only used with sender port sharing/sharding
+    pub fn sender_port(mut self, sender_port: u16) -> Self {
+        self.sender_port = sender_port;
+        self
+    }
+
+    pub fn parallel(mut self, amount_threads: u16) -> Self {
+        self.amount_threads = amount_threads;
+        self
+    }
+
+    // Every socket of a thread uses its own port. Multiple sockets per thread are only driven by the epoll IO model.
+    pub fn sockets_per_thread(mut self, sockets_per_thread: u16) -> Self {
+        self.sockets_per_thread = sockets_per_thread;
+        self
+    }
+
+    // Every flow of a thread has its own test ID. The flows are either distinguished by the test ID only or use their own socket (source port).
+    pub fn flows(mut self, flows_per_thread: u16, flow_mode: FlowMode) -> Self {
+        self.flows_per_thread = flows_per_thread;
+        self.flow_mode = flow_mode;
+        self
+    }
+
+    pub fn interval(mut self, output_interval: f64) -> Self {
+        self.output_interval = output_interval;
+        self
+    }
+
+    pub fn time(mut self, test_runtime_length: u64) -> Self {
+        self.test_runtime_length = test_runtime_length;
+        self
+    }
+
+    pub fn output_format(mut self, output_format: OutputFormat) -> Self {
+        self.output_format = output_format;
+        self
+    }
+
+    // Setting an output file switches the output format to file, unless the Prometheus exposition is written to it
+    pub fn output_file_path(mut self, output_file_path: path::PathBuf) -> Self {
+        self.output_file_path = Some(output_file_path);
+        self
+    }
+
+    pub fn thread_statistics(mut self, thread_statistics: bool) -> Self {
+        self.thread_statistics = thread_statistics;
+        self
+    }
+
+    // Measures every thread with the hardware (or software) performance counters of perf_event_open
+    pub fn perf_counters(mut self, perf_counters: bool) -> Self {
+        self.perf_counters = perf_counters;
+        self
+    }
+
+    pub fn labels(mut self, test_name: &str, run_name: &str, repetition_id: u16) -> Self {
+        self.test_name = test_name.to_string();
+        self.run_name = run_name.to_string();
+        self.repetition_id = repetition_id;
+        self
+    }
+
+    pub fn io_model(mut self, io_model: IOModel) -> Self {
+        self.io_model = io_model;
+        self
+    }
+
+    // Registers the sockets edge-triggered and/or with EPOLLEXCLUSIVE, if the epoll IO model is used
+    pub fn epoll(mut self, edge_triggered: bool, exclusive: bool) -> Self {
+        self.epoll_edge_triggered = edge_triggered;
+        self.epoll_exclusive = exclusive;
+        self
+    }
+
+    pub fn mss(mut self, mss: u32) -> Self {
+        self.mss = mss;
+        self
+    }
+
+    pub fn datagram_size(mut self, datagram_size: u32) -> Self {
+        self.datagram_size = datagram_size;
+        self
+    }
+
+    // Distribution of the sizes of the sent datagrams. The minimum size is only used by the uniform distribution, whose maximum is the datagram size.
+    pub fn datagram_size_distribution(mut self, datagram_size_distribution: DatagramSizeDistribution, datagram_size_min: u32) -> Self {
+        self.datagram_size_distribution = datagram_size_distribution;
+        self.datagram_size_min = datagram_size_min;
+        self
+    }
+
+    pub fn datagram_size_file_path(mut self, datagram_size_file_path: path::PathBuf) -> Self {
+        self.datagram_size_file_path = Some(datagram_size_file_path);
+        self
+    }
+
+    // The seed is only used by the random payload mode
+    pub fn payload(mut self, payload_mode: PayloadMode, payload_seed: u64) -> Self {
+        self.payload_mode = payload_mode;
+        self.payload_seed = payload_seed;
+        self
+    }
+
+    pub fn payload_file_path(mut self, payload_file_path: path::PathBuf) -> Self {
+        self.payload_file_path = Some(payload_file_path);
+        self
+    }
+
+    // The receiving side verifies the payload of every datagram. Sender and receiver have to use the same payload mode, which is taken over with the control channel.
+    pub fn payload_verification(mut self, payload_verification: bool) -> Self {
+        self.payload_verification = payload_verification;
+        self
+    }
+
+    // Enables GSO on the sender and GRO on the receiver. The GSO buffer size is used as MSS.
+    pub fn gsro(mut self, gsro: bool, gso_buffer_size: u32) -> Self {
+        self.gsro = gsro;
+        self.gso_buffer_size = gso_buffer_size;
+        self
+    }
+
+    // Bandwidth in total (not per thread) in Mbit/s, which is enforced by kernel pacing
+    pub fn bandwidth(mut self, bandwidth: u64) -> Self {
+        self.bandwidth = bandwidth;
+        self
+    }
+
+    // Datagrams per second in total (not per thread), which are enforced by the userspace pacer
+    pub fn bandwidth_pps(mut self, bandwidth_pps: u64) -> Self {
+        self.bandwidth_pps = bandwidth_pps;
+        self
+    }
+
+    // Enforces the bandwidth with a token bucket in udperf, which is shared by all threads, instead of kernel pacing. Doesn't require the fq qdisc.
+    pub fn userspace_pacing(mut self, userspace_pacing: bool) -> Self {
+        self.userspace_pacing = userspace_pacing;
+        self
+    }
+
+    // Arrival process of the datagrams. The burst length and pause in milliseconds are only used by on/off.
+    pub fn traffic_pattern(mut self, traffic_pattern: TrafficPattern, burst_length: u64, burst_pause: u64) -> Self {
+        self.traffic_pattern = traffic_pattern;
+        self.burst_length = burst_length;
+        self.burst_pause = burst_pause;
+        self
+    }
+
+    pub fn ramp_file_path(mut self, ramp_file_path: path::PathBuf) -> Self {
+        self.ramp_file_path = Some(ramp_file_path);
+        self
+    }
+
+    pub fn exchange_function(mut self, exchange_function: ExchangeFunction) -> Self {
+        self.exchange_function = exchange_function;
+        self
+    }
+
+    pub fn mmsg_amount(mut self, mmsg_amount: usize) -> Self {
+        self.mmsg_amount = mmsg_amount;
+        self
+    }
+
+    pub fn multiplex_port(mut self, multiplex_port: MultiplexPort, multiplex_port_receiver: MultiplexPort) -> Self {
+        self.multiplex_port = multiplex_port;
+        self.multiplex_port_receiver = multiplex_port_receiver;
+        self
+    }
+
+    // If not set, all threads simulate a single connection (same test IDs), if the receiver threads share a port
+    pub fn simulate_connection(mut self, simulate_connection: SimulateConnection) -> Self {
+        self.simulate_connection = Some(simulate_connection);
+        self
+    }
+
+    pub fn core_affinity(mut self, core_affinity: bool) -> Self {
+        self.core_affinity = core_affinity;
+        self
+    }
+
+    pub fn numa_affinity(mut self, numa_affinity: bool) -> Self {
+        self.numa_affinity = numa_affinity;
+        self
+    }
+
+    pub fn control_channel(mut self, control_channel: bool) -> Self {
+        self.control_channel = control_channel;
+        self
+    }
+
+    // Reverse and bidirectional mode enable the control channel
+    pub fn reverse(mut self, reverse: bool) -> Self {
+        self.reverse = reverse;
+        self
+    }
+
+    pub fn bidirectional(mut self, bidirectional: bool) -> Self {
+        self.bidirectional = bidirectional;
+        self
+    }
+
+    pub fn ping_pong(mut self, ping_pong: bool) -> Self {
+        self.ping_pong = ping_pong;
+        self
+    }
+
+    pub fn one_way_delay(mut self, one_way_delay: bool) -> Self {
+        self.one_way_delay = one_way_delay;
+        self
+    }
+
+    pub fn socket_options(mut self, socket_options: SocketOptionsBuilder) -> Self {
+        self.socket_options = socket_options;
+        self
+    }
+
+    pub fn uring_parameter(mut self, uring_parameter: UringParameterBuilder) -> Self {
+        self.uring_parameter = uring_parameter;
+        self
+    }
+
+    pub fn build(mut self) -> Result<Parameter, UdperfError> {
+        if self.datagram_size > crate::MAX_UDP_DATAGRAM_SIZE {
+            error!("UDP datagram size is too big! Maximum is {}", crate::MAX_UDP_DATAGRAM_SIZE);
+            return Err(UdperfError::InvalidParameter("UDP datagram size is too big!"));
+        }
+
+        if self.payload_mode == PayloadMode::File && self.payload_file_path.is_none() {
+            return Err(UdperfError::InvalidParameter("Payload mode file requires a payload file!"));
+        }
+
+        // With a size distribution, the datagram size is the maximum size of the distribution
+        let datagram_sizes = self.build_datagram_sizes()?;
+        if let Some(max_size) = datagram_sizes.iter().map(|x| x.size).max() {
+            self.datagram_size = max_size;
+        }
+
+        if self.output_interval > 0.0 && (self.output_interval * (self.test_runtime_length as f64 / self.output_interval).round() - self.test_runtime_length as f64).abs() > 1e-9  {
+            return Err(UdperfError::InvalidParameter("Interval doesn't fit perfect in the time!"));
+        }
+
+        if self.output_interval > 0.0 && self.test_runtime_length == 0 && self.mode == UDPerfMode::Receiver {
+            return Err(UdperfError::InvalidParameter("Interval is set but time is 0! Time must be set when interval output is enabled!"));
+        }
+
+        if Self::has_more_than_one_decimal(self.output_interval) {
+            return Err(UdperfError::InvalidParameter("Interval has more than one decimal place! Only tenth of a second is allowed!"));
+        }
+
+        if self.io_model == IOModel::IoUring && self.uring_parameter.uring_mode == UringMode::Zerocopy && self.mode != UDPerfMode::Sender {
+            warn!("Zero copy is only available with io_uring on the sender!");
+            return Err(UdperfError::InvalidParameter("Zero copy is only available with io_uring on the sender!"));
+        }
+
+        if self.mode == UDPerfMode::Sender && self.bandwidth as u128 / 8 / 1000 / 1000 >= u64::MAX.into() {
+            error!("Socket pacing rate is too big! Maximum is {} Mbit/s", u64::MAX / 1000 / 1000 * 8);
+            return Err(UdperfError::InvalidParameter("Socket pacing rate is too big!"));
+        }
+
+        // The kernel can't pace datagrams per second or generate traffic patterns
+        let userspace_pacing = self.uses_userspace_pacing() && self.mode == UDPerfMode::Sender;
+        if userspace_pacing {
+            if self.bandwidth > 0 && self.bandwidth_pps > 0 {
+                return Err(UdperfError::InvalidParameter("The bandwidth can either be set in Mbit/s or in datagrams per second!"));
+            }
+
+            if self.bandwidth == 0 && self.bandwidth_pps == 0 && self.traffic_pattern == TrafficPattern::Cbr {
+                warn!("Userspace pacing is enabled, but no bandwidth is set! Parameter is ignored");
+            }
+
+            if self.ping_pong {
+                return Err(UdperfError::InvalidParameter("Userspace pacing can't be used together with ping-pong mode!"));
+            }
+        }
+
+        let ramp_schedule = match self.traffic_pattern {
+            _ if self.mode == UDPerfMode::Receiver => Vec::new(),
+            TrafficPattern::Poisson if self.bandwidth == 0 && self.bandwidth_pps == 0 => {
+                return Err(UdperfError::InvalidParameter("Poisson traffic pattern requires a bandwidth as mean rate!"));
+            },
+            TrafficPattern::OnOff if self.burst_length == 0 => {
+                return Err(UdperfError::InvalidParameter("On/off traffic pattern requires a burst length greater than 0!"));
+            },
+            TrafficPattern::Ramp => {
+                if self.bandwidth > 0 || self.bandwidth_pps > 0 {
+                    return Err(UdperfError::InvalidParameter("Ramp traffic pattern takes the bandwidth from the ramp file!"));
+                }
+                match &self.ramp_file_path {
+                    Some(path) => pacer::read_ramp_file(path)?,
+                    None => return Err(UdperfError::InvalidParameter("Ramp traffic pattern requires a ramp file!"))
+                }
+            },
+            _ => {
+                if self.ramp_file_path.is_some() {
+                    warn!("Ramp file is only used with the ramp traffic pattern! Parameter is ignored");
+                }
+                Vec::new()
+            }
+        };
+
+        let (reverse, bidirectional) = if self.mode == UDPerfMode::Receiver {
+            if self.reverse {
+                warn!("Reverse mode is set by the sender over the control channel! Parameter is ignored");
+            }
+            if self.bidirectional {
+                warn!("Bidirectional mode is set by the sender over the control channel! Parameter is ignored");
+            }
+            (false, false)
+        } else {
+            (self.reverse, self.bidirectional)
+        };
+
+        if reverse && bidirectional {
+            return Err(UdperfError::InvalidParameter("Reverse and bidirectional mode can't be used together!"));
+        }
+
+        let individual_ports = self.multiplex_port == MultiplexPort::Individual && self.multiplex_port_receiver == MultiplexPort::Individual;
+        if (reverse || bidirectional) && !individual_ports {
+            return Err(UdperfError::InvalidParameter("Reverse and bidirectional mode only support individual ports on the sender and receiver!"));
+        }
+
+        if self.ping_pong {
+            if reverse || bidirectional {
+                return Err(UdperfError::InvalidParameter("Ping-pong mode can't be used together with reverse or bidirectional mode!"));
+            }
+
+            if !individual_ports {
+                return Err(UdperfError::InvalidParameter("Ping-pong mode only supports individual ports on the sender and receiver!"));
+            }
+
+            if self.datagram_size < net::MessageHeader::new(net::MessageType::MEASUREMENT, 0, 0).len() as u32 {
+                return Err(UdperfError::InvalidParameter("Datagram size is too small to carry the message header in ping-pong mode!"));
+            }
+
+            if self.gsro {
+                warn!("GSO/GRO is not supported in ping-pong mode, since every datagram is echoed on its own! Parameter is ignored");
+            }
+        }
+
+        if self.sockets_per_thread == 0 {
+            return Err(UdperfError::InvalidParameter("At least one socket per thread is required!"));
+        }
+
+        if self.sockets_per_thread > 1 {
+            if self.io_model != IOModel::Epoll {
+                return Err(UdperfError::InvalidParameter("Multiple sockets per thread are only supported with the epoll IO model!"));
+            }
+
+            if reverse || bidirectional || self.ping_pong {
+                return Err(UdperfError::InvalidParameter("Multiple sockets per thread can't be used together with reverse, bidirectional or ping-pong mode!"));
+            }
+
+            if !individual_ports {
+                return Err(UdperfError::InvalidParameter("Multiple sockets per thread only support individual ports on the sender and receiver!"));
+            }
+
+            if !self.socket_options.nonblocking {
+                return Err(UdperfError::InvalidParameter("Multiple sockets per thread require non-blocking sockets!"));
+            }
+
+            if self.socket_options.tx_timestamps {
+                return Err(UdperfError::InvalidParameter("Transmit timestamps are only supported with one socket per thread!"));
+            }
+        }
+
+        if self.flows_per_thread == 0 {
+            return Err(UdperfError::InvalidParameter("At least one flow per thread is required!"));
+        }
+
+        if self.flows_per_thread > 1 {
+            if self.sockets_per_thread > 1 {
+                return Err(UdperfError::InvalidParameter("Multiple flows per thread can't be used together with multiple sockets per thread!"));
+            }
+
+            if self.io_model == IOModel::IoUring {
+                return Err(UdperfError::InvalidParameter("Multiple flows per thread are not supported with io_uring!"));
+            }
+
+            if reverse || bidirectional || self.ping_pong {
+                return Err(UdperfError::InvalidParameter("Multiple flows per thread can't be used together with reverse, bidirectional or ping-pong mode!"));
+            }
+
+            if self.flow_mode == FlowMode::FourTuple && self.multiplex_port != MultiplexPort::Individual {
+                return Err(UdperfError::InvalidParameter("Flows with their own 4-tuple require individual ports on the sender!"));
+            }
+
+            if self.socket_options.tx_timestamps {
+                return Err(UdperfError::InvalidParameter("Transmit timestamps are only supported with one flow per thread!"));
+            }
+        }
+
+        if self.amount_threads as usize * self.sockets_per_thread as usize * self.flows_per_thread as usize > crate::MAX_TEST_ID {
+            error!("Too many flows in total! Maximum is {}", crate::MAX_TEST_ID);
+            return Err(UdperfError::InvalidParameter("Too many flows in total!"));
+        }
+
+        if let Some(flow_label) = self.socket_options.flow_label {
+            if flow_label > 0xFFFFF {
+                error!("Flow label {:#x} is bigger than 20 bit!", flow_label);
+                return Err(UdperfError::InvalidParameter("Flow label is bigger than 20 bit!"));
+            }
+        }
+
+        if let Some(hop_limit) = self.socket_options.hop_limit {
+            if hop_limit == 0 || hop_limit > 255 {
+                return Err(UdperfError::InvalidParameter("Hop limit must be between 1 and 255!"));
+            }
+        }
+
+        let uring_parameter = self.uring_parameter.build()?;
+        let socket_options = self.build_socket_options();
+
+        let mut packet_buffer_size = match self.exchange_function {
+            ExchangeFunction::Mmsg => self.mmsg_amount,
+            _ => 1,
+        };
+
+        if self.io_model == IOModel::IoUring && uring_parameter.uring_mode == UringMode::Normal || uring_parameter.uring_mode == UringMode::Zerocopy {
+            warn!("Setting packet_buffer_size to {}!", uring_parameter.buffer_size);
+            packet_buffer_size = uring_parameter.buffer_size as usize;
+        }
+
+        let mut mss = if self.gsro && !self.ping_pong {
+            info!("GSO/GRO enabled with buffer size {}", self.gso_buffer_size);
+            self.gso_buffer_size
+        } else {
+            self.mss
+        };
+
+        if self.ping_pong && self.mode == UDPerfMode::Sender {
+            // Every msghdr carries exactly one datagram, which is echoed by the receiver
+            mss = self.datagram_size;
+
+            if self.io_model == IOModel::IoUring {
+                packet_buffer_size = 1;
+            }
+        }
+
+        if self.datagram_size_distribution != DatagramSizeDistribution::Fixed && self.mode == UDPerfMode::Sender {
+            // Every msghdr carries a single datagram, whose length is set before every send call
+            mss = self.datagram_size;
+        }
+
+        let simulate_connection = self.simulate_connection.unwrap_or(match self.multiplex_port_receiver {
+            MultiplexPort::Sharing => SimulateConnection::Single,
+            _ => SimulateConnection::Multiple
+        });
+
+        let output_format = if self.output_file_path.is_some() && self.output_format != OutputFormat::Prometheus { OutputFormat::File } else { self.output_format };
+
+        Ok(Parameter {
+            test_name: self.test_name,
+            run_name: self.run_name,
+            repetition_id: self.repetition_id,
+            mode: self.mode,
+            ip: self.ip,
+            port: self.port,
+            sender_port: self.sender_port,
+            amount_threads: self.amount_threads,
+            sockets_per_thread: self.sockets_per_thread,
+            flows_per_thread: self.flows_per_thread,
+            flow_mode: self.flow_mode,
+            output_interval: self.output_interval,
+            output_format,
+            output_file_path: self.output_file_path.unwrap_or(path::PathBuf::from(crate::DEFAULT_FILE_NAME)),
+            thread_statistics: self.thread_statistics,
+            perf_counters: self.perf_counters,
+            io_model: self.io_model,
+            epoll_edge_triggered: self.epoll_edge_triggered,
+            epoll_exclusive: self.epoll_exclusive,
+            test_runtime_length: self.test_runtime_length,
+            mss,
+            datagram_size: self.datagram_size,
+            datagram_size_distribution: if self.mode == UDPerfMode::Sender { self.datagram_size_distribution } else { DatagramSizeDistribution::Fixed },
+            datagram_size_min: self.datagram_size_min,
+            datagram_sizes,
+            payload_mode: self.payload_mode,
+            payload_seed: self.payload_seed,
+            payload_file_path: self.payload_file_path,
+            payload_verification: self.payload_verification,
+            packet_buffer_size,
+            userspace_pacing: userspace_pacing && (self.bandwidth > 0 || self.bandwidth_pps > 0 || self.traffic_pattern != TrafficPattern::Cbr),
+            pacing_rate: if userspace_pacing { self.bandwidth * 1000 * 1000 / 8 } else { 0 },
+            pacing_rate_pps: if userspace_pacing { self.bandwidth_pps } else { 0 },
+            traffic_pattern: if userspace_pacing { self.traffic_pattern } else { TrafficPattern::Cbr },
+            burst_length: self.burst_length,
+            burst_pause: self.burst_pause,
+            ramp_schedule,
+            socket_options,
+            exchange_function: self.exchange_function,
+            multiplex_port: self.multiplex_port,
+            multiplex_port_receiver: self.multiplex_port_receiver,
+            simulate_connection,
+            core_affinity: self.core_affinity,
+            numa_affinity: self.numa_affinity,
+            control_channel: self.control_channel || reverse || bidirectional,
+            reverse,
+            bidirectional,
+            ping_pong: self.ping_pong,
+            one_way_delay: self.one_way_delay,
+            uring_parameter
+        })
+    }
+
+    fn build_socket_options(&self) -> SocketOptions {
+        let ipv6 = self.ip.is_ipv6();
+
+        // In ping-pong mode every datagram is echoed on its own, so GSO/GRO can't be used
+        let gsro = self.gsro && !self.ping_pong;
+        let gso = if gsro && self.mode == UDPerfMode::Sender {
+            Some(self.datagram_size)
+        } else {
+            None
+        };
+        let gro = gsro && self.mode == UDPerfMode::Receiver;
+
+        let reuseport = match self.mode {
+            UDPerfMode::Sender => self.multiplex_port == MultiplexPort::Sharding,
+            UDPerfMode::Receiver => self.multiplex_port_receiver == MultiplexPort::Sharding,
+        };
+
+        // Convert Mbit/s total to byte/s per thread
+        let bandwidth_per_thread = if self.mode == UDPerfMode::Receiver || self.uses_userspace_pacing() {
+            0
+        } else if self.multiplex_port != MultiplexPort::Sharing {
+            self.bandwidth / self.amount_threads as u64
+        } else {
+            self.bandwidth
+        } / 8 * 1000 * 1000;
+        info!("Bandwidth per thread: {} Bytes/s", bandwidth_per_thread);
+
+        let mut socket_options = self.socket_options;
+        if socket_options.flow_label.is_some() && !ipv6 {
+            warn!("Flow label is only available with IPv6! Parameter is ignored");
+            socket_options.flow_label = None;
+        }
+        if let (Some(recv_buffer_size), Some(send_buffer_size)) = (socket_options.recv_buffer_size, socket_options.send_buffer_size) {
+            info!("Setting udp buffer sizes with recv {} and send {}", recv_buffer_size, send_buffer_size);
+        }
+
+        socket_options.build(ipv6, reuseport, gso, gro, bandwidth_per_thread)
+    }
+
+    fn build_datagram_sizes(&self) -> Result<Vec<WeightedSize>, UdperfError> {
+        if self.mode == UDPerfMode::Receiver || self.datagram_size_distribution == DatagramSizeDistribution::Fixed {
+            if self.datagram_size_file_path.is_some() {
+                warn!("Datagram size file is only used by the sender with the file size distribution! Parameter is ignored");
+            }
+            return Ok(Vec::new());
+        }
+
+        // GSO segments a buffer into datagrams of the same size and ping-pong echoes datagrams of the datagram size
+        if self.gsro {
+            return Err(UdperfError::InvalidParameter("Datagram size distributions can't be used together with GSO!"));
+        }
+        if self.ping_pong {
+            return Err(UdperfError::InvalidParameter("Datagram size distributions can't be used together with ping-pong mode!"));
+        }
+
+        let min_size = net::LEN_HEADER as u32;
+        match self.datagram_size_distribution {
+            DatagramSizeDistribution::Fixed => Ok(Vec::new()),
+            DatagramSizeDistribution::Uniform => {
+                if self.datagram_size_min < min_size || self.datagram_size_min > self.datagram_size {
+                    error!("Minimum datagram size has to be between {} and the datagram size {}", min_size, self.datagram_size);
+                    return Err(UdperfError::InvalidParameter("Minimum datagram size is out of range!"));
+                }
+                Ok(Vec::new())
+            },
+            DatagramSizeDistribution::Imix => Ok(datagram_size::imix(self.ip.is_ipv6(), min_size)),
+            DatagramSizeDistribution::File => {
+                let sizes = match &self.datagram_size_file_path {
+                    Some(path) => datagram_size::read_datagram_size_file(path)?,
+                    None => return Err(UdperfError::InvalidParameter("File size distribution requires a datagram size file!"))
+                };
+                if sizes.iter().any(|x| x.size < min_size || x.size > crate::MAX_UDP_DATAGRAM_SIZE) {
+                    error!("Datagram sizes have to be between {} and {}", min_size, crate::MAX_UDP_DATAGRAM_SIZE);
+                    return Err(UdperfError::InvalidParameter("Datagram size file contains a size, which is out of range!"));
+                }
+                Ok(sizes)
+            }
+        }
+    }
+
+    // The userspace pacer is needed for rates in datagrams per second and for every traffic pattern except constant bit rate
+    fn uses_userspace_pacing(&self) -> bool {
+        self.userspace_pacing || self.bandwidth_pps > 0 || self.traffic_pattern != TrafficPattern::Cbr
+    }
+
+    fn has_more_than_one_decimal(n: f64) -> bool {
+        let s = format!("{}", n);
+        let parts: Vec<&str> = s.split('.').collect();
+        if parts.get(1).is_none() {
+            return false;
+        }
+        parts[1].len() > 1
+    }
+}
@@ -0,0 +1,36 @@
+use crate::UdperfError;
+use super::random::SplitMix64;
+use super::statistic::{Parameter, PayloadMode};
+
+// Creates the payload of a datagram with the datagram size. The payload only depends on the offset in the datagram, so the receiver can create the same payload to verify the received datagrams.
+// The first bytes are overwritten by the message header.
+pub fn create_payload(parameter: &Parameter) -> Result<Vec<u8>, UdperfError> {
+    let length = parameter.datagram_size as usize;
+
+    match parameter.payload_mode {
+        PayloadMode::Zeros => Ok(vec![0; length]),
+        // Similar to iperf3's fill_with_repeating_pattern
+        PayloadMode::Pattern => Ok((0..length).map(|i| (48 + (i % 11) as u8).to_ascii_lowercase()).collect()),
+        PayloadMode::Random => {
+            let mut random = SplitMix64::new(parameter.payload_seed);
+            Ok((0..length.div_ceil(8)).flat_map(|_| random.next_u64().to_le_bytes()).take(length).collect())
+        },
+        PayloadMode::File => {
+            let path = parameter.payload_file_path.as_ref().ok_or(UdperfError::InvalidParameter("Payload mode file requires a payload file!"))?;
+            let content = std::fs::read(path).map_err(|x| UdperfError::from_io_error("Unable to read payload file", &x))?;
+            if content.is_empty() {
+                return Err(UdperfError::InvalidParameter("Payload file is empty!"));
+            }
+            Ok(content.iter().cycle().take(length).copied().collect())
+        }
+    }
+}
+
+// Compares the payload after the message header with the expected payload. Datagrams, which are longer than the expected payload, are only compared up to its length.
+pub fn verify_payload(datagram: &[u8], expected_payload: &[u8]) -> bool {
+    let end = usize::min(datagram.len(), expected_payload.len());
+    if end <= crate::net::LEN_HEADER {
+        return true;
+    }
+    datagram[crate::net::LEN_HEADER..end] == expected_payload[crate::net::LEN_HEADER..end]
+}
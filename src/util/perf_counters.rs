@@ -0,0 +1,157 @@
+use log::{debug, info, warn};
+
+use crate::UdperfError;
+
+// Constants and struct perf_event_attr from linux/perf_event.h, which are not exported by libc
+const PERF_TYPE_HARDWARE: u32 = 0;
+const PERF_TYPE_SOFTWARE: u32 = 1;
+const PERF_COUNT_HW_CPU_CYCLES: u64 = 0;
+const PERF_COUNT_HW_INSTRUCTIONS: u64 = 1;
+const PERF_COUNT_HW_CACHE_MISSES: u64 = 3;
+const PERF_COUNT_SW_TASK_CLOCK: u64 = 1;
+const PERF_COUNT_SW_PAGE_FAULTS: u64 = 2;
+const PERF_COUNT_SW_CONTEXT_SWITCHES: u64 = 3;
+const PERF_ATTR_FLAG_DISABLED: u64 = 1 << 0;
+const PERF_ATTR_FLAG_EXCLUDE_KERNEL: u64 = 1 << 5;
+const PERF_ATTR_FLAG_EXCLUDE_HV: u64 = 1 << 6;
+const PERF_FLAG_FD_CLOEXEC: libc::c_ulong = 1 << 3;
+const PERF_EVENT_IOC_ENABLE: libc::c_ulong = 0x2400;
+
+// First version of struct perf_event_attr (PERF_ATTR_SIZE_VER0), which is accepted by every kernel
+#[repr(C)]
+#[derive(Debug, Default)]
+struct PerfEventAttr {
+    event_type: u32,
+    size: u32,
+    config: u64,
+    sample_period: u64,
+    sample_type: u64,
+    read_format: u64,
+    flags: u64,
+    wakeup_events: u32,
+    bp_type: u32,
+    config1: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PerfEvent {
+    Cycles,
+    Instructions,
+    CacheMisses,
+    TaskClock,
+    ContextSwitches,
+    PageFaults,
+}
+
+impl PerfEvent {
+    fn type_and_config(&self) -> (u32, u64) {
+        match self {
+            PerfEvent::Cycles => (PERF_TYPE_HARDWARE, PERF_COUNT_HW_CPU_CYCLES),
+            PerfEvent::Instructions => (PERF_TYPE_HARDWARE, PERF_COUNT_HW_INSTRUCTIONS),
+            PerfEvent::CacheMisses => (PERF_TYPE_HARDWARE, PERF_COUNT_HW_CACHE_MISSES),
+            PerfEvent::TaskClock => (PERF_TYPE_SOFTWARE, PERF_COUNT_SW_TASK_CLOCK),
+            PerfEvent::ContextSwitches => (PERF_TYPE_SOFTWARE, PERF_COUNT_SW_CONTEXT_SWITCHES),
+            PerfEvent::PageFaults => (PERF_TYPE_SOFTWARE, PERF_COUNT_SW_PAGE_FAULTS),
+        }
+    }
+
+    fn is_hardware(&self) -> bool {
+        self.type_and_config().0 == PERF_TYPE_HARDWARE
+    }
+}
+
+// Values of the counters. The hardware counters are zero, if no hardware PMU is available (e.g. in a virtual machine).
+#[derive(Debug, Default, Clone, Copy)]
+pub struct PerfValues {
+    pub cycles: u64,
+    pub instructions: u64,
+    pub cache_misses: u64,
+    pub task_clock: u64, // in nanoseconds
+    pub context_switches: u64,
+    pub page_faults: u64,
+}
+
+// Performance counters of the calling thread. Has to be used by the same thread, which created it.
+pub struct PerfCounters {
+    counters: Vec<(PerfEvent, i32)>,
+}
+
+impl PerfCounters {
+    pub fn thread() -> Result<PerfCounters, UdperfError> {
+        let mut counters = Vec::new();
+        // The kernel is only measured, if perf_event_paranoid or the capabilities of the process allow it
+        let mut exclude_kernel = false;
+
+        for event in [PerfEvent::TaskClock, PerfEvent::ContextSwitches, PerfEvent::PageFaults, PerfEvent::Cycles, PerfEvent::Instructions, PerfEvent::CacheMisses] {
+            let mut result = open_counter(event, exclude_kernel);
+            if !exclude_kernel && matches!(result.map_err(|error| error.errno()), Err(Some(libc::EACCES)) | Err(Some(libc::EPERM))) {
+                warn!("Not allowed to measure the kernel with perf_event_open. Only user space is measured, check kernel.perf_event_paranoid");
+                exclude_kernel = true;
+                result = open_counter(event, exclude_kernel);
+            }
+
+            match result {
+                Ok(fd) => counters.push((event, fd)),
+                // Without a hardware PMU (e.g. in a virtual machine) or with a PMU not supporting the event, only the remaining counters are used
+                Err(error) if event.is_hardware() && matches!(error.errno(), Some(libc::ENOENT) | Some(libc::EOPNOTSUPP) | Some(libc::ENODEV)) => {
+                    info!("Hardware performance counter {:?} not available: {}", event, error);
+                },
+                Err(error) => {
+                    warn!("{} {:?}", error, event);
+                    counters.iter().for_each(|(_, fd)| unsafe { libc::close(*fd); });
+                    return Err(error);
+                }
+            }
+        }
+
+        debug!("Opened performance counters {:?}", counters);
+        counters.iter().for_each(|(_, fd)| unsafe { libc::ioctl(*fd, PERF_EVENT_IOC_ENABLE, 0); });
+        Ok(PerfCounters { counters })
+    }
+
+    pub fn read(&self) -> PerfValues {
+        let mut values = PerfValues::default();
+        for (event, fd) in self.counters.iter() {
+            let mut value: u64 = 0;
+            let read_result = unsafe { libc::read(*fd, &mut value as *mut _ as _, std::mem::size_of::<u64>()) };
+            if read_result != std::mem::size_of::<u64>() as isize {
+                warn!("Failed to read performance counter {:?}", event);
+                continue;
+            }
+            match event {
+                PerfEvent::Cycles => values.cycles = value,
+                PerfEvent::Instructions => values.instructions = value,
+                PerfEvent::CacheMisses => values.cache_misses = value,
+                PerfEvent::TaskClock => values.task_clock = value,
+                PerfEvent::ContextSwitches => values.context_switches = value,
+                PerfEvent::PageFaults => values.page_faults = value,
+            }
+        }
+        values
+    }
+}
+
+impl Drop for PerfCounters {
+    fn drop(&mut self) {
+        self.counters.iter().for_each(|(_, fd)| unsafe { libc::close(*fd); });
+    }
+}
+
+// Counts the event for the calling thread on any CPU
+fn open_counter(event: PerfEvent, exclude_kernel: bool) -> Result<i32, UdperfError> {
+    let (event_type, config) = event.type_and_config();
+    let attr = PerfEventAttr {
+        event_type,
+        size: std::mem::size_of::<PerfEventAttr>() as u32,
+        config,
+        flags: PERF_ATTR_FLAG_DISABLED | PERF_ATTR_FLAG_EXCLUDE_HV | if exclude_kernel { PERF_ATTR_FLAG_EXCLUDE_KERNEL } else { 0 },
+        ..Default::default()
+    };
+
+    let fd = unsafe { libc::syscall(libc::SYS_perf_event_open, &attr as *const PerfEventAttr, 0, -1, -1, PERF_FLAG_FD_CLOEXEC) };
+    if fd == -1 {
+        Err(UdperfError::last_os_error("Failed to open performance counter"))
+    } else {
+        Ok(fd as i32)
+    }
+}
@@ -0,0 +1,43 @@
+// Small pseudo random number generator (SplitMix64), which is good enough to generate traffic and payloads. It's reproducible with the same seed.
+#[derive(Debug, Clone, Copy)]
+pub struct SplitMix64 {
+    state: u64,
+}
+
+const GOLDEN_GAMMA: u64 = 0x9E3779B97F4A7C15;
+
+impl SplitMix64 {
+    pub fn new(seed: u64) -> SplitMix64 {
+        SplitMix64 { state: seed }
+    }
+
+    // Seed from the current time, if the sequence doesn't have to be reproducible
+    pub fn from_time() -> SplitMix64 {
+        SplitMix64::new(super::get_timestamp_ns())
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(GOLDEN_GAMMA);
+        mix(self.state)
+    }
+
+    // Uniformly distributed in [0, bound)
+    pub fn next_below(&mut self, bound: u64) -> u64 {
+        ((self.next_u64() as u128 * bound as u128) >> 64) as u64
+    }
+}
+
+// The state can be shared by multiple threads with an atomic counter, which is incremented by GOLDEN_GAMMA before every call
+pub fn next_shared_u64(state: &std::sync::atomic::AtomicU64) -> u64 {
+    mix(state.fetch_add(GOLDEN_GAMMA, std::sync::atomic::Ordering::Relaxed).wrapping_add(GOLDEN_GAMMA))
+}
+
+pub fn to_f64(x: u64) -> f64 {
+    ((x >> 11) + 1) as f64 / (1_u64 << 53) as f64
+}
+
+fn mix(mut x: u64) -> u64 {
+    x = (x ^ (x >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94D049BB133111EB);
+    x ^ (x >> 31)
+}
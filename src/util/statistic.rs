@@ -1,20 +1,31 @@
-use std::{fs::OpenOptions, ops::Add, path, thread, time::{Instant, SystemTime, UNIX_EPOCH}};
-use log::{debug, error, info};
-use serde::Serialize;
+use std::{fs::OpenOptions, ops::Add, path, sync::mpsc, thread, time::{Instant, SystemTime, UNIX_EPOCH}};
+use log::{debug, error, info, warn};
+use serde::{Deserialize, Serialize};
 use serde_json::{self};
 use crate::{io_uring::{UringMode, UringSqFillingMode, UringTaskWork}, net::socket_options::SocketOptions};
+use super::cpu_util::{CpuUsage, CpuUtil};
+use super::histogram::Histogram;
+use super::udp_counters::UdpCounters;
+use super::perf_counters::PerfValues;
+use super::exporter;
+use super::datagram_size::WeightedSize;
+use super::pacer::RampStep;
+use super::parameter_builder::ParameterBuilder;
 use serde::Serializer;
 use std::collections::HashMap;
 
-#[derive(clap::ValueEnum, Default, PartialEq, Debug, Clone, Copy, Serialize)]
+#[derive(clap::ValueEnum, Default, PartialEq, Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum OutputFormat {
     #[default]
     Text,
     Json,
-    File
+    File,
+    JsonLines,
+    Prometheus,
+    Influx
 }
 
-#[derive(clap::ValueEnum, Debug, PartialEq, Serialize, Clone, Copy, Default)]
+#[derive(clap::ValueEnum, Debug, PartialEq, Serialize, Deserialize, Clone, Copy, Default)]
 pub enum MultiplexPort {
     #[default]
     Individual,
@@ -22,13 +33,64 @@ pub enum MultiplexPort {
     Sharding
 }
 
-#[derive(clap::ValueEnum, Debug, PartialEq, Serialize, Clone, Copy, Default)]
+#[derive(clap::ValueEnum, Debug, PartialEq, Serialize, Deserialize, Clone, Copy, Default)]
 pub enum SimulateConnection {
     Single,
     #[default]
     Multiple
 }
 
+// How the flows of a connection are distinguished by the network stack of the receiver
+#[derive(clap::ValueEnum, Debug, PartialEq, Serialize, Deserialize, Clone, Copy, Default)]
+pub enum FlowMode {
+    // All flows share the 4-tuple of the socket and only differ in the test ID, like the connection ID of QUIC
+    #[default]
+    ConnectionId,
+    // Every flow uses its own socket and therefore its own source port
+    FourTuple
+}
+
+// Arrival process of the datagrams, which is generated by the userspace pacer of the sender
+#[derive(clap::ValueEnum, Debug, PartialEq, Serialize, Deserialize, Clone, Copy, Default)]
+pub enum TrafficPattern {
+    // Constant bit rate
+    #[default]
+    Cbr,
+    // Bursts with the bandwidth (or as fast as possible) followed by a pause
+    OnOff,
+    // Exponentially distributed inter-departure times with the bandwidth as mean rate
+    Poisson,
+    // Stepped bandwidth schedule read from a file
+    Ramp
+}
+
+// Distribution of the sizes of the sent datagrams
+#[derive(clap::ValueEnum, Debug, PartialEq, Serialize, Deserialize, Clone, Copy, Default)]
+pub enum DatagramSizeDistribution {
+    // All datagrams have the datagram size
+    #[default]
+    Fixed,
+    // Uniformly distributed between the minimum size and the datagram size
+    Uniform,
+    // Mix of small, medium and full-size packets (64/576/1500 bytes IP packets in the ratio 7:4:1)
+    Imix,
+    // Weighted list of sizes read from a file
+    File
+}
+
+// Content of the payload after the message header. Every datagram carries the same payload, so the receiver can verify it.
+#[derive(clap::ValueEnum, Debug, PartialEq, Serialize, Deserialize, Clone, Copy, Default)]
+pub enum PayloadMode {
+    Zeros,
+    // Repeating pattern of ASCII characters like iperf3
+    #[default]
+    Pattern,
+    // Pseudo random bytes generated from the payload seed
+    Random,
+    // Contents of a file, which is repeated to fill the datagram
+    File
+}
+
 #[derive(Debug, Clone)]
 pub struct StatisticInterval {
     interval_id: u64,
@@ -38,10 +100,13 @@ pub struct StatisticInterval {
     pub total_interval_outputs: u64,
     amount_interval_outputs: u64,
     pub statistics: Vec<Statistic>,
+    reporter: Option<mpsc::Sender<Statistic>>,
+    // CPU utilization of the thread, which measures the intervals
+    cpu_util: CpuUtil,
 }
 
 impl StatisticInterval {
-    pub fn new(last_send_instant: Instant, output_interval: f64, runtime_length: u64) -> StatisticInterval {
+    pub fn new(last_send_instant: Instant, output_interval: f64, runtime_length: u64, reporter: Option<mpsc::Sender<Statistic>>) -> StatisticInterval {
         let total_interval_outputs = if output_interval == 0.0 { 0 } else { (runtime_length as f64 / output_interval).floor() as u64 };
         StatisticInterval {
             interval_id: 1,
@@ -50,13 +115,17 @@ impl StatisticInterval {
             last_send_timestamp: Statistic::get_unix_timestamp(),
             amount_interval_outputs: 0,
             total_interval_outputs,
-            statistics: Vec::with_capacity(total_interval_outputs as usize)
+            statistics: Vec::with_capacity(total_interval_outputs as usize),
+            reporter,
+            cpu_util: CpuUtil::thread()
         }
     }
 
     pub fn start(&mut self, offset_in_milliseconds: Option<u64>) {
         self.last_send_instant = Instant::now() + std::time::Duration::from_millis(offset_in_milliseconds.unwrap_or(0));
         self.last_send_timestamp = Statistic::get_unix_timestamp() + offset_in_milliseconds.unwrap_or(0) as f64;
+        // The CPU utilization of the first interval starts with the measurement
+        self.cpu_util.get_relative_cpu_util();
     }
 
     pub fn finished(&self) -> bool {
@@ -75,6 +144,7 @@ impl StatisticInterval {
 
         statistic_new.interval_id = self.interval_id;
         statistic_new.set_test_duration(Some(self.last_send_timestamp), Some(current_time_unix));
+        statistic_new.set_cpu_usage(self.cpu_util.get_relative_cpu_util());
         statistic_new.calculate_statistics();
 
         // Update the last send operation instant to the current instant
@@ -83,12 +153,18 @@ impl StatisticInterval {
         self.amount_interval_outputs += 1;
         self.interval_id += 1;
 
+        // Report the interval immediately, so it's printed while the measurement is still running
+        if let Some(reporter) = &self.reporter {
+            if reporter.send(statistic_new.clone()).is_err() {
+                debug!("{:?}: Interval reporter is not running anymore", thread::current().id());
+            }
+        }
         self.statistics.push(statistic_new);
     }
 }
 
 
-#[derive(Debug, Serialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Statistic {
     #[serde(flatten)]
     pub parameter: Parameter,
@@ -101,26 +177,114 @@ pub struct Statistic {
     pub amount_data_bytes: usize,
     pub amount_reordered_datagrams: u64,
     pub amount_duplicated_datagrams: u64,
+    pub amount_corrupted_datagrams: u64,
     pub amount_omitted_datagrams: i64,
+    // Datagrams dropped because the receive queue of a socket was full (SO_RXQ_OVFL) and the peak receive memory of a single socket (SK_MEMINFO)
+    pub amount_socket_drops: u64,
+    pub socket_rmem_peak: u64,
     pub amount_syscalls: u64,
     pub amount_io_model_calls: u64,
     pub amount_eagain: u64,
     pub data_rate_gbit: f64,
+    // Data, which the traffic pattern of the pacer offered to the thread
+    pub amount_offered_data_bytes: u64,
+    pub offered_data_rate_gbit: f64,
     pub packet_loss: f64,
     pub cpu_user_time: f64,
     pub cpu_system_time: f64,
     pub cpu_total_time: f64,
-    #[serde(skip_serializing)]
+    // Context switches and page faults of getrusage and the maximum resident set size of the process in KiB
+    pub voluntary_context_switches: u64,
+    pub involuntary_context_switches: u64,
+    pub minor_page_faults: u64,
+    pub major_page_faults: u64,
+    pub max_rss: u64,
+    // Utilization of the measured cores (all or the pinned cores) from /proc/stat, including interrupt and softirq context
+    pub cores: String,
+    pub cores_user_time: f64,
+    pub cores_system_time: f64,
+    pub cores_irq_time: f64,
+    pub cores_softirq_time: f64,
+    // Deltas of the system-wide UDP and IP reassembly counters of the kernel
+    pub udp_in_errors: u64,
+    pub udp_rcvbuf_errors: u64,
+    pub udp_sndbuf_errors: u64,
+    pub udp_no_ports: u64,
+    pub udp_in_csum_errors: u64,
+    pub ip_reasm_reqds: u64,
+    pub ip_reasm_fails: u64,
+    // Performance counters of the threads (perf_event_open). Cycles, instructions and cache misses are zero without a hardware PMU.
+    pub perf_cycles: u64,
+    pub perf_instructions: u64,
+    pub perf_cache_misses: u64,
+    pub perf_task_clock: u64, // in nanoseconds
+    pub perf_context_switches: u64,
+    pub perf_page_faults: u64,
+    pub cycles_per_datagram: f64,
+    pub instructions_per_datagram: f64,
+    pub cache_misses_per_datagram: f64,
+    pub task_clock_per_datagram: f64,
+    pub cycles_per_syscall: f64,
+    pub instructions_per_syscall: f64,
+    pub rtt_p50: f64,
+    pub rtt_p99: f64,
+    pub rtt_p999: f64,
+    pub rtt_max: f64,
+    #[serde(skip)]
+    pub rtt_histogram: Histogram,
+    pub jitter: f64,
+    pub owd_p50: f64,
+    pub owd_p99: f64,
+    pub owd_p999: f64,
+    pub owd_max: f64,
+    #[serde(skip)]
+    pub jitter_state: Jitter,
+    #[serde(skip)]
+    pub owd_histogram: Histogram,
+    pub rx_latency_p50: f64,
+    pub rx_latency_p99: f64,
+    pub rx_latency_p999: f64,
+    pub rx_latency_max: f64,
+    #[serde(skip)]
+    pub rx_latency_histogram: Histogram,
+    pub tx_sched_delay_p50: f64,
+    pub tx_sched_delay_p99: f64,
+    pub tx_sched_delay_p999: f64,
+    pub tx_sched_delay_max: f64,
+    #[serde(skip)]
+    pub tx_sched_delay_histogram: Histogram,
+    pub tx_driver_delay_p50: f64,
+    pub tx_driver_delay_p99: f64,
+    pub tx_driver_delay_p999: f64,
+    pub tx_driver_delay_max: f64,
+    #[serde(skip)]
+    pub tx_driver_delay_histogram: Histogram,
+    pub amount_flows: u64,
+    pub flow_datagrams_min: u64,
+    pub flow_datagrams_max: u64,
+    pub flow_packet_loss_max: f64,
+    // One-way delay p50/p99/p99.9/max of every flow as "<test ID>:<p50>/<p99>/<p99.9>/<max>", separated by ";"
+    pub flow_owd: String,
+    // Thread, which measured the statistic. Not set for the total statistic of all threads.
+    pub thread_id: Option<u16>,
+    pub core_id: Option<usize>,
+    pub port: Option<u16>,
+    pub test_ids: String,
+    // Fairness of the data rates between the threads (Jain's fairness index). Only set for the total statistic.
+    pub thread_fairness_index: f64,
+    pub thread_data_rate_min: f64,
+    pub thread_data_rate_max: f64,
+    #[serde(skip_serializing, default)]
     pub uring_cq_overflows: u64,
-    #[serde(skip_serializing)]
+    #[serde(skip_serializing, default)]
     pub uring_out_of_buffers: u64,
     pub uring_copied_zc: u64,
     pub uring_canceled_multishot: u64,
-    #[serde(with = "utilization_option_box_slice")]
+    #[serde(serialize_with = "utilization_option_box_slice::serialize", skip_deserializing)]
     pub uring_sq_utilization: Option<Box<[usize]>>,
-    #[serde(with = "utilization_option_box_slice")]
+    #[serde(serialize_with = "utilization_option_box_slice::serialize", skip_deserializing)]
     pub uring_cq_utilization: Option<Box<[usize]>>,
-    #[serde(with = "utilization_option_box_slice")]
+    #[serde(serialize_with = "utilization_option_box_slice::serialize", skip_deserializing)]
     pub uring_inflight_utilization: Option<Box<[usize]>>,
 }
 
@@ -128,12 +292,27 @@ pub struct Statistic {
 // Measurement is used to measure the time of a specific statistc. Type time::Instant cannot be serialized, so it is not included in the Statistic struct.
 #[allow(dead_code)]
 #[derive(Debug, Clone)]
+// Per-flow state of a receiver thread. Only the counters of the flow are kept, which are added to the statistic of the thread, when an interval or the measurement is reported.
 pub struct Measurement {
-    pub start_time: f64,
-    pub end_time: f64,
-    pub statistic: Statistic,
     pub first_packet_received: bool,
     pub last_packet_received: bool,
+    // Every flow (test ID) has its own sequence of packet ids
+    pub next_packet_id: u64,
+    // Counters of the current interval
+    pub amount_datagrams: u64,
+    pub amount_data_bytes: usize,
+    pub amount_reordered_datagrams: u64,
+    pub amount_duplicated_datagrams: u64,
+    pub amount_corrupted_datagrams: u64,
+    pub amount_omitted_datagrams: i64,
+    pub owd_histogram: Histogram,
+    pub rx_latency_histogram: Histogram,
+    // The jitter estimate of the flow continues over all intervals
+    pub jitter_state: Jitter,
+    // Datagrams of the flow in the previous intervals
+    pub flow_datagrams: u64,
+    pub flow_omitted_datagrams: i64,
+    pub flow_owd_histogram: Histogram,
 }
 
 impl Statistic {
@@ -150,15 +329,88 @@ impl Statistic {
             amount_data_bytes: 0,
             amount_reordered_datagrams: 0,
             amount_duplicated_datagrams: 0,
+            amount_corrupted_datagrams: 0,
             amount_omitted_datagrams: 0,
+            amount_socket_drops: 0,
+            socket_rmem_peak: 0,
             amount_syscalls: 0,
             amount_io_model_calls: 0,
             amount_eagain: 0,
             data_rate_gbit: 0.0,
+            amount_offered_data_bytes: 0,
+            offered_data_rate_gbit: 0.0,
             packet_loss: 0.0,
             cpu_user_time: 0.0,
             cpu_system_time: 0.0,
             cpu_total_time: 0.0,
+            voluntary_context_switches: 0,
+            involuntary_context_switches: 0,
+            minor_page_faults: 0,
+            major_page_faults: 0,
+            max_rss: 0,
+            cores: String::new(),
+            cores_user_time: 0.0,
+            cores_system_time: 0.0,
+            cores_irq_time: 0.0,
+            cores_softirq_time: 0.0,
+            udp_in_errors: 0,
+            udp_rcvbuf_errors: 0,
+            udp_sndbuf_errors: 0,
+            udp_no_ports: 0,
+            udp_in_csum_errors: 0,
+            ip_reasm_reqds: 0,
+            ip_reasm_fails: 0,
+            perf_cycles: 0,
+            perf_instructions: 0,
+            perf_cache_misses: 0,
+            perf_task_clock: 0,
+            perf_context_switches: 0,
+            perf_page_faults: 0,
+            cycles_per_datagram: 0.0,
+            instructions_per_datagram: 0.0,
+            cache_misses_per_datagram: 0.0,
+            task_clock_per_datagram: 0.0,
+            cycles_per_syscall: 0.0,
+            instructions_per_syscall: 0.0,
+            rtt_p50: 0.0,
+            rtt_p99: 0.0,
+            rtt_p999: 0.0,
+            rtt_max: 0.0,
+            rtt_histogram: Histogram::new(),
+            jitter: 0.0,
+            owd_p50: 0.0,
+            owd_p99: 0.0,
+            owd_p999: 0.0,
+            owd_max: 0.0,
+            jitter_state: Jitter::default(),
+            owd_histogram: Histogram::new(),
+            rx_latency_p50: 0.0,
+            rx_latency_p99: 0.0,
+            rx_latency_p999: 0.0,
+            rx_latency_max: 0.0,
+            rx_latency_histogram: Histogram::new(),
+            tx_sched_delay_p50: 0.0,
+            tx_sched_delay_p99: 0.0,
+            tx_sched_delay_p999: 0.0,
+            tx_sched_delay_max: 0.0,
+            tx_sched_delay_histogram: Histogram::new(),
+            tx_driver_delay_p50: 0.0,
+            tx_driver_delay_p99: 0.0,
+            tx_driver_delay_p999: 0.0,
+            tx_driver_delay_max: 0.0,
+            tx_driver_delay_histogram: Histogram::new(),
+            amount_flows: 0,
+            flow_datagrams_min: 0,
+            flow_datagrams_max: 0,
+            flow_packet_loss_max: 0.0,
+            flow_owd: String::new(),
+            thread_id: None,
+            core_id: None,
+            port: None,
+            test_ids: String::new(),
+            thread_fairness_index: 0.0,
+            thread_data_rate_min: 0.0,
+            thread_data_rate_max: 0.0,
             uring_cq_overflows: 0,
             uring_out_of_buffers: 0,
             uring_copied_zc: 0,
@@ -180,10 +432,104 @@ impl Statistic {
         debug!("Updating statistic...");
         self.total_data_gbyte = self.calculate_total_data();
         self.data_rate_gbit = self.calculate_data_rate();
+        self.offered_data_rate_gbit = self.calculate_offered_data_rate();
         self.packet_loss = self.calculate_packet_loss();
+        self.calculate_rtt();
+        self.calculate_delay();
+        self.calculate_rx_latency();
+        self.calculate_tx_delay();
+        self.calculate_perf_costs();
         debug!("Statistic updated: {:?}", self);
     }
 
+    // Compact comparison of both directions of a bidirectional measurement. The statistics have to be printed before, since the rates are calculated while printing.
+    pub fn print_bidirectional(statistics: &[Statistic]) {
+        println!("------------------------");
+        println!("Summary Bidirectional Measurement");
+        println!("------------------------");
+        print!("{:<18}", "");
+        statistics.iter().for_each(|statistic| print!("{:>14}", format!("{:?}", statistic.parameter.mode)));
+        println!();
+        print!("{:<18}", "Data rate Gibit/s");
+        statistics.iter().for_each(|statistic| print!("{:>14.2}", statistic.data_rate_gbit));
+        println!();
+        print!("{:<18}", "Total GiBytes");
+        statistics.iter().for_each(|statistic| print!("{:>14.2}", statistic.total_data_gbyte));
+        println!();
+        print!("{:<18}", "Packet loss %");
+        statistics.iter().for_each(|statistic| print!("{:>14.2}", statistic.packet_loss));
+        println!();
+        print!("{:<18}", "Datagrams");
+        statistics.iter().for_each(|statistic| print!("{:>14}", statistic.amount_datagrams));
+        println!();
+        println!("------------------------");
+        if let Some(statistic) = statistics.first() {
+            println!("CPU total (both directions): {:.2}%", statistic.cpu_total_time);
+            println!("------------------------");
+        }
+    }
+
+    // Statistics of the single threads of a direction. The text output is a compact table, the other formats contain the full statistic of every thread.
+    pub fn print_threads(statistics: &mut [Statistic], output_format: OutputFormat) {
+        if output_format != OutputFormat::Text {
+            statistics.iter_mut().for_each(|statistic| statistic.print(output_format, false));
+            return;
+        }
+
+        println!("------------------------");
+        println!("Threads {:?}", statistics.first().map(|statistic| statistic.parameter.mode).unwrap_or_default());
+        println!("------------------------");
+        let socket_drops = statistics.first().is_some_and(|statistic| statistic.parameter.socket_options.rxq_overflow && statistic.parameter.mode == super::UDPerfMode::Receiver);
+        print!("{:>6} {:>6} {:>7} {:>12} {:>12} {:>10} {:>8} {:>8} {:>8} {:>10}", "Thread", "Core", "Port", "Test IDs", "Datagrams", "Gibit/s", "Loss %", "User %", "Sys %", "Invol. CS");
+        if socket_drops {
+            print!(" {:>12} {:>12}", "Sock drops", "Rmem peak");
+        }
+        println!();
+        for statistic in statistics.iter_mut() {
+            statistic.calculate_statistics();
+            print!(
+                "{:>6} {:>6} {:>7} {:>12} {:>12} {:>10.2} {:>8.2} {:>8.2} {:>8.2} {:>10}",
+                statistic.thread_id.map_or(String::from("-"), |thread_id| thread_id.to_string()),
+                statistic.core_id.map_or(String::from("-"), |core_id| core_id.to_string()),
+                statistic.port.map_or(String::from("-"), |port| port.to_string()),
+                if statistic.test_ids.is_empty() { "-" } else { &statistic.test_ids },
+                statistic.amount_datagrams,
+                statistic.thread_data_rate(),
+                if statistic.amount_datagrams == 0 { 0.0 } else { statistic.packet_loss },
+                statistic.cpu_user_time,
+                statistic.cpu_system_time,
+                statistic.involuntary_context_switches
+            );
+            if socket_drops {
+                print!(" {:>12} {:>12}", statistic.amount_socket_drops, statistic.socket_rmem_peak);
+            }
+            println!();
+        }
+    }
+
+    // Jain's fairness index of the data rates of the threads: 1.0 if all threads have the same data rate, 1/n if a single thread got everything.
+    // Threads without any datagram count with a data rate of zero.
+    pub fn set_thread_fairness(&mut self, thread_statistics: &mut [Statistic]) {
+        if thread_statistics.is_empty() {
+            return;
+        }
+        let data_rates: Vec<f64> = thread_statistics.iter_mut().map(|statistic| {
+            statistic.calculate_statistics();
+            statistic.thread_data_rate()
+        }).collect();
+
+        let sum: f64 = data_rates.iter().sum();
+        let sum_of_squares: f64 = data_rates.iter().map(|data_rate| data_rate * data_rate).sum();
+        self.thread_fairness_index = if sum_of_squares == 0.0 { 0.0 } else { sum * sum / (data_rates.len() as f64 * sum_of_squares) };
+        self.thread_data_rate_min = data_rates.iter().copied().fold(f64::INFINITY, f64::min);
+        self.thread_data_rate_max = data_rates.iter().copied().fold(0.0, f64::max);
+    }
+
+    // The data rate is undefined for a thread, which didn't measure anything
+    fn thread_data_rate(&self) -> f64 {
+        if self.amount_datagrams == 0 || self.test_duration <= 0.0 { 0.0 } else { self.data_rate_gbit }
+    }
+
     pub fn print(&mut self, output_format: OutputFormat, interval_print: bool) {
         self.calculate_statistics();
 
@@ -194,17 +540,50 @@ impl Statistic {
             OutputFormat::Text => {
                 let interval_timestamp = self.interval_id as f64 * self.parameter.output_interval;
 
-                if interval_print {
+                if interval_print && self.parameter.mode == super::UDPerfMode::Receiver {
+                    print!(
+                        "[{:3}] {:2.2}-{:2.2} sec  {:.2} GBytes  {:.2} Gbits/sec  {:.3} ms  {}/{} ({:.1}%)  CPU {:.1}%",
+                        self.interval_id, 
+                        if interval_timestamp == 0.0 { 0.0 } else { interval_timestamp - self.parameter.output_interval }, 
+                        interval_timestamp, 
+                        self.total_data_gbyte, 
+                        self.data_rate_gbit, 
+                        self.jitter / 1000.0,
+                        self.amount_omitted_datagrams, 
+                        self.amount_datagrams, 
+                        self.packet_loss,
+                        self.cpu_total_time
+                    );
+                    if self.parameter.socket_options.rxq_overflow {
+                        print!("  socket drops {}", self.amount_socket_drops);
+                    }
+                    println!();
+                } else if interval_print && self.parameter.has_offered_rate() {
                     println!(
-                        "[{:3}] {:2.2}-{:2.2} sec  {:.2} GBytes  {:.2} Gbits/sec  {}/{} ({:.1}%)",
+                        "[{:3}] {:2.2}-{:2.2} sec  {:.2} GBytes  {:.2} Gbits/sec (offered {:.2} Gbits/sec)  {}/{} ({:.1}%)  CPU {:.1}%",
                         self.interval_id, 
                         if interval_timestamp == 0.0 { 0.0 } else { interval_timestamp - self.parameter.output_interval }, 
                         interval_timestamp, 
                         self.total_data_gbyte, 
                         self.data_rate_gbit, 
+                        self.offered_data_rate_gbit, 
                         self.amount_omitted_datagrams, 
                         self.amount_datagrams, 
-                        self.packet_loss
+                        self.packet_loss,
+                        self.cpu_total_time
+                    );
+                } else if interval_print {
+                    println!(
+                        "[{:3}] {:2.2}-{:2.2} sec  {:.2} GBytes  {:.2} Gbits/sec  {}/{} ({:.1}%)  CPU {:.1}%",
+                        self.interval_id, 
+                        if interval_timestamp == 0.0 { 0.0 } else { interval_timestamp - self.parameter.output_interval }, 
+                        interval_timestamp, 
+                        self.total_data_gbyte, 
+                        self.data_rate_gbit, 
+                        self.amount_omitted_datagrams, 
+                        self.amount_datagrams, 
+                        self.packet_loss,
+                        self.cpu_total_time
                     );
                 } else {
                 println!("------------------------");
@@ -213,22 +592,112 @@ impl Statistic {
                 println!("Total time: {:.2}s", self.test_duration);
                 println!("Total data: {:.2} GiBytes", self.total_data_gbyte);
                 println!("Data rate: {:.2} GiBytes/s / {:.2} Gibit/s", self.data_rate_gbit / 8.0, self.data_rate_gbit);
+                if self.parameter.has_offered_rate() {
+                    println!("Offered data rate ({:?}): {:.2} Gibit/s", self.parameter.traffic_pattern, self.offered_data_rate_gbit);
+                }
                 println!("Packet loss: {:.2}%", self.packet_loss);
+                if self.parameter.mode == super::UDPerfMode::Receiver {
+                    // Only measured, if the sender stamps the datagrams with its transmit time
+                    if self.jitter > 0.0 || self.owd_max > 0.0 {
+                        println!("Jitter: {:.3} ms", self.jitter / 1000.0);
+                        println!("One-way delay p50/p99/p99.9/max: {:.2}/{:.2}/{:.2}/{:.2}us", self.owd_p50, self.owd_p99, self.owd_p999, self.owd_max);
+                    }
+                    if self.parameter.socket_options.rx_timestamps {
+                        println!("Kernel to user space latency p50/p99/p99.9/max: {:.2}/{:.2}/{:.2}/{:.2}us", self.rx_latency_p50, self.rx_latency_p99, self.rx_latency_p999, self.rx_latency_max);
+                    }
+                }
                 println!("------------------------");
+                if self.parameter.mode == super::UDPerfMode::Sender && self.parameter.socket_options.tx_timestamps {
+                    println!("Send call to qdisc delay p50/p99/p99.9/max: {:.2}/{:.2}/{:.2}/{:.2}us", self.tx_sched_delay_p50, self.tx_sched_delay_p99, self.tx_sched_delay_p999, self.tx_sched_delay_max);
+                    println!("Qdisc to driver delay p50/p99/p99.9/max: {:.2}/{:.2}/{:.2}/{:.2}us", self.tx_driver_delay_p50, self.tx_driver_delay_p99, self.tx_driver_delay_p999, self.tx_driver_delay_max);
+                    println!("------------------------");
+                }
                 println!("CPU user space: {:.2}%", self.cpu_user_time);
                 println!("CPU system space: {:.2}%", self.cpu_system_time);
                 println!("CPU total: {:.2}%", self.cpu_total_time);
+                println!("Context switches voluntary/involuntary: {}/{}", self.voluntary_context_switches, self.involuntary_context_switches);
+                println!("Page faults minor/major: {}/{}", self.minor_page_faults, self.major_page_faults);
+                println!("Maximum resident set size: {:.2} MiB", self.max_rss as f64 / 1024.0);
+                if !self.cores.is_empty() {
+                    println!("CPU of cores {} user/system/irq/softirq: {:.2}/{:.2}/{:.2}/{:.2}%", self.cores, self.cores_user_time, self.cores_system_time, self.cores_irq_time, self.cores_softirq_time);
+                }
                 println!("Threads used: {}", self.parameter.amount_threads);
+                if self.parameter.sockets_per_thread > 1 {
+                    println!("Sockets per thread: {}", self.parameter.sockets_per_thread);
+                }
+                if self.parameter.flows_per_thread > 1 {
+                    println!("Flows per thread: {} ({:?})", self.parameter.flows_per_thread, self.parameter.flow_mode);
+                }
                 println!("------------------------");
                 println!("Amount of datagrams: {}", self.amount_datagrams);
                 println!("Amount of reordered datagrams: {}", self.amount_reordered_datagrams);
                 println!("Amount of duplicated datagrams: {}", self.amount_duplicated_datagrams);
+                if self.parameter.payload_verification && self.parameter.mode == super::UDPerfMode::Receiver {
+                    println!("Amount of corrupted datagrams: {}", self.amount_corrupted_datagrams);
+                }
                 println!("Amount of omitted datagrams: {}", self.amount_omitted_datagrams);
+                if self.parameter.socket_options.rxq_overflow && self.parameter.mode == super::UDPerfMode::Receiver {
+                    println!("Amount of datagrams dropped by the socket: {} (peak receive memory: {} Bytes)", self.amount_socket_drops, self.socket_rmem_peak);
+                }
                 println!("Amount of syscalls: {}", self.amount_syscalls);
                 println!("Amount of datagrams per syscall: {:.0}", if self.amount_syscalls != 0 { self.amount_datagrams / self.amount_syscalls } else { 0 });
                 println!("Amount of IO model syscalls: {}", self.amount_io_model_calls);
                 println!("Amount of received EAGAIN errors: {}", self.amount_eagain);
                 println!("------------------------");
+                println!("Kernel UDP counters (system-wide)");
+                println!("------------------------");
+                println!("Receive errors: {} (receive buffer full: {}, checksum: {})", self.udp_in_errors, self.udp_rcvbuf_errors, self.udp_in_csum_errors);
+                println!("Send buffer errors: {}", self.udp_sndbuf_errors);
+                println!("Datagrams to ports without socket: {}", self.udp_no_ports);
+                println!("IP reassembly requests/failures: {}/{}", self.ip_reasm_reqds, self.ip_reasm_fails);
+                println!("------------------------");
+                if self.parameter.perf_counters {
+                    println!("Performance counters");
+                    println!("------------------------");
+                    if self.perf_task_clock == 0 && self.perf_cycles == 0 && self.perf_instructions == 0 {
+                        println!("Performance counters unavailable");
+                    } else {
+                        if self.perf_cycles == 0 && self.perf_instructions == 0 {
+                            println!("Hardware counters unavailable, only software counters");
+                        } else {
+                            println!("Cycles/instructions/cache misses: {}/{}/{}", self.perf_cycles, self.perf_instructions, self.perf_cache_misses);
+                            println!("Per datagram cycles/instructions/cache misses: {:.1}/{:.1}/{:.3}", self.cycles_per_datagram, self.instructions_per_datagram, self.cache_misses_per_datagram);
+                            println!("Per syscall cycles/instructions: {:.1}/{:.1}", self.cycles_per_syscall, self.instructions_per_syscall);
+                        }
+                        println!("Task clock: {:.3}s ({:.1}ns per datagram)", self.perf_task_clock as f64 / 1_000_000_000.0, self.task_clock_per_datagram);
+                        println!("Context switches/page faults: {}/{}", self.perf_context_switches, self.perf_page_faults);
+                    }
+                    println!("------------------------");
+                }
+                if self.amount_flows > 1 {
+                    println!("Flows");
+                    println!("------------------------");
+                    println!("Amount of flows: {}", self.amount_flows);
+                    println!("Datagrams per flow min/max: {}/{}", self.flow_datagrams_min, self.flow_datagrams_max);
+                    println!("Packet loss of the worst flow: {:.2}%", self.flow_packet_loss_max);
+                    if !self.flow_owd.is_empty() {
+                        println!("One-way delay per flow p50/p99/p99.9/max (us):");
+                        self.flow_owd.split(';').for_each(|flow_owd| println!("  {}", flow_owd.replacen(':', ": ", 1)));
+                    }
+                    println!("------------------------");
+                }
+                if self.parameter.thread_statistics && self.thread_id.is_none() && self.parameter.amount_threads > 1 {
+                    println!("Threads");
+                    println!("------------------------");
+                    println!("Jain's fairness index: {:.3}", self.thread_fairness_index);
+                    println!("Data rate per thread min/max: {:.2}/{:.2} Gibit/s", self.thread_data_rate_min, self.thread_data_rate_max);
+                    println!("------------------------");
+                }
+                if self.parameter.ping_pong && self.parameter.mode == super::UDPerfMode::Sender {
+                    println!("Round-trip time");
+                    println!("------------------------");
+                    println!("Amount of round trips: {}", self.rtt_histogram.len());
+                    println!("RTT p50: {:.2}us", self.rtt_p50);
+                    println!("RTT p99: {:.2}us", self.rtt_p99);
+                    println!("RTT p99.9: {:.2}us", self.rtt_p999);
+                    println!("RTT max: {:.2}us", self.rtt_max);
+                    println!("------------------------");
+                }
                 if self.parameter.io_model == super::IOModel::IoUring {
                     println!("Io-Uring");
                     println!("------------------------");
@@ -303,7 +772,10 @@ impl Statistic {
                 } else {
                     error!("Failed to create file: {}", output_file.display());
                 }
-            }
+            },
+            OutputFormat::JsonLines => exporter::print_json_line(self, interval_print),
+            OutputFormat::Prometheus => exporter::write_prometheus_file(self, interval_print),
+            OutputFormat::Influx => exporter::print_influx_line(self, interval_print)
         }
     }
 
@@ -327,9 +799,144 @@ impl Statistic {
         ( self.total_data_gbyte / elapsed_time_in_seconds ) * 8.0
     }
     
+    fn calculate_offered_data_rate(&self) -> f64 {
+        if self.test_duration == 0.0 {
+            return 0.0;
+        }
+        ( self.amount_offered_data_bytes as f64 / 1024.0 / 1024.0 / 1024.0 / self.test_duration ) * 8.0
+    }
+
+    fn calculate_perf_costs(&mut self) {
+        let per_datagram = |counter: u64| if self.amount_datagrams == 0 { 0.0 } else { counter as f64 / self.amount_datagrams as f64 };
+        let per_syscall = |counter: u64| if self.amount_syscalls == 0 { 0.0 } else { counter as f64 / self.amount_syscalls as f64 };
+        self.cycles_per_datagram = per_datagram(self.perf_cycles);
+        self.instructions_per_datagram = per_datagram(self.perf_instructions);
+        self.cache_misses_per_datagram = per_datagram(self.perf_cache_misses);
+        self.task_clock_per_datagram = per_datagram(self.perf_task_clock);
+        self.cycles_per_syscall = per_syscall(self.perf_cycles);
+        self.instructions_per_syscall = per_syscall(self.perf_instructions);
+    }
+
     fn calculate_packet_loss(&self) -> f64 {
         (self.amount_omitted_datagrams as f64 / self.amount_datagrams as f64) * 100.0
     }
+
+    // The histogram records nanoseconds, the percentiles are reported in microseconds. Statistics received over the control channel don't contain the histogram, so their percentiles are kept.
+    fn calculate_rtt(&mut self) {
+        if self.rtt_histogram.is_empty() {
+            return;
+        }
+        self.rtt_p50 = self.rtt_histogram.percentile(50.0) as f64 / 1000.0;
+        self.rtt_p99 = self.rtt_histogram.percentile(99.0) as f64 / 1000.0;
+        self.rtt_p999 = self.rtt_histogram.percentile(99.9) as f64 / 1000.0;
+        self.rtt_max = self.rtt_histogram.max() as f64 / 1000.0;
+    }
+
+    // Like the round-trip time, the one-way delay and the jitter are reported in microseconds
+    fn calculate_delay(&mut self) {
+        if !self.jitter_state.is_empty() {
+            self.jitter = self.jitter_state.get() / 1000.0;
+        }
+        if self.owd_histogram.is_empty() {
+            return;
+        }
+        self.owd_p50 = self.owd_histogram.percentile(50.0) as f64 / 1000.0;
+        self.owd_p99 = self.owd_histogram.percentile(99.0) as f64 / 1000.0;
+        self.owd_p999 = self.owd_histogram.percentile(99.9) as f64 / 1000.0;
+        self.owd_max = self.owd_histogram.max() as f64 / 1000.0;
+    }
+
+    // Latency between the software receive timestamp of the kernel and the processing of the datagram in user space. Reported in microseconds.
+    fn calculate_rx_latency(&mut self) {
+        if self.rx_latency_histogram.is_empty() {
+            return;
+        }
+        self.rx_latency_p50 = self.rx_latency_histogram.percentile(50.0) as f64 / 1000.0;
+        self.rx_latency_p99 = self.rx_latency_histogram.percentile(99.0) as f64 / 1000.0;
+        self.rx_latency_p999 = self.rx_latency_histogram.percentile(99.9) as f64 / 1000.0;
+        self.rx_latency_max = self.rx_latency_histogram.max() as f64 / 1000.0;
+    }
+
+    // Delays of the transmit timestamps in microseconds: From the send call until the datagram enters the qdisc, and from the qdisc until it is passed to the driver (includes the pacing of fq)
+    fn calculate_tx_delay(&mut self) {
+        if !self.tx_sched_delay_histogram.is_empty() {
+            self.tx_sched_delay_p50 = self.tx_sched_delay_histogram.percentile(50.0) as f64 / 1000.0;
+            self.tx_sched_delay_p99 = self.tx_sched_delay_histogram.percentile(99.0) as f64 / 1000.0;
+            self.tx_sched_delay_p999 = self.tx_sched_delay_histogram.percentile(99.9) as f64 / 1000.0;
+            self.tx_sched_delay_max = self.tx_sched_delay_histogram.max() as f64 / 1000.0;
+        }
+        if !self.tx_driver_delay_histogram.is_empty() {
+            self.tx_driver_delay_p50 = self.tx_driver_delay_histogram.percentile(50.0) as f64 / 1000.0;
+            self.tx_driver_delay_p99 = self.tx_driver_delay_histogram.percentile(99.0) as f64 / 1000.0;
+            self.tx_driver_delay_p999 = self.tx_driver_delay_histogram.percentile(99.9) as f64 / 1000.0;
+            self.tx_driver_delay_max = self.tx_driver_delay_histogram.max() as f64 / 1000.0;
+        }
+    }
+
+    // Summary of the flows (test IDs) of a receiver thread over the whole measurement. Flows without any datagram are not counted.
+    pub fn set_flow_statistics(&mut self, measurements: &HashMap<usize, Measurement>) {
+        let mut test_ids: Vec<usize> = measurements.iter().filter(|(_, measurement)| measurement.first_packet_received).map(|(test_id, _)| *test_id).collect();
+        test_ids.sort_unstable();
+
+        for test_id in test_ids.iter() {
+            let measurement = &measurements[test_id];
+            let datagrams = measurement.flow_datagrams + measurement.amount_datagrams;
+            let omitted_datagrams = measurement.flow_omitted_datagrams + measurement.amount_omitted_datagrams;
+            let packet_loss = if datagrams == 0 { 0.0 } else { omitted_datagrams as f64 / (datagrams as i64 + omitted_datagrams) as f64 * 100.0 };
+            debug!("Flow {}: {} datagrams, {} omitted datagrams", test_id, datagrams, omitted_datagrams);
+
+            self.flow_datagrams_min = if self.amount_flows == 0 { datagrams } else { u64::min(self.flow_datagrams_min, datagrams) };
+            self.flow_datagrams_max = u64::max(self.flow_datagrams_max, datagrams);
+            self.flow_packet_loss_max = f64::max(self.flow_packet_loss_max, packet_loss);
+            self.amount_flows += 1;
+
+            let mut owd_histogram = measurement.flow_owd_histogram.clone();
+            owd_histogram.merge(&measurement.owd_histogram);
+            if !owd_histogram.is_empty() {
+                let flow_owd = format!("{}:{:.2}/{:.2}/{:.2}/{:.2}", test_id, owd_histogram.percentile(50.0) as f64 / 1000.0, owd_histogram.percentile(99.0) as f64 / 1000.0, owd_histogram.percentile(99.9) as f64 / 1000.0, owd_histogram.max() as f64 / 1000.0);
+                self.flow_owd = if self.flow_owd.is_empty() { flow_owd } else { format!("{};{}", self.flow_owd, flow_owd) };
+            }
+        }
+        self.test_ids = format_id_ranges(test_ids.into_iter().map(|test_id| test_id as u64));
+    }
+
+    // The counters are system-wide deltas since the last snapshot
+    pub fn set_udp_counters(&mut self, counters: UdpCounters) {
+        self.udp_in_errors = counters.in_errors;
+        self.udp_rcvbuf_errors = counters.rcvbuf_errors;
+        self.udp_sndbuf_errors = counters.sndbuf_errors;
+        self.udp_no_ports = counters.no_ports;
+        self.udp_in_csum_errors = counters.in_csum_errors;
+        self.ip_reasm_reqds = counters.reasm_reqds;
+        self.ip_reasm_fails = counters.reasm_fails;
+    }
+
+    pub fn set_cpu_usage(&mut self, cpu_usage: CpuUsage) {
+        self.cpu_user_time = cpu_usage.user;
+        self.cpu_system_time = cpu_usage.system;
+        self.cpu_total_time = cpu_usage.total;
+        self.voluntary_context_switches = cpu_usage.voluntary_context_switches;
+        self.involuntary_context_switches = cpu_usage.involuntary_context_switches;
+        self.minor_page_faults = cpu_usage.minor_page_faults;
+        self.major_page_faults = cpu_usage.major_page_faults;
+        self.max_rss = cpu_usage.max_rss;
+    }
+
+    pub fn set_perf_counters(&mut self, values: PerfValues) {
+        self.perf_cycles = values.cycles;
+        self.perf_instructions = values.instructions;
+        self.perf_cache_misses = values.cache_misses;
+        self.perf_task_clock = values.task_clock;
+        self.perf_context_switches = values.context_switches;
+        self.perf_page_faults = values.page_faults;
+    }
+
+    // Starts the statistic of the next interval. The jitter is a running estimate over the whole measurement (like in iperf3), so its state is carried over.
+    pub fn next_interval(&self) -> Statistic {
+        let mut statistic = Statistic::new(self.parameter.clone());
+        statistic.jitter_state = self.jitter_state;
+        statistic
+    }
     
     pub fn set_test_duration(&mut self, start_time: Option<f64>, end_time: Option<f64>) {
         self.start_timestamp  = if let Some(time) = start_time { time } else { self.start_timestamp };
@@ -339,6 +946,19 @@ impl Statistic {
 }
 
 
+// Ascending IDs (e.g. test IDs or core IDs) as compact ranges, e.g. "0-3;8"
+pub fn format_id_ranges(ids: impl Iterator<Item = u64>) -> String {
+    let mut ranges: Vec<(u64, u64)> = Vec::new();
+    for id in ids {
+        match ranges.last_mut() {
+            Some((_, last)) if *last + 1 == id => *last = id,
+            _ => ranges.push((id, id)),
+        }
+    }
+    ranges.iter().map(|(first, last)| if first == last { first.to_string() } else { format!("{}-{}", first, last) }).collect::<Vec<_>>().join(";")
+}
+
+
 impl Add for Statistic {
     type Output = Self;
 
@@ -390,6 +1010,26 @@ impl Add for Statistic {
                 (None, None, None)
             };
 
+        let mut rtt_histogram = self.rtt_histogram;
+        rtt_histogram.merge(&other.rtt_histogram);
+        let mut owd_histogram = self.owd_histogram;
+        owd_histogram.merge(&other.owd_histogram);
+        let mut rx_latency_histogram = self.rx_latency_histogram;
+        rx_latency_histogram.merge(&other.rx_latency_histogram);
+        let mut tx_sched_delay_histogram = self.tx_sched_delay_histogram;
+        tx_sched_delay_histogram.merge(&other.tx_sched_delay_histogram);
+        let mut tx_driver_delay_histogram = self.tx_driver_delay_histogram;
+        tx_driver_delay_histogram.merge(&other.tx_driver_delay_histogram);
+
+        // Check if one is zero, to avoid division by zero
+        let jitter = if self.jitter == 0.0 {
+            other.jitter
+        } else if other.jitter == 0.0 {
+            self.jitter
+        } else {
+            ( self.jitter + other.jitter ) / 2.0 // Average of jitter
+        };
+
         Statistic {
             parameter: self.parameter, // Assumption is that both statistics have the same test parameters
             start_timestamp: f64::min(self.start_timestamp, other.start_timestamp),
@@ -401,15 +1041,95 @@ impl Add for Statistic {
             amount_data_bytes: self.amount_data_bytes + other.amount_data_bytes,
             amount_reordered_datagrams: self.amount_reordered_datagrams + other.amount_reordered_datagrams,
             amount_duplicated_datagrams: self.amount_duplicated_datagrams + other.amount_duplicated_datagrams,
+            amount_corrupted_datagrams: self.amount_corrupted_datagrams + other.amount_corrupted_datagrams,
             amount_omitted_datagrams: self.amount_omitted_datagrams + other.amount_omitted_datagrams,
+            amount_socket_drops: self.amount_socket_drops + other.amount_socket_drops,
+            socket_rmem_peak: u64::max(self.socket_rmem_peak, other.socket_rmem_peak),
             amount_syscalls: self.amount_syscalls + other.amount_syscalls,
             amount_io_model_calls: self.amount_io_model_calls + other.amount_io_model_calls,
             amount_eagain: self.amount_eagain + other.amount_eagain,
             data_rate_gbit, 
+            amount_offered_data_bytes: self.amount_offered_data_bytes + other.amount_offered_data_bytes,
+            offered_data_rate_gbit: 0.0, // Calculated from the offered data and the test duration
             packet_loss,
-            cpu_user_time: 0.0,
-            cpu_system_time: 0.0,
-            cpu_total_time: 0.0,
+            // The CPU utilization of multiple threads adds up, like the utilization of the process
+            cpu_user_time: self.cpu_user_time + other.cpu_user_time,
+            cpu_system_time: self.cpu_system_time + other.cpu_system_time,
+            cpu_total_time: self.cpu_total_time + other.cpu_total_time,
+            voluntary_context_switches: self.voluntary_context_switches + other.voluntary_context_switches,
+            involuntary_context_switches: self.involuntary_context_switches + other.involuntary_context_switches,
+            minor_page_faults: self.minor_page_faults + other.minor_page_faults,
+            major_page_faults: self.major_page_faults + other.major_page_faults,
+            max_rss: u64::max(self.max_rss, other.max_rss),
+            // The cores are measured for the whole process, not per thread
+            cores: if self.cores.is_empty() { other.cores } else { self.cores },
+            cores_user_time: f64::max(self.cores_user_time, other.cores_user_time),
+            cores_system_time: f64::max(self.cores_system_time, other.cores_system_time),
+            cores_irq_time: f64::max(self.cores_irq_time, other.cores_irq_time),
+            cores_softirq_time: f64::max(self.cores_softirq_time, other.cores_softirq_time),
+            // The kernel counters are system-wide, not per thread
+            udp_in_errors: u64::max(self.udp_in_errors, other.udp_in_errors),
+            udp_rcvbuf_errors: u64::max(self.udp_rcvbuf_errors, other.udp_rcvbuf_errors),
+            udp_sndbuf_errors: u64::max(self.udp_sndbuf_errors, other.udp_sndbuf_errors),
+            udp_no_ports: u64::max(self.udp_no_ports, other.udp_no_ports),
+            udp_in_csum_errors: u64::max(self.udp_in_csum_errors, other.udp_in_csum_errors),
+            ip_reasm_reqds: u64::max(self.ip_reasm_reqds, other.ip_reasm_reqds),
+            ip_reasm_fails: u64::max(self.ip_reasm_fails, other.ip_reasm_fails),
+            perf_cycles: self.perf_cycles + other.perf_cycles,
+            perf_instructions: self.perf_instructions + other.perf_instructions,
+            perf_cache_misses: self.perf_cache_misses + other.perf_cache_misses,
+            perf_task_clock: self.perf_task_clock + other.perf_task_clock,
+            perf_context_switches: self.perf_context_switches + other.perf_context_switches,
+            perf_page_faults: self.perf_page_faults + other.perf_page_faults,
+            // Calculated from the counters and the amount of datagrams and syscalls
+            cycles_per_datagram: 0.0,
+            instructions_per_datagram: 0.0,
+            cache_misses_per_datagram: 0.0,
+            task_clock_per_datagram: 0.0,
+            cycles_per_syscall: 0.0,
+            instructions_per_syscall: 0.0,
+            rtt_p50: f64::max(self.rtt_p50, other.rtt_p50),
+            rtt_p99: f64::max(self.rtt_p99, other.rtt_p99),
+            rtt_p999: f64::max(self.rtt_p999, other.rtt_p999),
+            rtt_max: f64::max(self.rtt_max, other.rtt_max),
+            rtt_histogram,
+            jitter,
+            owd_p50: f64::max(self.owd_p50, other.owd_p50),
+            owd_p99: f64::max(self.owd_p99, other.owd_p99),
+            owd_p999: f64::max(self.owd_p999, other.owd_p999),
+            owd_max: f64::max(self.owd_max, other.owd_max),
+            jitter_state: self.jitter_state.merge(&other.jitter_state),
+            owd_histogram,
+            rx_latency_p50: f64::max(self.rx_latency_p50, other.rx_latency_p50),
+            rx_latency_p99: f64::max(self.rx_latency_p99, other.rx_latency_p99),
+            rx_latency_p999: f64::max(self.rx_latency_p999, other.rx_latency_p999),
+            rx_latency_max: f64::max(self.rx_latency_max, other.rx_latency_max),
+            rx_latency_histogram,
+            tx_sched_delay_p50: f64::max(self.tx_sched_delay_p50, other.tx_sched_delay_p50),
+            tx_sched_delay_p99: f64::max(self.tx_sched_delay_p99, other.tx_sched_delay_p99),
+            tx_sched_delay_p999: f64::max(self.tx_sched_delay_p999, other.tx_sched_delay_p999),
+            tx_sched_delay_max: f64::max(self.tx_sched_delay_max, other.tx_sched_delay_max),
+            tx_sched_delay_histogram,
+            tx_driver_delay_p50: f64::max(self.tx_driver_delay_p50, other.tx_driver_delay_p50),
+            tx_driver_delay_p99: f64::max(self.tx_driver_delay_p99, other.tx_driver_delay_p99),
+            tx_driver_delay_p999: f64::max(self.tx_driver_delay_p999, other.tx_driver_delay_p999),
+            tx_driver_delay_max: f64::max(self.tx_driver_delay_max, other.tx_driver_delay_max),
+            tx_driver_delay_histogram,
+            amount_flows: self.amount_flows + other.amount_flows,
+            flow_datagrams_min: if self.amount_flows == 0 { other.flow_datagrams_min } else if other.amount_flows == 0 { self.flow_datagrams_min } else { u64::min(self.flow_datagrams_min, other.flow_datagrams_min) },
+            flow_datagrams_max: u64::max(self.flow_datagrams_max, other.flow_datagrams_max),
+            flow_packet_loss_max: f64::max(self.flow_packet_loss_max, other.flow_packet_loss_max),
+            // The threads receive different flows
+            flow_owd: [self.flow_owd, other.flow_owd].into_iter().filter(|flow_owd| !flow_owd.is_empty()).collect::<Vec<_>>().join(";"),
+            // The sum of multiple threads doesn't belong to a single thread
+            thread_id: None,
+            core_id: None,
+            port: None,
+            test_ids: String::new(),
+            thread_fairness_index: f64::max(self.thread_fairness_index, other.thread_fairness_index),
+            // Statistics without threads (e.g. an empty interval) have no minimum data rate
+            thread_data_rate_min: if self.thread_data_rate_min == 0.0 { other.thread_data_rate_min } else if other.thread_data_rate_min == 0.0 { self.thread_data_rate_min } else { f64::min(self.thread_data_rate_min, other.thread_data_rate_min) },
+            thread_data_rate_max: f64::max(self.thread_data_rate_max, other.thread_data_rate_max),
             uring_cq_overflows: self.uring_cq_overflows + other.uring_cq_overflows,
             uring_out_of_buffers: self.uring_out_of_buffers + other.uring_out_of_buffers,
             uring_copied_zc: self.uring_copied_zc + other.uring_copied_zc,
@@ -422,37 +1142,184 @@ impl Add for Statistic {
 }
 
 
+// Interarrival jitter estimate as defined in RFC 3550 (Section 6.4.1 and A.8) in nanoseconds.
+// Merging the jitter of multiple streams (test IDs) results in the average jitter of the streams.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Jitter {
+    last_transit: Option<i64>,
+    jitter: f64,
+    amount_streams: u32,
+}
+
+impl Jitter {
+    pub fn update(&mut self, send_timestamp: u64, receive_timestamp: u64) {
+        let transit = receive_timestamp.wrapping_sub(send_timestamp) as i64;
+        if let Some(last_transit) = self.last_transit {
+            let difference = transit.wrapping_sub(last_transit).unsigned_abs() as f64;
+            self.jitter += (difference - self.jitter) / 16.0;
+        } else {
+            self.amount_streams = 1;
+        }
+        self.last_transit = Some(transit);
+    }
+
+    pub fn merge(&self, other: &Jitter) -> Jitter {
+        if other.is_empty() {
+            return *self;
+        }
+        if self.is_empty() {
+            return *other;
+        }
+
+        let amount_streams = self.amount_streams + other.amount_streams;
+        Jitter {
+            last_transit: self.last_transit,
+            jitter: (self.jitter * self.amount_streams as f64 + other.jitter * other.amount_streams as f64) / amount_streams as f64,
+            amount_streams,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.amount_streams == 0
+    }
+
+    pub fn get(&self) -> f64 {
+        self.jitter
+    }
+}
+
+
 impl Measurement {
-    pub fn new(parameter: Parameter) -> Measurement {
+    pub fn new() -> Measurement {
         Measurement {
-            start_time: Statistic::get_unix_timestamp(),
-            end_time: Statistic::get_unix_timestamp(),
-            statistic: Statistic::new(parameter),
             first_packet_received: false,
             last_packet_received: false,
+            next_packet_id: 0,
+            amount_datagrams: 0,
+            amount_data_bytes: 0,
+            amount_reordered_datagrams: 0,
+            amount_duplicated_datagrams: 0,
+            amount_corrupted_datagrams: 0,
+            amount_omitted_datagrams: 0,
+            owd_histogram: Histogram::new(),
+            rx_latency_histogram: Histogram::new(),
+            jitter_state: Jitter::default(),
+            flow_datagrams: 0,
+            flow_omitted_datagrams: 0,
+            flow_owd_histogram: Histogram::new(),
+        }
+    }
+
+    // Returns the measurement of the flow (test ID) and creates it, if no datagram of the flow was received before
+    // The measurements are keyed by the test ID, since a thread only receives a few flows out of the whole range of test IDs
+    pub fn get(measurements: &mut HashMap<usize, Measurement>, test_id: usize) -> Result<&mut Measurement, crate::UdperfError> {
+        if test_id >= crate::MAX_TEST_ID {
+            error!("Received test id is greater than the maximum test id: {} > {}!", test_id, crate::MAX_TEST_ID);
+            return Err(crate::UdperfError::Other("Received test id is greater than the maximum test id"))
+        }
+        Ok(measurements.entry(test_id).or_insert_with(Measurement::new))
+    }
+
+    // Start measurement with receiving of the first MEASUREMENT message
+    pub fn start(&mut self, test_id: usize) {
+        if !self.first_packet_received {
+            info!("{:?}: First packet received from test {}!", thread::current().id(), test_id);
+            self.first_packet_received = true;
+        }
+    }
+
+    pub fn finish(&mut self, test_id: usize) {
+        info!("{:?}: LAST packet received from test {}!", thread::current().id(), test_id);
+        if !self.first_packet_received {
+            warn!("{:?}: LAST packet received from test {} without any MEASUREMENT packet before!", thread::current().id(), test_id);
+            return;
         }
+        self.last_packet_received = true;
+    }
+
+    // Adds the counters of the current interval of all flows to the statistic of the thread
+    pub fn add_to_statistic<'a>(measurements: impl Iterator<Item = &'a Measurement>, mut statistic: Statistic) -> Statistic {
+        for measurement in measurements {
+            statistic.amount_datagrams += measurement.amount_datagrams;
+            statistic.amount_data_bytes += measurement.amount_data_bytes;
+            statistic.amount_reordered_datagrams += measurement.amount_reordered_datagrams;
+            statistic.amount_duplicated_datagrams += measurement.amount_duplicated_datagrams;
+            statistic.amount_corrupted_datagrams += measurement.amount_corrupted_datagrams;
+            statistic.amount_omitted_datagrams += measurement.amount_omitted_datagrams;
+            statistic.owd_histogram.merge(&measurement.owd_histogram);
+            statistic.rx_latency_histogram.merge(&measurement.rx_latency_histogram);
+            statistic.jitter_state = statistic.jitter_state.merge(&measurement.jitter_state);
+        }
+        statistic
+    }
+
+    pub fn next_interval(&mut self) {
+        self.flow_datagrams += self.amount_datagrams;
+        self.flow_omitted_datagrams += self.amount_omitted_datagrams;
+        self.flow_owd_histogram.merge(&self.owd_histogram);
+        self.amount_datagrams = 0;
+        self.amount_data_bytes = 0;
+        self.amount_reordered_datagrams = 0;
+        self.amount_duplicated_datagrams = 0;
+        self.amount_corrupted_datagrams = 0;
+        self.amount_omitted_datagrams = 0;
+        self.owd_histogram = Histogram::new();
+        self.rx_latency_histogram = Histogram::new();
     }
 }
 
-#[derive(Debug, Serialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Parameter {
     pub test_name: String,
     pub run_name: String,
     pub repetition_id: u16,
     pub mode: super::UDPerfMode,
-    pub ip: std::net::Ipv4Addr,
+    pub ip: std::net::IpAddr,
+    #[serde(skip_serializing, default)]
+    pub port: u16,
+    #[serde(skip_serializing, default)]
+    pub sender_port: u16,
     pub amount_threads: u16,
-    #[serde(skip_serializing)]
+    pub sockets_per_thread: u16,
+    pub flows_per_thread: u16,
+    pub flow_mode: FlowMode,
+    #[serde(skip_serializing, default)]
     pub output_interval: f64,
-    #[serde(skip_serializing)]
+    #[serde(skip_serializing, default)]
     pub output_format: OutputFormat,
-    #[serde(skip_serializing)]
+    #[serde(skip_serializing, default)]
     pub output_file_path: path::PathBuf,
+    // Output the statistic of every thread besides the total statistic
+    pub thread_statistics: bool,
+    // Measure every thread with performance counters (perf_event_open)
+    pub perf_counters: bool,
     pub io_model: super::IOModel,
+    pub epoll_edge_triggered: bool,
+    pub epoll_exclusive: bool,
     pub test_runtime_length: u64,
     pub mss: u32,
+    // With a size distribution, the datagram size is the maximum size of the distribution
     pub datagram_size: u32,
+    pub datagram_size_distribution: DatagramSizeDistribution,
+    pub datagram_size_min: u32,
+    #[serde(with = "super::datagram_size::weighted_sizes")]
+    pub datagram_sizes: Vec<WeightedSize>,
+    pub payload_mode: PayloadMode,
+    pub payload_seed: u64,
+    pub payload_file_path: Option<path::PathBuf>,
+    // The receiver compares the payload of every datagram with the expected payload
+    pub payload_verification: bool,
     pub packet_buffer_size: usize,
+    // Rate of the userspace pacer in total (not per thread) in bytes/s or datagrams/s. The kernel pacing rate is set in the socket options.
+    pub userspace_pacing: bool,
+    pub pacing_rate: u64,
+    pub pacing_rate_pps: u64,
+    pub traffic_pattern: TrafficPattern,
+    // On/off: Length of the bursts and the pauses in milliseconds
+    pub burst_length: u64,
+    pub burst_pause: u64,
+    #[serde(with = "super::pacer::ramp_schedule")]
+    pub ramp_schedule: Vec<RampStep>,
     #[serde(flatten)]
     pub socket_options: SocketOptions,
     pub exchange_function: super::ExchangeFunction,
@@ -461,64 +1328,126 @@ pub struct Parameter {
     pub simulate_connection: SimulateConnection,
     pub core_affinity: bool,
     pub numa_affinity: bool,
+    pub control_channel: bool,
+    pub reverse: bool,
+    pub bidirectional: bool,
+    pub ping_pong: bool,
+    pub one_way_delay: bool,
     #[serde(flatten)]
     pub uring_parameter: UringParameter,
 }
 
 impl Parameter {
-    #[allow(clippy::too_many_arguments)]
-    pub fn new(
-        test_name: String,
-        run_name: String,
-        repetition_id: u16,
-        mode: super::UDPerfMode, 
-        ip: std::net::Ipv4Addr, 
-        amount_threads: u16, 
-        output_interval: f64,
-        output_format: OutputFormat, 
-        output_file_path: path::PathBuf,
-        io_model: super::IOModel, 
-        test_runtime_length: u64, 
-        mss: u32, 
-        datagram_size: u32, 
-        packet_buffer_size: usize, 
-        socket_options: SocketOptions, 
-        exchange_function: super::ExchangeFunction, 
-        multiplex_port: MultiplexPort, 
-        multiplex_port_receiver: MultiplexPort, 
-        simulate_connection: SimulateConnection, 
-        core_affinity: bool, 
-        numa_affinity: bool, 
-        uring_parameter: UringParameter
-    ) -> Parameter {
-        Parameter {
-            test_name,
-            run_name,
-            repetition_id,
-            mode,
-            ip,
-            amount_threads,
-            output_interval,
-            output_format,
-            output_file_path,
-            io_model,
-            test_runtime_length,
-            mss,
-            datagram_size,
-            packet_buffer_size,
-            socket_options,
-            exchange_function,
-            multiplex_port,
-            multiplex_port_receiver,
-            simulate_connection,
-            core_affinity,
-            numa_affinity,
-            uring_parameter
+    pub fn builder(mode: super::UDPerfMode) -> ParameterBuilder {
+        ParameterBuilder::new(mode)
+    }
+
+    // The offered rate is only known by the sender, if the pacer enforces a rate. On/off without bandwidth sends the bursts as fast as possible.
+    pub fn has_offered_rate(&self) -> bool {
+        self.mode == super::UDPerfMode::Sender && self.userspace_pacing && (self.pacing_rate > 0 || self.pacing_rate_pps > 0 || self.traffic_pattern == TrafficPattern::Ramp)
+    }
+
+    // Without the control channel, the sender waits before the first datagram (for the receiver to be ready) and before the LAST message (for the buffers to drain). The receiver removes this time from the measurement.
+    // With the control channel, the start message already guarantees that the receiver sockets are bound and a lost LAST message is covered by the stop message.
+    pub fn control_message_delay(&self) -> u64 {
+        if self.control_channel { 0 } else { crate::WAIT_CONTROL_MESSAGE }
+    }
+
+    // Every flow of a thread uses its own test ID
+    pub fn amount_flows(&self) -> u16 {
+        self.sockets_per_thread * self.flows_per_thread
+    }
+
+    // Used by the receiver to take over the test parameters of the sender, which were received over the control channel
+    pub fn update_from_remote(&mut self, remote: &Parameter) {
+        info!("Taking over parameters from sender: threads {}, time {}s, interval {}s, datagram size {}", remote.amount_threads, remote.test_runtime_length, remote.output_interval, remote.datagram_size);
+        self.amount_threads = remote.amount_threads;
+        self.sockets_per_thread = remote.sockets_per_thread;
+        self.flows_per_thread = remote.flows_per_thread;
+        self.flow_mode = remote.flow_mode;
+        self.test_runtime_length = remote.test_runtime_length;
+        self.output_interval = remote.output_interval;
+        self.datagram_size = remote.datagram_size;
+        self.datagram_size_distribution = remote.datagram_size_distribution;
+        self.datagram_size_min = remote.datagram_size_min;
+        self.datagram_sizes = remote.datagram_sizes.clone();
+        self.payload_mode = remote.payload_mode;
+        self.payload_seed = remote.payload_seed;
+        self.payload_file_path = remote.payload_file_path.clone();
+        if self.datagram_size_distribution != DatagramSizeDistribution::Fixed {
+            // Every msghdr carries a single datagram of at most the maximum size of the distribution
+            self.mss = remote.mss;
+        }
+        self.multiplex_port = remote.multiplex_port;
+        self.multiplex_port_receiver = remote.multiplex_port_receiver;
+        self.simulate_connection = remote.simulate_connection;
+        self.socket_options.reuseport = self.multiplex_port_receiver == MultiplexPort::Sharding;
+        // The transmit timestamps are needed, if this host sends in reverse or bidirectional mode
+        self.one_way_delay = remote.one_way_delay;
+
+        if self.sockets_per_thread > 1 && self.io_model != super::IOModel::Epoll {
+            // Only epoll can drive multiple sockets with a single thread
+            info!("Sender uses {} sockets per thread, switching to IO model {:?}", self.sockets_per_thread, super::IOModel::Epoll);
+            self.io_model = super::IOModel::Epoll;
+        }
+
+        if remote.bidirectional {
+            // The pacing rate is used by the sender threads of the reverse direction
+            info!("Bidirectional mode requested by the sender");
+            self.bidirectional = true;
+            self.socket_options.socket_pacing_rate = remote.socket_options.socket_pacing_rate;
+            self.take_over_userspace_pacing(remote);
+        }
+
+        if remote.ping_pong {
+            // Every datagram is echoed on its own, so it must not be coalesced by GRO
+            info!("Ping-pong mode requested by the sender");
+            self.ping_pong = true;
+            self.socket_options.gro = false;
+        }
+
+        if remote.reverse {
+            // The receiver becomes the sender and uses the sender socket options (GSO, pacing, fragmentation) of the remote host
+            info!("Reverse mode requested by the sender, switching to mode {:?}", super::UDPerfMode::Sender);
+            self.reverse = true;
+            self.mode = super::UDPerfMode::Sender;
+            self.mss = remote.mss;
+            let ipv6 = self.socket_options.ipv6;
+            self.socket_options = remote.socket_options;
+            self.socket_options.ipv6 = ipv6;
+            self.take_over_userspace_pacing(remote);
+        }
+    }
+
+    fn take_over_userspace_pacing(&mut self, remote: &Parameter) {
+        self.userspace_pacing = remote.userspace_pacing;
+        self.pacing_rate = remote.pacing_rate;
+        self.pacing_rate_pps = remote.pacing_rate_pps;
+        self.traffic_pattern = remote.traffic_pattern;
+        self.burst_length = remote.burst_length;
+        self.burst_pause = remote.burst_pause;
+        self.ramp_schedule = remote.ramp_schedule.clone();
+    }
+
+    // Parameter of the opposite direction in bidirectional mode. The threads of the opposite direction run like in reverse mode.
+    pub fn reversed(&self) -> Parameter {
+        let mut parameter = self.clone();
+        parameter.reverse = true;
+        match self.mode {
+            super::UDPerfMode::Sender => {
+                parameter.mode = super::UDPerfMode::Receiver;
+                parameter.socket_options.convert_to_receiver();
+            },
+            super::UDPerfMode::Receiver => {
+                parameter.mode = super::UDPerfMode::Sender;
+                parameter.socket_options.convert_to_sender(self.datagram_size);
+            }
         }
+        parameter
     }
 }
 
-#[derive(Debug, Serialize, Copy, Clone)]
+#[derive(Debug, Serialize, Deserialize, Copy, Clone)]
 pub struct UringParameter {
     pub uring_mode: UringMode,
     pub ring_size: u32,
@@ -580,7 +1509,7 @@ pub mod utilization {
 
 
 pub mod serialize_option_as_bool {
-    use serde::Serializer;
+    use serde::{Deserialize, Deserializer, Serializer};
 
     // This function will be used to serialize Option<u32> fields
     pub fn serialize<S>(option: &Option<u32>, serializer: S) -> Result<S::Ok, S::Error>
@@ -592,4 +1521,23 @@ pub mod serialize_option_as_bool {
             None => serializer.serialize_bool(false),
         }
     }
+
+    // Inverse of serialize(): A boolean (false) is read back as None
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<u32>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum OptionAsBool {
+            Value(u32),
+            #[allow(dead_code)]
+            Disabled(bool),
+        }
+
+        match OptionAsBool::deserialize(deserializer)? {
+            OptionAsBool::Value(value) => Ok(Some(value)),
+            OptionAsBool::Disabled(_) => Ok(None),
+        }
+    }
 }
\ No newline at end of file
@@ -0,0 +1,94 @@
+use log::{debug, trace};
+
+use crate::net::socket::Socket;
+use crate::UdperfError;
+use super::statistic::Statistic;
+
+// Constants from linux/errqueue.h and linux/net_tstamp.h, which are not exported by libc
+pub const SO_EE_ORIGIN_TIMESTAMPING: u8 = 4;
+const SCM_TSTAMP_SND: u32 = 0;
+const SCM_TSTAMP_SCHED: u32 = 1;
+
+// Amount of send calls, whose timestamps are kept until the transmit timestamps of the kernel arrive. Older timestamps are overwritten.
+const AMOUNT_PENDING_SEND_CALLS: usize = 4096;
+// A message of the error queue contains SCM_TIMESTAMPING (3x timespec) and IP_RECVERR/IPV6_RECVERR (sock_extended_err and the offender address)
+const LENGTH_ERRQUEUE_CONTROL_MESSAGE_BUFFER: usize = 64 + 64;
+
+// Transmit timestamps of the kernel are read from the error queue of the socket.
+// Every send call gets a key by the kernel (SOF_TIMESTAMPING_OPT_ID), which is counted up for every datagram (or GSO buffer) sent. The key matches the transmit timestamps to the time of the send call.
+pub struct TxTimestamps {
+    next_key: u32,
+    send_call_timestamps: Vec<u64>,
+    sched_timestamps: Vec<u64>,
+    msghdr: libc::msghdr,
+    _control_buffer: Box<[u8; LENGTH_ERRQUEUE_CONTROL_MESSAGE_BUFFER]>,
+}
+
+impl TxTimestamps {
+    pub fn new() -> TxTimestamps {
+        let mut control_buffer = Box::new([0_u8; LENGTH_ERRQUEUE_CONTROL_MESSAGE_BUFFER]);
+        // With SOF_TIMESTAMPING_OPT_TSONLY the error queue doesn't contain the payload, so no iovec is needed
+        let mut msghdr = unsafe { std::mem::zeroed::<libc::msghdr>() };
+        msghdr.msg_control = control_buffer.as_mut_ptr() as *mut libc::c_void;
+        msghdr.msg_controllen = LENGTH_ERRQUEUE_CONTROL_MESSAGE_BUFFER;
+
+        TxTimestamps {
+            next_key: 0,
+            send_call_timestamps: vec![0; AMOUNT_PENDING_SEND_CALLS],
+            sched_timestamps: vec![0; AMOUNT_PENDING_SEND_CALLS],
+            msghdr,
+            _control_buffer: control_buffer,
+        }
+    }
+
+    // Has to be called for every message sent on the socket (including the INIT and LAST messages), since the kernel counts the key up for every message
+    pub fn record_send(&mut self, amount_messages: usize, timestamp: u64) {
+        for _ in 0..amount_messages {
+            let index = self.next_key as usize % AMOUNT_PENDING_SEND_CALLS;
+            self.send_call_timestamps[index] = timestamp;
+            self.sched_timestamps[index] = 0;
+            self.next_key = self.next_key.wrapping_add(1);
+        }
+    }
+
+    // Reads all transmit timestamps of the error queue and records the delay between the send call and the qdisc (TX_SCHED), and between the qdisc and the driver (TX_SOFTWARE)
+    pub fn drain(&mut self, socket: &Socket, statistic: &mut Statistic) -> Result<(), UdperfError> {
+        loop {
+            self.msghdr.msg_controllen = LENGTH_ERRQUEUE_CONTROL_MESSAGE_BUFFER;
+            self.msghdr.msg_flags = 0;
+
+            match socket.recv_errqueue(&mut self.msghdr) {
+                Ok(_) => {
+                    let control_messages = super::parse_cmsg(&mut self.msghdr);
+                    if let (Some(timestamp), Some((timestamp_type, key))) = (control_messages.timestamp, control_messages.tx_timestamp_id) {
+                        self.process(timestamp_type, key, timestamp, statistic);
+                    }
+                },
+                Err(UdperfError::WouldBlock) => return Ok(()),
+                Err(x) => return Err(x)
+            }
+        }
+    }
+
+    fn process(&mut self, timestamp_type: u32, key: u32, timestamp: u64, statistic: &mut Statistic) {
+        // The timestamp of the send call is already overwritten or the key wasn't recorded
+        if self.next_key.wrapping_sub(key) as usize > AMOUNT_PENDING_SEND_CALLS || key == self.next_key {
+            debug!("Transmit timestamp with key {} is too old or unknown. Next key: {}", key, self.next_key);
+            return;
+        }
+
+        let index = key as usize % AMOUNT_PENDING_SEND_CALLS;
+        match timestamp_type {
+            SCM_TSTAMP_SCHED => {
+                trace!("Key {}: Send call to qdisc {}ns", key, timestamp.saturating_sub(self.send_call_timestamps[index]));
+                statistic.tx_sched_delay_histogram.record(timestamp.saturating_sub(self.send_call_timestamps[index]));
+                self.sched_timestamps[index] = timestamp;
+            },
+            SCM_TSTAMP_SND if self.sched_timestamps[index] != 0 => {
+                trace!("Key {}: Qdisc to driver {}ns", key, timestamp.saturating_sub(self.sched_timestamps[index]));
+                statistic.tx_driver_delay_histogram.record(timestamp.saturating_sub(self.sched_timestamps[index]));
+            },
+            _ => debug!("Transmit timestamp of type {} with key {} is ignored", timestamp_type, key)
+        }
+    }
+}
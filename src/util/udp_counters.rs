@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+use std::fs;
+use log::warn;
+
+// System-wide counters of the UDP and IP layer of the kernel. Unlike the packet loss of the measurement, they tell where datagrams were dropped on this host.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UdpCounters {
+    pub in_errors: u64,
+    // Datagrams dropped because the receive buffer of the socket was full
+    pub rcvbuf_errors: u64,
+    pub sndbuf_errors: u64,
+    // Datagrams to a port without a socket
+    pub no_ports: u64,
+    pub in_csum_errors: u64,
+    pub reasm_reqds: u64,
+    pub reasm_fails: u64
+}
+
+impl UdpCounters {
+    fn delta(&self, previous: &UdpCounters) -> UdpCounters {
+        UdpCounters {
+            in_errors: self.in_errors.saturating_sub(previous.in_errors),
+            rcvbuf_errors: self.rcvbuf_errors.saturating_sub(previous.rcvbuf_errors),
+            sndbuf_errors: self.sndbuf_errors.saturating_sub(previous.sndbuf_errors),
+            no_ports: self.no_ports.saturating_sub(previous.no_ports),
+            in_csum_errors: self.in_csum_errors.saturating_sub(previous.in_csum_errors),
+            reasm_reqds: self.reasm_reqds.saturating_sub(previous.reasm_reqds),
+            reasm_fails: self.reasm_fails.saturating_sub(previous.reasm_fails)
+        }
+    }
+}
+
+// Snapshots of the counters from /proc/net/snmp (IPv4) or /proc/net/snmp6 (IPv6).
+// The drops of the single sockets (drops column of /proc/net/udp) are read by the receiver over sock_diag instead (--with-socket-drops), which doesn't have to parse the sockets of all other processes.
+#[derive(Debug, Clone)]
+pub struct UdpCounterSnapshot {
+    ipv6: bool,
+    last_counters: UdpCounters,
+    first_counters: UdpCounters
+}
+
+impl UdpCounterSnapshot {
+    pub fn new(ipv6: bool) -> Self {
+        let counters = Self::get_counters(ipv6);
+        UdpCounterSnapshot {
+            ipv6,
+            last_counters: counters,
+            first_counters: counters
+        }
+    }
+
+    fn get_counters(ipv6: bool) -> UdpCounters {
+        let path = if ipv6 { "/proc/net/snmp6" } else { "/proc/net/snmp" };
+        match fs::read_to_string(path) {
+            Ok(content) => if ipv6 { Self::parse_snmp6(&content) } else { Self::parse_snmp(&content) },
+            Err(x) => {
+                warn!("Error reading {}: {}", path, x);
+                UdpCounters::default()
+            }
+        }
+    }
+
+    // Every protocol has a line with the names of the counters, followed by a line with the values, e.g. "Udp: InDatagrams NoPorts ..." and "Udp: 10 0 ..."
+    fn parse_snmp(content: &str) -> UdpCounters {
+        let mut values: HashMap<String, u64> = HashMap::new();
+        let lines: Vec<&str> = content.lines().collect();
+        for pair in lines.chunks(2) {
+            let [names, counters] = pair else {
+                continue;
+            };
+            let mut names = names.split_whitespace();
+            let Some(protocol) = names.next().filter(|protocol| *protocol == "Udp:" || *protocol == "Ip:") else {
+                continue;
+            };
+            for (name, value) in names.zip(counters.split_whitespace().skip(1)) {
+                values.insert(format!("{}{}", protocol.trim_end_matches(':'), name), value.parse().unwrap_or(0));
+            }
+        }
+        Self::from_values(&values, "Udp", "Ip")
+    }
+
+    // Every line contains the name and the value of a single counter, e.g. "Udp6InErrors 0"
+    fn parse_snmp6(content: &str) -> UdpCounters {
+        let values: HashMap<String, u64> = content.lines()
+            .filter_map(|line| line.split_once(char::is_whitespace))
+            .map(|(name, value)| (name.to_string(), value.trim().parse().unwrap_or(0)))
+            .collect();
+        Self::from_values(&values, "Udp6", "Ip6")
+    }
+
+    fn from_values(values: &HashMap<String, u64>, udp: &str, ip: &str) -> UdpCounters {
+        let get = |name: String| values.get(&name).copied().unwrap_or(0);
+        UdpCounters {
+            in_errors: get(format!("{}InErrors", udp)),
+            rcvbuf_errors: get(format!("{}RcvbufErrors", udp)),
+            sndbuf_errors: get(format!("{}SndbufErrors", udp)),
+            no_ports: get(format!("{}NoPorts", udp)),
+            in_csum_errors: get(format!("{}InCsumErrors", udp)),
+            reasm_reqds: get(format!("{}ReasmReqds", ip)),
+            reasm_fails: get(format!("{}ReasmFails", ip))
+        }
+    }
+
+    // Counters since the last call
+    pub fn get_relative_counters(&mut self) -> UdpCounters {
+        let counters = Self::get_counters(self.ipv6);
+        let delta = counters.delta(&self.last_counters);
+        self.last_counters = counters;
+        delta
+    }
+
+    // Counters since the creation
+    pub fn get_absolut_counters(&mut self) -> UdpCounters {
+        let counters = Self::get_counters(self.ipv6);
+        self.last_counters = counters;
+        counters.delta(&self.first_counters)
+    }
+}
@@ -0,0 +1,37 @@
+use udperf::{ExchangeFunction, ParameterBuilder, SocketOptionsBuilder, UDPerfMode};
+
+mod common;
+
+#[test]
+fn run_with_builder() -> Result<(), Box<dyn std::error::Error>>{
+    let handle = common::start_udperf_receiver(Some(vec!["--with-control-channel".to_string(), "--port=45001".to_string()]));
+
+    let parameter = ParameterBuilder::new(UDPerfMode::Sender)
+        .ip("127.0.0.1".parse()?)
+        .port(45001)
+        .control_channel(true)
+        .time(4)
+        .interval(1.0)
+        .exchange_function(ExchangeFunction::Mmsg)
+        .mmsg_amount(10)
+        .socket_options(SocketOptionsBuilder::new().socket_buffer_size(425984))
+        .build()?;
+    assert_eq!(parameter.packet_buffer_size, 10);
+
+    let report = udperf::run(parameter)?;
+    assert!(report.total().unwrap().amount_datagrams > 10000);
+    assert_eq!(report.intervals.len(), 4);
+    assert!(report.intervals.iter().all(|interval| interval.amount_datagrams > 0));
+
+    handle.join().unwrap();
+    Ok(())
+}
+
+#[test]
+fn builder_rejects_invalid_parameter() {
+    assert!(ParameterBuilder::new(UDPerfMode::Sender).reverse(true).bidirectional(true).build().is_err());
+    assert!(ParameterBuilder::new(UDPerfMode::Sender).time(5).interval(2.0).build().is_err());
+    assert!(ParameterBuilder::new(UDPerfMode::Sender).datagram_size(70000).build().is_err());
+    // Reverse mode is set by the sender over the control channel
+    assert!(!ParameterBuilder::new(UDPerfMode::Receiver).reverse(true).build().unwrap().reverse);
+}
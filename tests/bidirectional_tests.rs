@@ -0,0 +1,17 @@
+mod common;
+
+#[test]
+fn bidirectional() -> Result<(), Box<dyn std::error::Error>>{
+    let handle = common::start_udperf_receiver(Some(vec!["--with-control-channel".to_string(), "--port=45001".to_string()]));
+
+    // Both hosts send and receive at the same time, the opposite direction uses port 45002
+    let args = vec!["sender", "--bidir", "--port=45001"];
+    let udperf = udperf::udperf::new().set_args(args);
+    let arguments = udperf.parse_parameter().unwrap();
+    if let Some(x) = udperf.exec(arguments) {
+        assert!(x.amount_datagrams > 10000);
+    };
+
+    handle.join().unwrap();
+    Ok(())
+}
@@ -0,0 +1,55 @@
+mod common;
+
+#[test]
+fn control_channel() -> Result<(), Box<dyn std::error::Error>>{
+    let handle = common::start_udperf_receiver(Some(vec!["--with-control-channel".to_string(), "--port=45001".to_string()]));
+
+    let args = vec!["sender", "--with-control-channel", "--port=45001"];
+    let udperf = udperf::udperf::new().set_args(args);
+    let arguments = udperf.parse_parameter().unwrap();
+    if let Some(x) = udperf.exec(arguments) {
+        assert!(x.amount_datagrams > 10000);
+    };
+
+    handle.join().unwrap();
+    Ok(())
+}
+
+#[test]
+fn control_channel_parameter_negotiation() -> Result<(), Box<dyn std::error::Error>>{
+    // Receiver is started with a single thread and takes over the amount of threads from the sender
+    let handle = common::start_udperf_receiver(Some(vec!["--with-control-channel".to_string(), "--port=45101".to_string()]));
+
+    let args = vec!["sender", "--with-control-channel", "--port=45101", "--parallel=2", "--time=5"];
+    let udperf = udperf::udperf::new().set_args(args);
+    let arguments = udperf.parse_parameter().unwrap();
+    if let Some(x) = udperf.exec(arguments) {
+        assert!(x.amount_datagrams > 10000);
+    };
+
+    handle.join().unwrap();
+    Ok(())
+}
+
+#[test]
+fn control_channel_without_control_message_delay() -> Result<(), Box<dyn std::error::Error>>{
+    let handle = common::start_udperf_receiver(Some(vec!["--with-control-channel".to_string(), "--port=45201".to_string()]));
+
+    // The start message of the control channel replaces the INIT message and the waiting time before the first and the LAST datagram
+    let args = vec!["sender", "--with-control-channel", "--port=45201", "--time=3"];
+    let udperf = udperf::udperf::new().set_args(args);
+    let arguments = udperf.parse_parameter().unwrap();
+    let start = std::time::Instant::now();
+    let statistic = udperf.exec(arguments);
+    let elapsed = start.elapsed().as_secs_f64();
+    assert!(statistic.is_some());
+    if let Some(x) = statistic {
+        assert!(x.amount_datagrams > 10000);
+        assert!(x.test_duration >= 3.0 && x.test_duration < 3.2);
+        // Both waiting times of 400ms would add up to 3.8s
+        assert!(elapsed < 3.6, "Measurement took {}s", elapsed);
+    };
+
+    handle.join().unwrap();
+    Ok(())
+}
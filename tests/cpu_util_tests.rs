@@ -0,0 +1,37 @@
+mod common;
+
+#[test]
+fn cpu_util_all_cores() -> Result<(), Box<dyn std::error::Error>>{
+    let handle = common::start_udperf_sender(Some(vec!["--port=45891".to_string(), "--time=5".to_string()]));
+
+    let args = vec!["receiver", "--port=45891"];
+    let udperf = udperf::udperf::new().set_args(args);
+    let arguments = udperf.parse_parameter().unwrap();
+    if let Some(x) = udperf.exec(arguments) {
+        assert!(x.amount_datagrams > 10000);
+        assert_eq!(x.cores, "all");
+        // The kernel spends time in the receive path of the sockets
+        assert!(x.cores_system_time + x.cores_softirq_time > 0.0);
+    };
+
+    handle.join().unwrap();
+    Ok(())
+}
+
+#[test]
+fn cpu_util_pinned_cores() -> Result<(), Box<dyn std::error::Error>>{
+    let handle = common::start_udperf_sender(Some(vec!["--port=45895".to_string(), "--time=5".to_string()]));
+
+    let args = vec!["receiver", "--with-core-affinity", "--port=45895"];
+    let udperf = udperf::udperf::new().set_args(args);
+    let arguments = udperf.parse_parameter().unwrap();
+    if let Some(x) = udperf.exec(arguments) {
+        assert!(x.amount_datagrams > 10000);
+        // Only the core of the single receiver thread is measured
+        assert!(x.cores.parse::<usize>().is_ok());
+        assert!(x.cores_user_time + x.cores_system_time + x.cores_softirq_time > 0.0);
+    };
+
+    handle.join().unwrap();
+    Ok(())
+}
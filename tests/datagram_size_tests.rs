@@ -0,0 +1,37 @@
+mod common;
+
+#[test]
+fn datagram_size_imix_mmsg() -> Result<(), Box<dyn std::error::Error>>{
+    let handle = common::start_udperf_sender(Some(vec!["--datagram-size-distribution=imix".to_string(), "--exchange-function=mmsg".to_string(), "--port=45821".to_string(), "--time=5".to_string()]));
+
+    let args = vec!["receiver", "--exchange-function=mmsg", "--port=45821"];
+    let udperf = udperf::udperf::new().set_args(args);
+    let arguments = udperf.parse_parameter().unwrap();
+    if let Some(x) = udperf.exec(arguments) {
+        assert!(x.amount_datagrams > 10000);
+        // The mean size of IMIX is far below the maximum size of 1472 bytes
+        let mean_size = x.amount_data_bytes as u64 / x.amount_datagrams;
+        assert!((36..1000).contains(&mean_size));
+    };
+
+    handle.join().unwrap();
+    Ok(())
+}
+
+#[test]
+fn datagram_size_uniform_normal() -> Result<(), Box<dyn std::error::Error>>{
+    let handle = common::start_udperf_sender(Some(vec!["--datagram-size-distribution=uniform".to_string(), "--datagram-size-min=200".to_string(), "--datagram-size=1000".to_string(), "--exchange-function=normal".to_string(), "--port=45831".to_string(), "--time=5".to_string()]));
+
+    let args = vec!["receiver", "--exchange-function=normal", "--port=45831"];
+    let udperf = udperf::udperf::new().set_args(args);
+    let arguments = udperf.parse_parameter().unwrap();
+    if let Some(x) = udperf.exec(arguments) {
+        assert!(x.amount_datagrams > 10000);
+        let mean_size = x.amount_data_bytes as u64 / x.amount_datagrams;
+        // The mean size lies strictly between the minimum and maximum size, if more than one size was sent
+        assert!(mean_size > 200 && mean_size < 1000);
+    };
+
+    handle.join().unwrap();
+    Ok(())
+}
@@ -0,0 +1,92 @@
+mod common;
+
+#[test]
+fn one_way_delay_and_jitter() -> Result<(), Box<dyn std::error::Error>>{
+    let handle = common::start_udperf_receiver(Some(vec!["--with-control-channel".to_string(), "--port=45001".to_string()]));
+
+    // In reverse mode the local host is the receiver, which measures the one-way delay and jitter
+    let args = vec!["sender", "--reverse", "--with-one-way-delay", "--port=45001", "--time=5"];
+    let udperf = udperf::udperf::new().set_args(args);
+    let arguments = udperf.parse_parameter().unwrap();
+    if let Some(x) = udperf.exec(arguments) {
+        assert!(x.amount_datagrams > 10000);
+        assert!(x.jitter > 0.0);
+        assert!(x.owd_p50 > 0.0);
+        assert!(x.owd_p50 <= x.owd_p99 && x.owd_p99 <= x.owd_p999 && x.owd_p999 <= x.owd_max);
+    };
+
+    handle.join().unwrap();
+    Ok(())
+}
+
+#[test]
+fn jitter_parallel_mmsg() -> Result<(), Box<dyn std::error::Error>>{
+    let handle = common::start_udperf_sender(Some(vec!["--parallel=2".to_string(), "--exchange-function=mmsg".to_string(), "--with-one-way-delay".to_string(), "--port=45101".to_string(), "--time=5".to_string()]));
+
+    let args = vec!["receiver", "--parallel=2", "--exchange-function=mmsg", "--port=45101"];
+    let udperf = udperf::udperf::new().set_args(args);
+    let arguments = udperf.parse_parameter().unwrap();
+    if let Some(x) = udperf.exec(arguments) {
+        assert!(x.amount_datagrams > 10000);
+        assert!(x.jitter > 0.0);
+        assert!(x.owd_max > 0.0);
+        // Every thread receives its own flow
+        assert_eq!(x.flow_owd.split(';').count(), 2);
+    };
+
+    handle.join().unwrap();
+    Ok(())
+}
+
+#[test]
+fn rx_timestamps_io_uring() -> Result<(), Box<dyn std::error::Error>>{
+    let handle = common::start_udperf_sender(Some(vec!["--io-model=io-uring".to_string(), "--port=45201".to_string(), "--time=5".to_string()]));
+
+    let args = vec!["receiver", "--io-model=io-uring", "--with-rx-timestamps", "--port=45201"];
+    let udperf = udperf::udperf::new().set_args(args);
+    let arguments = udperf.parse_parameter().unwrap();
+    if let Some(x) = udperf.exec(arguments) {
+        assert!(x.amount_datagrams > 10000);
+        assert!(x.rx_latency_p50 > 0.0);
+        assert!(x.rx_latency_p50 <= x.rx_latency_p99 && x.rx_latency_p99 <= x.rx_latency_max);
+    };
+
+    handle.join().unwrap();
+    Ok(())
+}
+
+#[test]
+fn tx_timestamps_mmsg() -> Result<(), Box<dyn std::error::Error>>{
+    let handle = common::start_udperf_receiver(Some(vec!["--exchange-function=mmsg".to_string(), "--port=45301".to_string()]));
+
+    let args = vec!["sender", "--exchange-function=mmsg", "--with-mmsg-amount=10", "--with-tx-timestamps", "--port=45301", "--time=5"];
+    let udperf = udperf::udperf::new().set_args(args);
+    let arguments = udperf.parse_parameter().unwrap();
+    if let Some(x) = udperf.exec(arguments) {
+        assert!(x.amount_datagrams > 10000);
+        assert!(x.tx_sched_delay_p50 > 0.0);
+        assert!(x.tx_driver_delay_p50 > 0.0);
+        assert!(x.tx_driver_delay_p50 <= x.tx_driver_delay_max);
+    };
+
+    handle.join().unwrap();
+    Ok(())
+}
+
+#[test]
+fn tx_timestamps_io_uring() -> Result<(), Box<dyn std::error::Error>>{
+    let handle = common::start_udperf_receiver(Some(vec!["--port=45401".to_string()]));
+
+    let args = vec!["sender", "--io-model=io-uring", "--with-tx-timestamps", "--port=45401", "--time=5"];
+    let udperf = udperf::udperf::new().set_args(args);
+    let arguments = udperf.parse_parameter().unwrap();
+    if let Some(x) = udperf.exec(arguments) {
+        assert!(x.amount_datagrams > 10000);
+        assert!(x.tx_sched_delay_p50 > 0.0);
+        assert!(x.tx_driver_delay_p50 > 0.0);
+        assert!(x.tx_driver_delay_p50 <= x.tx_driver_delay_max);
+    };
+
+    handle.join().unwrap();
+    Ok(())
+}
@@ -0,0 +1,52 @@
+mod common;
+
+#[test]
+fn epoll_multiple_sockets_per_thread() -> Result<(), Box<dyn std::error::Error>>{
+    let handle = common::start_udperf_sender(Some(vec!["--parallel=2".to_string(), "--sockets-per-thread=4".to_string(), "--io-model=epoll".to_string(), "--port=45401".to_string(), "--time=5".to_string()]));
+
+    let args = vec!["receiver", "--parallel=2", "--sockets-per-thread=4", "--io-model=epoll", "--port=45401"];
+    let udperf = udperf::udperf::new().set_args(args);
+    let arguments = udperf.parse_parameter().unwrap();
+    if let Some(x) = udperf.exec(arguments) {
+        assert!(x.amount_datagrams > 10000);
+        // Every socket has its own sequence of packet ids
+        assert_eq!(x.amount_reordered_datagrams, 0);
+    };
+
+    handle.join().unwrap();
+    Ok(())
+}
+
+#[test]
+fn epoll_edge_triggered_exclusive_shared_socket() -> Result<(), Box<dyn std::error::Error>>{
+    let handle = common::start_udperf_receiver(Some(vec!["--parallel=2".to_string(), "--io-model=epoll".to_string(), "--epoll-edge-triggered".to_string(), "--epoll-exclusive".to_string(), "--multiplex-port-receiver=sharing".to_string(), "--port=45501".to_string()]));
+
+    let args = vec!["sender", "--parallel=2", "--io-model=epoll", "--epoll-edge-triggered", "--multiplex-port-receiver=sharing", "--port=45501", "--time=5"];
+    let udperf = udperf::udperf::new().set_args(args);
+    let arguments = udperf.parse_parameter().unwrap();
+    if let Some(x) = udperf.exec(arguments) {
+        assert!(x.amount_datagrams > 10000);
+    };
+
+    handle.join().unwrap();
+    Ok(())
+}
+
+#[test]
+fn epoll_exclusive_ping_pong() -> Result<(), Box<dyn std::error::Error>>{
+    let handle = common::start_udperf_receiver(Some(vec!["--ping-pong".to_string(), "--port=45601".to_string()]));
+
+    // A full send buffer switches the socket registered with EPOLLEXCLUSIVE from EPOLLIN to EPOLLOUT and back
+    let args = vec!["sender", "--ping-pong", "--io-model=epoll", "--epoll-exclusive", "--port=45601", "--time=5"];
+    let udperf = udperf::udperf::new().set_args(args);
+    let arguments = udperf.parse_parameter().unwrap();
+    let statistic = udperf.exec(arguments);
+    assert!(statistic.is_some());
+    if let Some(x) = statistic {
+        assert!(x.amount_datagrams > 1000);
+        assert!(x.rtt_max > 0.0);
+    };
+
+    handle.join().unwrap();
+    Ok(())
+}
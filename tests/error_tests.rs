@@ -0,0 +1,27 @@
+use assert_cmd::Command;
+use udperf::{ParameterBuilder, UDPerfMode, UdperfError};
+
+#[test]
+fn run_returns_errno_without_receiver() -> Result<(), Box<dyn std::error::Error>>{
+    // Nobody listens on the port, so the ICMP port unreachable results in ECONNREFUSED on the connected socket
+    let parameter = ParameterBuilder::new(UDPerfMode::Sender)
+        .ip("127.0.0.1".parse()?)
+        .port(45901)
+        .time(2)
+        .build()?;
+
+    match udperf::run(parameter) {
+        Err(x) => {
+            assert!(matches!(x, UdperfError::Os { .. }));
+            assert_eq!(x.errno(), Some(libc::ECONNREFUSED));
+        },
+        Ok(_) => panic!("Measurement without receiver succeeded"),
+    }
+    Ok(())
+}
+
+#[test]
+fn failed_measurement_exit_code() {
+    let mut cmd = Command::cargo_bin("udperf").unwrap();
+    cmd.args(["sender", "--port=45902", "--time=2"]).assert().failure().code(1);
+}
@@ -0,0 +1,43 @@
+mod common;
+
+#[test]
+fn flows_connection_id_interleaved_mmsg() -> Result<(), Box<dyn std::error::Error>>{
+    let handle = common::start_udperf_sender(Some(vec!["--flows-per-thread=16".to_string(), "--exchange-function=mmsg".to_string(), "--with-mmsg-amount=20".to_string(), "--port=45601".to_string(), "--time=5".to_string()]));
+
+    let args = vec!["receiver", "--exchange-function=mmsg", "--with-mmsg-amount=20", "--port=45601"];
+    let udperf = udperf::udperf::new().set_args(args);
+    let arguments = udperf.parse_parameter().unwrap();
+    if let Some(x) = udperf.exec(arguments) {
+        assert!(x.amount_datagrams > 10000);
+        assert_eq!(x.amount_flows, 16);
+        // Every flow has its own sequence of packet ids
+        assert_eq!(x.amount_reordered_datagrams, 0);
+        assert!(x.flow_datagrams_min > 0);
+        // The loss of the worst flow is at least the loss of all flows together (lost datagrams of all sent datagrams)
+        let packet_loss = x.amount_omitted_datagrams as f64 / (x.amount_datagrams as i64 + x.amount_omitted_datagrams) as f64 * 100.0;
+        assert!(x.flow_packet_loss_max + 0.01 >= packet_loss && x.flow_packet_loss_max < 100.0);
+    };
+
+    handle.join().unwrap();
+    Ok(())
+}
+
+#[test]
+fn flows_four_tuple_parallel() -> Result<(), Box<dyn std::error::Error>>{
+    let handle = common::start_udperf_sender(Some(vec!["--parallel=2".to_string(), "--flows-per-thread=8".to_string(), "--flow-mode=four-tuple".to_string(), "--port=45701".to_string(), "--time=5".to_string()]));
+
+    let args = vec!["receiver", "--parallel=2", "--port=45701"];
+    let udperf = udperf::udperf::new().set_args(args);
+    let arguments = udperf.parse_parameter().unwrap();
+    if let Some(x) = udperf.exec(arguments) {
+        assert!(x.amount_datagrams > 10000);
+        assert_eq!(x.amount_flows, 16);
+        assert!(x.flow_datagrams_min > 0);
+        assert!(x.flow_datagrams_min * 16 <= x.amount_datagrams && x.amount_datagrams <= x.flow_datagrams_max * 16);
+        let packet_loss = x.amount_omitted_datagrams as f64 / (x.amount_datagrams as i64 + x.amount_omitted_datagrams) as f64 * 100.0;
+        assert!(x.flow_packet_loss_max + 0.01 >= packet_loss && x.flow_packet_loss_max < 100.0);
+    };
+
+    handle.join().unwrap();
+    Ok(())
+}
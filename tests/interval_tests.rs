@@ -0,0 +1,59 @@
+use std::io::{BufRead, BufReader};
+use std::process::{Command, Stdio};
+use assert_cmd::prelude::*;
+
+mod common;
+
+#[test]
+fn interval_printed_during_measurement() -> Result<(), Box<dyn std::error::Error>>{
+    let handle = common::start_udperf_receiver(Some(vec!["--parallel=2".to_string(), "--port=45001".to_string()]));
+
+    let mut sender = Command::cargo_bin("udperf")?
+        .args(["sender", "--parallel=2", "--port=45001", "--time=5", "--interval=1", "--output-format=json"])
+        .stdout(Stdio::piped())
+        .spawn()?;
+    let mut stdout = BufReader::new(sender.stdout.take().unwrap());
+
+    // The first interval has to be printed as JSON line while the sender is still running
+    let mut line = String::new();
+    stdout.read_line(&mut line)?;
+    assert!(sender.try_wait()?.is_none());
+    let interval: serde_json::Value = serde_json::from_str(&line)?;
+    assert_eq!(interval["interval_id"], 1);
+    assert!(interval["amount_datagrams"].as_u64().unwrap() > 0);
+
+    // The intervals of both threads are merged, followed by the summary. Depending on the timing, the last interval can be skipped.
+    let lines: Vec<String> = stdout.lines().collect::<Result<_, _>>()?;
+    assert!(lines.len() >= 2);
+    let summary: serde_json::Value = serde_json::from_str(lines.last().unwrap())?;
+    assert!(summary["amount_datagrams"].as_u64().unwrap() > interval["amount_datagrams"].as_u64().unwrap());
+    assert!(sender.wait()?.success());
+
+    handle.join().unwrap();
+    Ok(())
+}
+
+#[test]
+fn interval_and_thread_cpu_utilization() -> Result<(), Box<dyn std::error::Error>>{
+    let handle = common::start_udperf_receiver(Some(vec!["--parallel=2".to_string(), "--port=45881".to_string()]));
+
+    let sender = Command::cargo_bin("udperf")?
+        .args(["sender", "--parallel=2", "--port=45881", "--time=3", "--interval=1", "--output-format=json", "--with-thread-statistics"])
+        .output()?;
+    assert!(sender.status.success());
+
+    // Every interval, thread and the summary carry their own CPU utilization
+    let statistics: Vec<serde_json::Value> = String::from_utf8(sender.stdout)?.lines().map(serde_json::from_str).collect::<Result<_, _>>()?;
+    // At least one interval
+    assert!(statistics.len() > 2 + 1);
+    for statistic in statistics.iter() {
+        assert!(statistic["cpu_total_time"].as_f64().unwrap() > 0.0);
+    }
+    // The intervals are followed by the statistics of both threads and the summary
+    let amount_statistics = statistics.len();
+    assert!(statistics[amount_statistics - 3]["thread_id"].is_u64() && statistics[amount_statistics - 2]["thread_id"].is_u64());
+    assert!(statistics[amount_statistics - 1]["thread_id"].is_null());
+
+    handle.join().unwrap();
+    Ok(())
+}
@@ -0,0 +1,32 @@
+mod common;
+
+#[test]
+fn ipv6_loopback() -> Result<(), Box<dyn std::error::Error>>{
+    let handle = common::start_udperf_receiver(Some(vec!["--ip=::1".to_string(), "--port=45001".to_string()]));
+
+    let args = vec!["sender", "--ip=::1", "--port=45001", "--with-hop-limit=8", "--with-flow-label=4660"];
+    let udperf = udperf::udperf::new().set_args(args);
+    let arguments = udperf.parse_parameter().unwrap();
+    if let Some(x) = udperf.exec(arguments) {
+        assert!(x.amount_datagrams > 10000);
+    };
+
+    handle.join().unwrap();
+    Ok(())
+}
+
+#[test]
+fn ipv6_dual_stack() -> Result<(), Box<dyn std::error::Error>>{
+    // Receiver listens on the IPv6 wildcard address and accepts the IPv4 sender with an IPv4-mapped address
+    let handle = common::start_udperf_receiver(Some(vec!["--ip=::".to_string(), "--port=45101".to_string()]));
+
+    let args = vec!["sender", "--ip=127.0.0.1", "--port=45101"];
+    let udperf = udperf::udperf::new().set_args(args);
+    let arguments = udperf.parse_parameter().unwrap();
+    if let Some(x) = udperf.exec(arguments) {
+        assert!(x.amount_datagrams > 10000);
+    };
+
+    handle.join().unwrap();
+    Ok(())
+}
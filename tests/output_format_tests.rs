@@ -0,0 +1,77 @@
+use std::process::Command;
+use assert_cmd::prelude::*;
+
+mod common;
+
+#[test]
+fn output_format_json_lines() -> Result<(), Box<dyn std::error::Error>>{
+    let handle = common::start_udperf_receiver(Some(vec!["--port=45991".to_string()]));
+
+    let sender = Command::cargo_bin("udperf")?
+        .args(["sender", "--port=45991", "--time=3", "--interval=1", "--output-format=json-lines", "--label-test=json-lines", "--repetition-id=3"])
+        .output()?;
+    assert!(sender.status.success());
+
+    // Every interval and the summary are tagged with the labels of the benchmark automation
+    let records: Vec<serde_json::Value> = String::from_utf8(sender.stdout)?.lines().map(serde_json::from_str).collect::<Result<_, _>>()?;
+    // At least one interval and exactly one summary
+    assert!(records.len() > 1);
+    for record in records.iter() {
+        assert_eq!(record["label_test"], "json-lines");
+        assert_eq!(record["repetition_id"], 3);
+        assert_eq!(record["mode"], "sender");
+        assert!(record["amount_datagrams"].as_u64().unwrap() > 0);
+    }
+    assert_eq!(records[records.len() - 1]["record"], "summary");
+    assert!(records[..records.len() - 1].iter().all(|record| record["record"] == "interval"));
+
+    handle.join().unwrap();
+    Ok(())
+}
+
+#[test]
+fn output_format_influx() -> Result<(), Box<dyn std::error::Error>>{
+    let handle = common::start_udperf_receiver(Some(vec!["--port=46001".to_string()]));
+
+    let sender = Command::cargo_bin("udperf")?
+        .args(["sender", "--port=46001", "--time=2", "--output-format=influx", "--label-run=influx run"])
+        .output()?;
+    assert!(sender.status.success());
+
+    // Spaces in tags are escaped and integer fields are suffixed with i
+    let stdout = String::from_utf8(sender.stdout)?;
+    let line = stdout.lines().last().unwrap();
+    assert!(line.starts_with("udperf,label_test=udperf-test,label_run=influx\\ run,repetition_id=1,mode=sender,record=summary "));
+    let mut parts = line.rsplitn(3, ' ');
+    assert!(parts.next().unwrap().parse::<u64>()? > 0);
+    assert!(parts.next().unwrap().split(',').any(|field| field.starts_with("amount_datagrams=") && field.ends_with('i')));
+
+    handle.join().unwrap();
+    Ok(())
+}
+
+#[test]
+fn output_format_prometheus() -> Result<(), Box<dyn std::error::Error>>{
+    let output_file_path = std::env::temp_dir().join("udperf-prometheus-test").join("udperf");
+    let _ = std::fs::remove_file(output_file_path.with_extension("prom"));
+    let handle = common::start_udperf_sender(Some(vec!["--port=46011".to_string(), "--time=3".to_string()]));
+
+    let receiver = Command::cargo_bin("udperf")?
+        .args(["receiver", "--port=46011", "--interval=1", "--output-format=prometheus", "--label-test=prometheus"])
+        .arg(format!("--output-file-path={}", output_file_path.display()))
+        .output()?;
+    assert!(receiver.status.success());
+
+    // The latest interval and the summary are kept as separate series in the textfile
+    let exposition = std::fs::read_to_string(output_file_path.with_extension("prom"))?;
+    assert!(exposition.contains("# TYPE udperf_amount_datagrams gauge"));
+    for record in ["interval", "summary"] {
+        let labels = format!("{{label_test=\"prometheus\",label_run=\"run-udperf\",repetition_id=\"1\",mode=\"receiver\",record=\"{}\"}}", record);
+        let sample = exposition.lines().find(|line| line.starts_with(&format!("udperf_amount_datagrams{}", labels))).unwrap();
+        assert!(sample.split(' ').next_back().unwrap().parse::<f64>()? > 0.0);
+    }
+    assert!(!output_file_path.with_extension("prom.tmp").exists());
+
+    handle.join().unwrap();
+    Ok(())
+}
@@ -0,0 +1,63 @@
+mod common;
+
+#[test]
+fn userspace_pacing_bandwidth_parallel() -> Result<(), Box<dyn std::error::Error>>{
+    let handle = common::start_udperf_sender(Some(vec!["--parallel=2".to_string(), "--bandwidth=100".to_string(), "--with-userspace-pacing".to_string(), "--port=45801".to_string(), "--time=5".to_string()]));
+
+    let args = vec!["receiver", "--parallel=2", "--port=45801"];
+    let udperf = udperf::udperf::new().set_args(args);
+    let arguments = udperf.parse_parameter().unwrap();
+    if let Some(x) = udperf.exec(arguments) {
+        // 100 Mbit/s shared by both threads with 1472 byte datagrams for 5 seconds
+        assert!(x.amount_datagrams > 0);
+        assert!(x.amount_datagrams < 100_000_000 / 8 / 1472 * 5 * 11 / 10);
+    };
+
+    handle.join().unwrap();
+    Ok(())
+}
+
+#[test]
+fn userspace_pacing_pps_mmsg() -> Result<(), Box<dyn std::error::Error>>{
+    let handle = common::start_udperf_sender(Some(vec!["--bandwidth-pps=10000".to_string(), "--exchange-function=mmsg".to_string(), "--with-mmsg-amount=10".to_string(), "--port=45901".to_string(), "--time=5".to_string()]));
+
+    let args = vec!["receiver", "--exchange-function=mmsg", "--with-mmsg-amount=10", "--port=45901"];
+    let udperf = udperf::udperf::new().set_args(args);
+    let arguments = udperf.parse_parameter().unwrap();
+    if let Some(x) = udperf.exec(arguments) {
+        assert!(x.amount_datagrams > 0);
+        assert!(x.amount_datagrams <= 55000);
+    };
+
+    handle.join().unwrap();
+    Ok(())
+}
+
+#[test]
+fn traffic_pattern_ramp_offered_rate() -> Result<(), Box<dyn std::error::Error>>{
+    let ramp_file = std::env::temp_dir().join("udperf-ramp-test.txt");
+    std::fs::write(&ramp_file, "# start bandwidth\n0 50\n2 100\n")?;
+    let handle = common::start_udperf_receiver(Some(vec!["--port=45811".to_string()]));
+
+    let ramp_file_arg = format!("--ramp-file={}", ramp_file.display());
+    let args = vec!["sender", "--traffic-pattern=ramp", ramp_file_arg.as_str(), "--port=45811", "--time=4", "--interval=1"];
+    let udperf = udperf::udperf::new().set_args(args);
+    let arguments = udperf.parse_parameter().unwrap();
+    if let Some(x) = udperf.exec(arguments) {
+        // 2 seconds with 50 Mbit/s and 2 seconds with 100 Mbit/s
+        assert!(x.amount_offered_data_bytes > 0);
+        assert!(x.amount_offered_data_bytes <= 300_000_000 / 8 * 11 / 10);
+        assert!(x.amount_datagrams < 300_000_000 / 8 / 1472 * 11 / 10);
+    };
+
+    handle.join().unwrap();
+    Ok(())
+}
+
+#[test]
+fn traffic_pattern_poisson_requires_bandwidth() {
+    let result = udperf::ParameterBuilder::new(udperf::UDPerfMode::Sender)
+        .traffic_pattern(udperf::TrafficPattern::Poisson, 100, 100)
+        .build();
+    assert!(matches!(result, Err(udperf::UdperfError::InvalidParameter(_))));
+}
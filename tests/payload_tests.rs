@@ -0,0 +1,34 @@
+mod common;
+
+#[test]
+fn payload_random_verification_control_channel() -> Result<(), Box<dyn std::error::Error>>{
+    let handle = common::start_udperf_sender(Some(vec!["--payload-mode=random".to_string(), "--payload-seed=42".to_string(), "--with-control-channel".to_string(), "--port=45841".to_string(), "--time=5".to_string()]));
+
+    // The receiver takes over the payload parameters of the sender over the control channel
+    let args = vec!["receiver", "--with-payload-verification", "--with-control-channel", "--port=45841"];
+    let udperf = udperf::udperf::new().set_args(args);
+    let arguments = udperf.parse_parameter().unwrap();
+    if let Some(x) = udperf.exec(arguments) {
+        assert!(x.amount_datagrams > 10000);
+        assert_eq!(x.amount_corrupted_datagrams, 0);
+    };
+
+    handle.join().unwrap();
+    Ok(())
+}
+
+#[test]
+fn payload_verification_mismatch() -> Result<(), Box<dyn std::error::Error>>{
+    let handle = common::start_udperf_sender(Some(vec!["--payload-mode=zeros".to_string(), "--exchange-function=mmsg".to_string(), "--port=45851".to_string(), "--time=5".to_string()]));
+
+    let args = vec!["receiver", "--payload-mode=pattern", "--with-payload-verification", "--exchange-function=mmsg", "--port=45851"];
+    let udperf = udperf::udperf::new().set_args(args);
+    let arguments = udperf.parse_parameter().unwrap();
+    if let Some(x) = udperf.exec(arguments) {
+        assert!(x.amount_datagrams > 10000);
+        assert_eq!(x.amount_corrupted_datagrams, x.amount_datagrams);
+    };
+
+    handle.join().unwrap();
+    Ok(())
+}
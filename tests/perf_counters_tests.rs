@@ -0,0 +1,35 @@
+mod common;
+
+#[test]
+fn perf_counters_receiver() -> Result<(), Box<dyn std::error::Error>>{
+    let handle = common::start_udperf_sender(Some(vec!["--exchange-function=mmsg".to_string(), "--port=45951".to_string(), "--time=3".to_string()]));
+
+    let args = vec!["receiver", "--with-perf-counters", "--exchange-function=mmsg", "--port=45951"];
+    let udperf = udperf::udperf::new().set_args(args);
+    let arguments = udperf.parse_parameter().unwrap();
+    if let Some(x) = udperf.exec(arguments) {
+        assert!(x.amount_datagrams > 1000);
+        // The counters are zero, if perf_event_open isn't available (e.g. in a container)
+        assert_eq!(x.perf_task_clock == 0, x.task_clock_per_datagram == 0.0);
+        assert_eq!(x.perf_cycles == 0, x.cycles_per_datagram == 0.0);
+    };
+
+    handle.join().unwrap();
+    Ok(())
+}
+
+#[test]
+fn perf_counters_sender_io_uring() -> Result<(), Box<dyn std::error::Error>>{
+    let handle = common::start_udperf_receiver(Some(vec!["--port=45961".to_string()]));
+
+    let args = vec!["sender", "--with-perf-counters", "--io-model=io-uring", "--port=45961", "--time=3"];
+    let udperf = udperf::udperf::new().set_args(args);
+    let arguments = udperf.parse_parameter().unwrap();
+    if let Some(x) = udperf.exec(arguments) {
+        assert!(x.amount_datagrams > 1000);
+        assert_eq!(x.perf_task_clock == 0, x.task_clock_per_datagram == 0.0);
+    };
+
+    handle.join().unwrap();
+    Ok(())
+}
@@ -0,0 +1,35 @@
+mod common;
+
+#[test]
+fn ping_pong() -> Result<(), Box<dyn std::error::Error>>{
+    let handle = common::start_udperf_receiver(Some(vec!["--with-control-channel".to_string(), "--port=45001".to_string()]));
+
+    // The ping-pong mode is taken over by the receiver over the control channel
+    let args = vec!["sender", "--with-control-channel", "--ping-pong", "--port=45001", "--time=5"];
+    let udperf = udperf::udperf::new().set_args(args);
+    let arguments = udperf.parse_parameter().unwrap();
+    if let Some(x) = udperf.exec(arguments) {
+        assert!(x.amount_datagrams > 1000);
+        assert!(x.rtt_p50 > 0.0);
+        assert!(x.rtt_p50 <= x.rtt_p99 && x.rtt_p99 <= x.rtt_p999 && x.rtt_p999 <= x.rtt_max);
+    };
+
+    handle.join().unwrap();
+    Ok(())
+}
+
+#[test]
+fn ping_pong_uring_mmsg() -> Result<(), Box<dyn std::error::Error>>{
+    let handle = common::start_udperf_receiver(Some(vec!["--ping-pong".to_string(), "--io-model=io-uring".to_string(), "--port=45101".to_string()]));
+
+    let args = vec!["sender", "--ping-pong", "--exchange-function=mmsg", "--with-mmsg-amount=4", "--io-model=poll", "--port=45101", "--time=5"];
+    let udperf = udperf::udperf::new().set_args(args);
+    let arguments = udperf.parse_parameter().unwrap();
+    if let Some(x) = udperf.exec(arguments) {
+        assert!(x.amount_datagrams > 1000);
+        assert!(x.rtt_max > 0.0);
+    };
+
+    handle.join().unwrap();
+    Ok(())
+}
@@ -0,0 +1,32 @@
+mod common;
+
+#[test]
+fn reverse() -> Result<(), Box<dyn std::error::Error>>{
+    let handle = common::start_udperf_receiver(Some(vec!["--with-control-channel".to_string(), "--port=45001".to_string()]));
+
+    // The sender host receives the datagrams sent by the receiver host
+    let args = vec!["sender", "--reverse", "--port=45001"];
+    let udperf = udperf::udperf::new().set_args(args);
+    let arguments = udperf.parse_parameter().unwrap();
+    if let Some(x) = udperf.exec(arguments) {
+        assert!(x.amount_datagrams > 10000);
+    };
+
+    handle.join().unwrap();
+    Ok(())
+}
+
+#[test]
+fn reverse_ipv6_parallel() -> Result<(), Box<dyn std::error::Error>>{
+    let handle = common::start_udperf_receiver(Some(vec!["--ip=::".to_string(), "--with-control-channel".to_string(), "--port=45101".to_string()]));
+
+    let args = vec!["sender", "--reverse", "--ip=::1", "--port=45101", "--parallel=2", "--time=5"];
+    let udperf = udperf::udperf::new().set_args(args);
+    let arguments = udperf.parse_parameter().unwrap();
+    if let Some(x) = udperf.exec(arguments) {
+        assert!(x.amount_datagrams > 10000);
+    };
+
+    handle.join().unwrap();
+    Ok(())
+}
@@ -0,0 +1,36 @@
+mod common;
+
+#[test]
+fn rusage_receiver() -> Result<(), Box<dyn std::error::Error>>{
+    let handle = common::start_udperf_sender(Some(vec!["--port=45971".to_string(), "--time=3".to_string()]));
+
+    let args = vec!["receiver", "--port=45971"];
+    let udperf = udperf::udperf::new().set_args(args);
+    let arguments = udperf.parse_parameter().unwrap();
+    if let Some(x) = udperf.exec(arguments) {
+        assert!(x.amount_datagrams > 1000);
+        assert!(x.voluntary_context_switches + x.involuntary_context_switches > 0);
+        assert!(x.minor_page_faults > 0);
+        assert!(x.max_rss > 0);
+    };
+
+    handle.join().unwrap();
+    Ok(())
+}
+
+#[test]
+fn rusage_sender_multiple_threads() -> Result<(), Box<dyn std::error::Error>>{
+    let handle = common::start_udperf_receiver(Some(vec!["--parallel=2".to_string(), "--port=45981".to_string()]));
+
+    let args = vec!["sender", "--parallel=2", "--port=45981", "--time=3"];
+    let udperf = udperf::udperf::new().set_args(args);
+    let arguments = udperf.parse_parameter().unwrap();
+    if let Some(x) = udperf.exec(arguments) {
+        assert!(x.amount_datagrams > 1000);
+        assert!(x.voluntary_context_switches + x.involuntary_context_switches > 0);
+        assert!(x.max_rss > 0);
+    };
+
+    handle.join().unwrap();
+    Ok(())
+}
@@ -0,0 +1,36 @@
+mod common;
+
+#[test]
+fn socket_drops_recvmsg() -> Result<(), Box<dyn std::error::Error>>{
+    let handle = common::start_udperf_sender(Some(vec!["--exchange-function=mmsg".to_string(), "--port=45931".to_string(), "--time=5".to_string()]));
+
+    // The tiny receive buffer overflows, which is reported per socket by SO_RXQ_OVFL and SK_MEMINFO
+    let args = vec!["receiver", "--with-socket-drops", "--with-socket-buffer=0.01", "--port=45931"];
+    let udperf = udperf::udperf::new().set_args(args);
+    let arguments = udperf.parse_parameter().unwrap();
+    if let Some(x) = udperf.exec(arguments) {
+        assert!(x.amount_datagrams > 1000);
+        assert!(x.amount_socket_drops > 0);
+        assert!(x.socket_rmem_peak > 0);
+    };
+
+    handle.join().unwrap();
+    Ok(())
+}
+
+#[test]
+fn socket_drops_io_uring() -> Result<(), Box<dyn std::error::Error>>{
+    let handle = common::start_udperf_sender(Some(vec!["--exchange-function=mmsg".to_string(), "--port=45941".to_string(), "--time=5".to_string()]));
+
+    let args = vec!["receiver", "--io-model=io-uring", "--with-socket-drops", "--with-socket-buffer=0.01", "--port=45941"];
+    let udperf = udperf::udperf::new().set_args(args);
+    let arguments = udperf.parse_parameter().unwrap();
+    if let Some(x) = udperf.exec(arguments) {
+        assert!(x.amount_datagrams > 1000);
+        assert!(x.amount_socket_drops > 0);
+        assert!(x.socket_rmem_peak > 0);
+    };
+
+    handle.join().unwrap();
+    Ok(())
+}
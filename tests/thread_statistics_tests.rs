@@ -0,0 +1,38 @@
+mod common;
+
+#[test]
+fn thread_statistics_fair() -> Result<(), Box<dyn std::error::Error>>{
+    let handle = common::start_udperf_sender(Some(vec!["--parallel=2".to_string(), "--port=45861".to_string(), "--time=5".to_string()]));
+
+    let args = vec!["receiver", "--parallel=2", "--with-thread-statistics", "--port=45861"];
+    let udperf = udperf::udperf::new().set_args(args);
+    let arguments = udperf.parse_parameter().unwrap();
+    if let Some(x) = udperf.exec(arguments) {
+        assert!(x.amount_datagrams > 10000);
+        assert!(x.thread_id.is_none());
+        assert!(x.thread_fairness_index > 0.8);
+        assert!(x.thread_data_rate_min > 0.0 && x.thread_data_rate_min <= x.thread_data_rate_max);
+    };
+
+    handle.join().unwrap();
+    Ok(())
+}
+
+#[test]
+fn thread_statistics_sharding() -> Result<(), Box<dyn std::error::Error>>{
+    let handle = common::start_udperf_sender(Some(vec!["--parallel=2".to_string(), "--multiplex-port-receiver=sharding".to_string(), "--port=45871".to_string(), "--time=5".to_string()]));
+
+    // All flows end up on the socket of the first thread, so the fairness index is 1/n
+    let args = vec!["receiver", "--parallel=2", "--with-thread-statistics", "--port=45871"];
+    let udperf = udperf::udperf::new().set_args(args);
+    let arguments = udperf.parse_parameter().unwrap();
+    if let Some(x) = udperf.exec(arguments) {
+        assert!(x.amount_datagrams > 10000);
+        assert!((x.thread_fairness_index - 0.5).abs() < 0.01);
+        assert_eq!(x.thread_data_rate_min, 0.0);
+        assert!(x.thread_data_rate_max > 0.0);
+    };
+
+    handle.join().unwrap();
+    Ok(())
+}
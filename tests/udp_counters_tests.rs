@@ -0,0 +1,35 @@
+mod common;
+
+#[test]
+fn udp_counters_receive_buffer_full() -> Result<(), Box<dyn std::error::Error>>{
+    let handle = common::start_udperf_sender(Some(vec!["--exchange-function=mmsg".to_string(), "--port=45911".to_string(), "--time=5".to_string()]));
+
+    // The tiny receive buffer overflows, which is counted by the kernel
+    let args = vec!["receiver", "--with-socket-buffer=0.01", "--port=45911"];
+    let udperf = udperf::udperf::new().set_args(args);
+    let arguments = udperf.parse_parameter().unwrap();
+    if let Some(x) = udperf.exec(arguments) {
+        assert!(x.amount_datagrams > 1000);
+        assert!(x.udp_rcvbuf_errors > 0);
+        assert!(x.udp_in_errors >= x.udp_rcvbuf_errors);
+    };
+
+    handle.join().unwrap();
+    Ok(())
+}
+
+#[test]
+fn udp_counters_receive_buffer_full_ipv6() -> Result<(), Box<dyn std::error::Error>>{
+    let handle = common::start_udperf_sender(Some(vec!["--ip=::1".to_string(), "--exchange-function=mmsg".to_string(), "--port=45921".to_string(), "--time=5".to_string()]));
+
+    let args = vec!["receiver", "--ip=::1", "--with-socket-buffer=0.01", "--port=45921"];
+    let udperf = udperf::udperf::new().set_args(args);
+    let arguments = udperf.parse_parameter().unwrap();
+    if let Some(x) = udperf.exec(arguments) {
+        assert!(x.amount_datagrams > 1000);
+        assert!(x.udp_rcvbuf_errors > 0);
+    };
+
+    handle.join().unwrap();
+    Ok(())
+}